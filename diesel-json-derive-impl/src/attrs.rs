@@ -0,0 +1,858 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Attribute, Generics, Lit, LitStr, Path, Token, WhereClause, WherePredicate,
+};
+
+/// The cfg predicates of `backend_cfg`, for all impls (`backend_cfg = "..."`)
+/// or for those of one of the backend features
+/// (`backend_cfg(postgres = "...")`).
+#[derive(Clone, Default)]
+pub(crate) struct BackendCfg {
+    pub(crate) all: Option<syn::Meta>,
+    pub(crate) postgres: Option<syn::Meta>,
+    pub(crate) sqlite: Option<syn::Meta>,
+    pub(crate) mysql: Option<syn::Meta>,
+}
+
+impl BackendCfg {
+    /// Whether `backend_cfg(...)` gates the impls of a backend feature.
+    pub(crate) fn per_backend(&self) -> bool {
+        self.postgres.is_some() || self.sqlite.is_some() || self.mysql.is_some()
+    }
+}
+
+/// The diesel sql type the impls are generated for, taken from the
+/// `#[diesel(sql_type = ...)]` attribute that `AsExpression` and `FromSqlRow`
+/// also read. Defaults to `Jsonb`. `Text` is for databases storing JSON in
+/// plain text columns and is handled like `Json`, `Binary` stores the JSON
+/// bytes without the Postgres JSONB version byte.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum SqlType {
+    #[default]
+    Jsonb,
+    Json,
+    Text,
+    Binary,
+}
+
+impl SqlType {
+    /// The sql type named by the last segment of `path`. Anything else would
+    /// only fail later, in the generated impls or when diesel checks the
+    /// column type, far from the attribute.
+    fn from_path(path: &Path) -> syn::Result<Self> {
+        match path.segments.last() {
+            Some(segment) if segment.ident == "Jsonb" => Ok(SqlType::Jsonb),
+            Some(segment) if segment.ident == "Json" => Ok(SqlType::Json),
+            Some(segment) if segment.ident == "Text" => Ok(SqlType::Text),
+            Some(segment) if segment.ident == "Binary" => Ok(SqlType::Binary),
+            _ => Err(syn::Error::new_spanned(
+                path,
+                "unsupported sql type, expected `Jsonb`, `Json`, `Text` or `Binary`",
+            )),
+        }
+    }
+}
+
+impl ToTokens for SqlType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            SqlType::Jsonb => quote!(Jsonb),
+            SqlType::Json => quote!(Json),
+            SqlType::Text => quote!(Text),
+            SqlType::Binary => quote!(Binary),
+        });
+    }
+}
+
+/// How values are encoded, `#[diesel_json(format = "...")]`. Anything but
+/// JSON needs `#[diesel(sql_type = Binary)]`.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum Format {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Format {
+    /// The value of the `format` attribute.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::MessagePack => "msgpack",
+            Format::Cbor => "cbor",
+        }
+    }
+
+    /// The name used in error messages.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Format::Json => "Json",
+            Format::MessagePack => "MessagePack",
+            Format::Cbor => "CBOR",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Attrs {
+    /// The sql type the impls are generated for, one of `sql_types`.
+    pub(crate) sql_type: SqlType,
+    /// Every sql type given with `#[diesel(sql_type = ...)]` or
+    /// `#[diesel_json(sql_types(...))]`, each gets its own impls. Empty for
+    /// just the default `sql_type`.
+    pub(crate) sql_types: Vec<SqlType>,
+    /// Whether `sql_type` came from a `#[diesel(sql_type = ...)]` attribute.
+    pub(crate) has_sql_type_attr: bool,
+    /// The paths of the `#[diesel(sql_type = ...)]` attributes, which have to
+    /// name diesel's sql types.
+    pub(crate) sql_type_paths: Vec<(SqlType, Path)>,
+    /// The JSONB format version byte written and accepted, `#[diesel_json(version = N)]`.
+    /// `None` for the facade's `JSONB_VERSION`.
+    pub(crate) version: Option<u8>,
+    /// Skip the version check and just strip the first byte,
+    /// `#[diesel_json(accept_any_version)]` or `skip_version_check`.
+    pub(crate) accept_any_version: bool,
+    /// Read values starting with what could start JSON as JSON without a
+    /// version byte, `#[diesel_json(detect_version_byte)]`.
+    pub(crate) detect_version_byte: bool,
+    /// A `fn(u8) -> bool` deciding which version bytes are accepted on read,
+    /// `#[diesel_json(version_check = "path::to::fn")]`.
+    pub(crate) version_check: Option<Path>,
+    /// Read values of a version that isn't accepted as `Default::default()`
+    /// instead of failing, `#[diesel_json(unknown_version = "default")]`.
+    pub(crate) default_on_unknown_version: bool,
+    /// The version of the value's shape, written into the JSON,
+    /// `#[diesel_json(schema_version = N)]`.
+    pub(crate) schema_version: Option<u8>,
+    /// A `fn(u8, serde_json::Value) -> Result<T, String>` reading values of
+    /// older schema versions, `#[diesel_json(migrate = "path::to::fn")]`.
+    pub(crate) migrate: Option<Path>,
+    /// Where the generated code finds diesel-json-derive itself,
+    /// `#[diesel_json(crate_path = ...)]`.
+    pub(crate) crate_path: Path,
+    /// Where the generated code finds diesel, `#[diesel_json(diesel_path = ...)]`.
+    pub(crate) diesel_path: Path,
+    /// Where the generated code finds serde_json,
+    /// `#[diesel_json(serde_json_path = ...)]`.
+    pub(crate) serde_json_path: Path,
+    /// Capacity of the buffer the value is serialized into before writing it,
+    /// `#[diesel_json(size_hint = N)]`.
+    pub(crate) size_hint: Option<usize>,
+    /// Fail `to_sql` for values of more bytes than this,
+    /// `#[diesel_json(max_bytes = N)]`.
+    pub(crate) max_bytes: Option<usize>,
+    /// Fail `to_sql` if the JSON the value serialized to doesn't parse,
+    /// `#[diesel_json(validate_on_write)]`.
+    pub(crate) validate_on_write: bool,
+    /// `#[diesel_json(format = "...")]`.
+    pub(crate) format: Format,
+    /// Whether `format` came from the `DIESEL_JSONB_DEFAULT_FORMAT`
+    /// environment variable rather than the attribute.
+    pub(crate) format_from_env: bool,
+    /// Whether a `format` was given, which the environment variable never
+    /// overrides.
+    pub(crate) has_format_attr: bool,
+    /// Called with the deserialized value, rejecting it if it returns an
+    /// error, `#[diesel_json(validate = "path::to::fn")]`.
+    pub(crate) validate: Option<Path>,
+    /// Called with the value before it is written, rejecting it if it returns
+    /// an error, `#[diesel_json(pre_serialize = "path::to::fn")]`.
+    pub(crate) pre_serialize: Option<Path>,
+    /// Parse with `serde_json::from_reader` instead of `from_slice`,
+    /// `#[diesel_json(streaming)]` or `#[diesel_json(reader)]`.
+    pub(crate) streaming: bool,
+    /// Read with an interner for the `Interned` strings of the value,
+    /// `#[diesel_json(intern_strings)]`.
+    pub(crate) intern_strings: bool,
+    /// Write indented JSON, `#[diesel_json(pretty)]`.
+    pub(crate) pretty: bool,
+    /// A function returning the `serde_json::ser::Formatter` to write with,
+    /// `#[diesel_json(formatter = "path::to::fn")]`.
+    pub(crate) formatter: Option<Path>,
+    /// Write the keys of objects in order, `#[diesel_json(sort_keys)]`.
+    pub(crate) sort_keys: bool,
+    /// Write the canonical JSON of RFC 8785, `#[diesel_json(canonical)]`.
+    pub(crate) canonical: bool,
+    /// Reject JSON nesting arrays and objects deeper than this before parsing
+    /// it, `#[diesel_json(max_depth = N)]`.
+    pub(crate) max_depth: Option<usize>,
+    /// One impl for every raw bytes backend instead of one per backend
+    /// feature, `#[diesel_json(generic_backend)]`. Only valid for `Json`.
+    pub(crate) generic_backend: bool,
+    /// A backend to generate the impls for instead of the backend features,
+    /// `#[diesel_json(backend = path::to::Backend)]`.
+    pub(crate) backend: Option<Path>,
+    /// The raw value type of `backend`, `#[diesel_json(value = path::to::RawValue)]`.
+    pub(crate) raw_value: Option<Path>,
+    /// The user's cfgs gating the impls, `#[diesel_json(backend_cfg = "...")]`.
+    pub(crate) backend_cfg: BackendCfg,
+    /// Store the single field of a newtype, e.g. a `serde_json::Value`, as
+    /// the value, `#[diesel_json(transparent_value)]`.
+    pub(crate) transparent_value: bool,
+    /// Also convert to and from `serde_json::Value`,
+    /// `#[diesel_json(value_conversions)]`.
+    pub(crate) value_conversions: bool,
+    /// Add `to_jsonb_bytes` and `from_jsonb_bytes` to the type,
+    /// `#[diesel_json(inherent_methods)]`.
+    pub(crate) inherent_methods: bool,
+    /// Read the first JSON value and ignore anything after it,
+    /// `#[diesel_json(ignore_trailing)]`.
+    pub(crate) ignore_trailing: bool,
+    /// Read empty values as `Default::default()`,
+    /// `#[diesel_json(empty_as_default)]`.
+    pub(crate) empty_as_default: bool,
+    /// Read the JSON that legacy rows store for "no value" as
+    /// `Default::default()`, `#[diesel_json(null_sentinel = "{}")]`.
+    pub(crate) null_sentinel: Option<LitStr>,
+    /// Read anything that fails to be read as `Default::default()`,
+    /// `#[diesel_json(default_on_error)]`.
+    pub(crate) default_on_error: bool,
+    /// Require `Default` in a function of its own instead of on the `FromSql`
+    /// impl, `#[diesel_json(require_default)]`.
+    pub(crate) require_default: bool,
+    /// Where the values are stored, e.g. the column, named in read errors
+    /// after the type, `#[diesel_json(context = "users.settings")]`.
+    pub(crate) context: Option<LitStr>,
+    /// The instantiations of a generic type that get impls, instead of impls
+    /// for every instantiation, `#[diesel_json(instantiate(Foo<u32>))]`.
+    pub(crate) instantiate: Vec<Path>,
+    /// Reject values that are not JSON objects, `#[diesel_json(strict)]`.
+    pub(crate) strict: bool,
+    /// Fill in missing fields from `Default::default()`,
+    /// `#[diesel_json(lenient)]`.
+    pub(crate) lenient: bool,
+    /// Drop the entries the struct has no field for before deserializing,
+    /// `#[diesel_json(allow_unknown_fields)]`.
+    pub(crate) allow_unknown_fields: bool,
+    /// Store a CRC32 of the JSON in front of it,
+    /// `#[diesel_json(checksum = "crc32")]`.
+    pub(crate) checksum: bool,
+    /// Store the JSON gzipped behind a byte saying so, reading uncompressed
+    /// values as well, `#[diesel_json(compress = "gzip")]`.
+    pub(crate) compress: bool,
+    /// Write a byte naming the format in front of the value and read any
+    /// format by it, `#[diesel_json(tagged_format)]`.
+    pub(crate) tagged_format: bool,
+    /// Serialize into a buffer before writing anything,
+    /// `#[diesel_json(atomic_write)]`.
+    pub(crate) atomic_write: bool,
+    /// Serialize into a buffer kept for the next value of the thread,
+    /// `#[diesel_json(reuse_buffer)]`.
+    pub(crate) reuse_buffer: bool,
+    /// Store the variant of an enum as a byte in front of the JSON of its
+    /// fields, `#[diesel_json(enum_discriminant)]`.
+    pub(crate) enum_discriminant: bool,
+    /// Only generate `ToSql`, `#[diesel_json(serialize_only)]`.
+    pub(crate) serialize_only: bool,
+    /// Only generate `FromSql`, `#[diesel_json(deserialize_only)]`.
+    pub(crate) deserialize_only: bool,
+    /// Implement `FromSql` for the type with `'static` lifetimes only,
+    /// `#[diesel_json(owned_read)]`.
+    pub(crate) owned_read: bool,
+    /// The type holds a `RawValue` the derive can't see in its fields, so it
+    /// is read and written with serde_json, `#[diesel_json(raw_value)]`.
+    pub(crate) has_raw_value: bool,
+    /// Where predicates replacing the `Serialize` bound of the `ToSql` impl,
+    /// `#[diesel_json(serialize_bound = "T: Serialize")]`.
+    pub(crate) serialize_bound: Option<Vec<WherePredicate>>,
+    /// Where predicates replacing the `DeserializeOwned` bound of the
+    /// `FromSql` impl, `#[diesel_json(deserialize_bound = "T: DeserializeOwned")]`.
+    pub(crate) deserialize_bound: Option<Vec<WherePredicate>>,
+    /// Also implement `FromSql<Nullable<..>>` for the type itself, so that it
+    /// loads from nullable columns without an `Option`,
+    /// `#[diesel_json(nullable)]`.
+    pub(crate) nullable: bool,
+    /// Print the generated code to stderr while compiling,
+    /// `#[diesel_json(debug)]`.
+    pub(crate) debug: bool,
+}
+
+impl Default for Attrs {
+    fn default() -> Self {
+        Self {
+            sql_type: SqlType::default(),
+            sql_types: Vec::new(),
+            has_sql_type_attr: false,
+            sql_type_paths: Vec::new(),
+            version: None,
+            accept_any_version: false,
+            default_on_unknown_version: false,
+            detect_version_byte: false,
+            version_check: None,
+            schema_version: None,
+            migrate: None,
+            crate_path: parse_quote!(::diesel_json_derive),
+            diesel_path: parse_quote!(::diesel),
+            serde_json_path: parse_quote!(::serde_json),
+            size_hint: None,
+            max_bytes: None,
+            validate_on_write: false,
+            format: Format::default(),
+            format_from_env: false,
+            has_format_attr: false,
+            validate: None,
+            pre_serialize: None,
+            streaming: false,
+            intern_strings: false,
+            pretty: false,
+            formatter: None,
+            sort_keys: false,
+            canonical: false,
+            max_depth: None,
+            generic_backend: false,
+            backend: None,
+            raw_value: None,
+            backend_cfg: BackendCfg::default(),
+            transparent_value: false,
+            value_conversions: false,
+            inherent_methods: false,
+            ignore_trailing: false,
+            empty_as_default: false,
+            null_sentinel: None,
+            default_on_error: false,
+            require_default: false,
+            context: None,
+            instantiate: Vec::new(),
+            strict: false,
+            lenient: false,
+            allow_unknown_fields: false,
+            checksum: false,
+            compress: false,
+            tagged_format: false,
+            atomic_write: false,
+            reuse_buffer: false,
+            enum_discriminant: false,
+            serialize_only: false,
+            deserialize_only: false,
+            owned_read: false,
+            has_raw_value: false,
+            serialize_bound: None,
+            deserialize_bound: None,
+            nullable: false,
+            debug: false,
+        }
+    }
+}
+
+impl Attrs {
+    pub(crate) fn from_attributes(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+
+        for attr in attrs {
+            if attr.path().is_ident("diesel") {
+                attr.parse_nested_meta(|meta| result.parse_diesel_meta(meta))?;
+            } else if attr.path().is_ident("diesel_json") {
+                attr.parse_nested_meta(|meta| result.parse_diesel_json_meta(meta))?;
+            }
+        }
+        result.apply_default_format()?;
+
+        Ok(result)
+    }
+
+    /// Stores types that are only `Binary` and have no `format` of their own
+    /// in the format named by `DIESEL_JSONB_DEFAULT_FORMAT`, when it is set
+    /// while compiling. `enum_discriminant` types keep their own encoding.
+    fn apply_default_format(&mut self) -> syn::Result<()> {
+        if self.has_format_attr || self.enum_discriminant || self.sql_types != [SqlType::Binary] {
+            return Ok(());
+        }
+        if let Some(format) = default_format()? {
+            self.format = format;
+            self.format_from_env = format != Format::Json;
+        }
+        Ok(())
+    }
+
+    /// The format for errors about options it doesn't go with, saying where
+    /// it came from.
+    pub(crate) fn format_description(&self) -> String {
+        if self.format_from_env {
+            format!(
+                "format = \"{}\" (from {DEFAULT_FORMAT_VAR}, set format = \"json\" to keep JSON)",
+                self.format.as_str()
+            )
+        } else {
+            format!("format = \"{}\"", self.format.as_str())
+        }
+    }
+
+    /// One item of `#[diesel(...)]`.
+    fn parse_diesel_meta(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("sql_type") {
+            let path: Path = meta.value()?.parse()?;
+            self.has_sql_type_attr = true;
+            let sql_type = SqlType::from_path(&path)?;
+            self.add_sql_type(sql_type);
+            self.sql_type_paths.push((sql_type, path));
+        } else {
+            // other diesel options are none of our business
+            skip_meta_value(meta.input)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the `ToSql` impls are generated.
+    pub(crate) fn writes(&self) -> bool {
+        !self.deserialize_only
+    }
+
+    /// Whether the `FromSql` impls are generated.
+    pub(crate) fn reads(&self) -> bool {
+        !self.serialize_only
+    }
+
+    /// Whether reading falls back to `Default::default()`, which `lenient`
+    /// needs too.
+    pub(crate) fn needs_default(&self) -> bool {
+        self.empty_as_default
+            || self.default_on_error
+            || self.default_on_unknown_version
+            || self.null_sentinel.is_some()
+            || self.lenient
+    }
+
+    fn add_sql_type(&mut self, sql_type: SqlType) {
+        self.sql_type = sql_type;
+        if !self.sql_types.contains(&sql_type) {
+            self.sql_types.push(sql_type);
+        }
+    }
+
+    /// One item of `#[diesel_json(...)]`.
+    fn parse_diesel_json_meta(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("sql_types") {
+            meta.parse_nested_meta(|meta| {
+                self.add_sql_type(SqlType::from_path(&meta.path)?);
+                Ok(())
+            })?;
+        } else if meta.path.is_ident("version") {
+            self.version = Some(parse_u8(&meta)?);
+        } else if meta.path.is_ident("accept_any_version")
+            || meta.path.is_ident("skip_version_check")
+        {
+            self.accept_any_version = true;
+        } else if meta.path.is_ident("detect_version_byte") {
+            self.detect_version_byte = true;
+        } else if meta.path.is_ident("version_check") {
+            self.version_check = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("unknown_version") {
+            let behavior: LitStr = meta.value()?.parse()?;
+            self.default_on_unknown_version = match behavior.value().as_str() {
+                "default" => true,
+                "error" => false,
+                _ => {
+                    return Err(syn::Error::new(
+                        behavior.span(),
+                        "unknown behavior, expected \"default\" or \"error\"",
+                    ))
+                }
+            };
+        } else if meta.path.is_ident("schema_version") {
+            self.schema_version = Some(parse_u8(&meta)?);
+        } else if meta.path.is_ident("migrate") {
+            self.migrate = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("crate_path") {
+            self.crate_path = meta.value()?.parse()?;
+        } else if meta.path.is_ident("diesel_path") {
+            self.diesel_path = meta.value()?.parse()?;
+        } else if meta.path.is_ident("serde_json_path") {
+            self.serde_json_path = meta.value()?.parse()?;
+        } else if meta.path.is_ident("size_hint") {
+            self.size_hint = Some(parse_usize(&meta)?);
+        } else if meta.path.is_ident("max_bytes") {
+            self.max_bytes = Some(parse_usize(&meta)?);
+        } else if meta.path.is_ident("validate_on_write") {
+            self.validate_on_write = true;
+        } else if meta.path.is_ident("format") {
+            self.format = parse_format(&meta)?;
+            self.has_format_attr = true;
+        } else if meta.path.is_ident("validate") {
+            self.validate = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("pre_serialize") {
+            self.pre_serialize = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("streaming") || meta.path.is_ident("reader") {
+            self.streaming = true;
+        } else if meta.path.is_ident("intern_strings") {
+            self.intern_strings = true;
+        } else if meta.path.is_ident("pretty") {
+            self.pretty = true;
+        } else if meta.path.is_ident("formatter") {
+            self.formatter = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("sort_keys") {
+            self.sort_keys = true;
+        } else if meta.path.is_ident("canonical") {
+            self.canonical = true;
+        } else if meta.path.is_ident("max_depth") {
+            self.max_depth = Some(parse_usize(&meta)?);
+        } else if meta.path.is_ident("generic_backend") {
+            self.generic_backend = true;
+        } else if meta.path.is_ident("backend_cfg") {
+            if meta.input.peek(Token![=]) {
+                self.backend_cfg.all = Some(parse_cfg(&meta)?);
+            } else {
+                meta.parse_nested_meta(|meta| {
+                    let cfg = if meta.path.is_ident("postgres") {
+                        &mut self.backend_cfg.postgres
+                    } else if meta.path.is_ident("sqlite") {
+                        &mut self.backend_cfg.sqlite
+                    } else if meta.path.is_ident("mysql") {
+                        &mut self.backend_cfg.mysql
+                    } else {
+                        return Err(
+                            meta.error("unknown backend, expected `postgres`, `sqlite` or `mysql`")
+                        );
+                    };
+                    *cfg = Some(parse_cfg(&meta)?);
+                    Ok(())
+                })?;
+            }
+        } else if meta.path.is_ident("backend") {
+            self.backend = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("value") {
+            self.raw_value = Some(parse_path(&meta)?);
+        } else if meta.path.is_ident("transparent_value") {
+            self.transparent_value = true;
+        } else if meta.path.is_ident("value_conversions") {
+            self.value_conversions = true;
+        } else if meta.path.is_ident("inherent_methods") {
+            self.inherent_methods = true;
+        } else if meta.path.is_ident("ignore_trailing") {
+            self.ignore_trailing = true;
+        } else if meta.path.is_ident("empty_as_default") {
+            self.empty_as_default = true;
+        } else if meta.path.is_ident("null_sentinel") {
+            let sentinel: LitStr = meta.value()?.parse()?;
+            if sentinel.value().trim().is_empty() {
+                return Err(syn::Error::new(
+                    sentinel.span(),
+                    "expected the JSON standing for no value, e.g. \"{}\"; \
+                     use empty_as_default for empty values",
+                ));
+            }
+            self.null_sentinel = Some(sentinel);
+        } else if meta.path.is_ident("default_on_error") {
+            self.default_on_error = true;
+        } else if meta.path.is_ident("require_default") {
+            self.require_default = true;
+        } else if meta.path.is_ident("context") {
+            let context: LitStr = meta.value()?.parse()?;
+            if context.value().trim().is_empty() {
+                return Err(syn::Error::new(
+                    context.span(),
+                    "expected where the values are stored, e.g. \"users.settings\"",
+                ));
+            }
+            self.context = Some(context);
+        } else if meta.path.is_ident("instantiate") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            let types = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+            if types.is_empty() {
+                return Err(meta.error("expected the instantiations, e.g. `instantiate(Foo<u32>)`"));
+            }
+            self.instantiate.extend(types);
+        } else if meta.path.is_ident("strict") {
+            self.strict = true;
+        } else if meta.path.is_ident("lenient") {
+            self.lenient = true;
+        } else if meta.path.is_ident("allow_unknown_fields") {
+            self.allow_unknown_fields = true;
+        } else if meta.path.is_ident("checksum") {
+            let algorithm: LitStr = meta.value()?.parse()?;
+            if algorithm.value() != "crc32" {
+                return Err(syn::Error::new(
+                    algorithm.span(),
+                    "unknown checksum, expected \"crc32\"",
+                ));
+            }
+            self.checksum = true;
+        } else if meta.path.is_ident("tagged_format") {
+            self.tagged_format = true;
+        } else if meta.path.is_ident("atomic_write") {
+            self.atomic_write = true;
+        } else if meta.path.is_ident("reuse_buffer") {
+            self.reuse_buffer = true;
+        } else if meta.path.is_ident("enum_discriminant") {
+            self.enum_discriminant = true;
+        } else if meta.path.is_ident("serialize_only") {
+            self.serialize_only = true;
+        } else if meta.path.is_ident("deserialize_only") {
+            self.deserialize_only = true;
+        } else if meta.path.is_ident("owned_read") {
+            self.owned_read = true;
+        } else if meta.path.is_ident("raw_value") {
+            self.has_raw_value = true;
+        } else if meta.path.is_ident("serialize_bound") {
+            self.serialize_bound = Some(parse_bound(&meta)?);
+        } else if meta.path.is_ident("deserialize_bound") {
+            self.deserialize_bound = Some(parse_bound(&meta)?);
+        } else if meta.path.is_ident("nullable") {
+            self.nullable = true;
+        } else if meta.path.is_ident("debug") {
+            self.debug = true;
+        } else if meta.path.is_ident("no_version_byte") {
+            return Err(meta.error(
+                "Postgres rejects JSONB values without the version byte, use \
+                 `#[diesel(sql_type = Binary)]` with a `bytea` column to store plain JSON bytes",
+            ));
+        } else if meta.path.is_ident("queryable") {
+            return Err(meta.error(
+                "`#[derive(FromSqlRow)]` already implements `Queryable` with `Row = Self` \
+                 for every sql type the value can be read from, so it can be loaded \
+                 directly, e.g. with `.select(foo::bar).load::<Bar>(conn)`",
+            ));
+        } else if meta.path.is_ident("compress") {
+            let algorithm: LitStr = meta.value()?.parse()?;
+            if algorithm.value() != "gzip" {
+                return Err(syn::Error::new(
+                    algorithm.span(),
+                    "unknown compression, expected \"gzip\"",
+                ));
+            }
+            if !cfg!(feature = "compression") {
+                return Err(syn::Error::new(
+                    algorithm.span(),
+                    "compress = \"gzip\" needs the `compression` feature of diesel-json-derive",
+                ));
+            }
+            self.compress = true;
+        } else if meta.path.is_ident("none_as_json_null") {
+            return Err(meta.error(
+                "Rust's orphan rule keeps the derive from implementing diesel's traits for \
+                 `Option<T>`, wrap it instead: `struct MaybeBar(Option<Bar>)` with \
+                 #[diesel_json(transparent_value)] stores `None` as the JSON `null`",
+            ));
+        } else if meta.path.is_ident("composite") {
+            return Err(meta.error(
+                "Postgres sends JSONB fields of records and composite types like JSONB \
+                 columns, version byte included, so they are read without any option, \
+                 e.g. as `(i32, Bar)` from a `Record<(Integer, Jsonb)>`",
+            ));
+        } else if meta.path.is_ident("rename_module") || meta.path.is_ident("pub_mod") {
+            return Err(meta.error(
+                "the impls are generated in an anonymous `const _: () = { ... }`, not in a \
+                 module, so there is no module to name or make public; trait impls apply \
+                 wherever the type is visible, so your own impls can go next to the type",
+            ));
+        } else if meta.path.is_ident("sqlite_text") {
+            return Err(meta.error(
+                "SQLite's JSON functions read text columns, use `#[diesel(sql_type = Text)]` \
+                 or `Json` to bind the JSON as text without a version prefix; a `Jsonb` \
+                 parameter cannot be bound as text, diesel's SQLite bind collector rejects \
+                 it with \"Type mismatch: Expected Binary, got Text\"",
+            ));
+        } else if meta.path.is_ident("borrow") {
+            return Err(meta.error(
+                "values cannot borrow from the database row: diesel's \
+                 `FromSql::from_sql` takes the raw value for an arbitrary lifetime, \
+                 so the result must be `serde::de::DeserializeOwned`",
+            ));
+        } else {
+            return Err(meta.error("unknown diesel_json attribute"));
+        }
+        Ok(())
+    }
+}
+
+/// The input of `impl_diesel_jsonb!`: the type followed by the options
+/// otherwise given as `#[diesel(sql_type = ...)]` and `#[diesel_json(...)]`,
+/// e.g. `impl_diesel_jsonb!(Bar, sql_type = Json, version = 2)`. Generic
+/// types are given like an impl header,
+/// `impl_diesel_jsonb!(impl<T: Bound> for Foo<T> where ..., version = 2)`.
+pub(crate) struct ImplInput {
+    pub(crate) ty: Path,
+    /// The generics of the impls, empty for a type named by path alone.
+    pub(crate) generics: Generics,
+    pub(crate) attrs: Attrs,
+}
+
+impl Parse for ImplInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut generics = Generics::default();
+        if input.parse::<Option<Token![impl]>>()?.is_some() {
+            generics = input.parse()?;
+            input.parse::<Token![for]>()?;
+        }
+        let ty = input.parse()?;
+        if input.peek(Token![where]) {
+            generics.where_clause = Some(parse_where_clause(input)?);
+        }
+        let mut attrs = Attrs::default();
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            let parser = syn::meta::parser(|meta| {
+                if meta.path.is_ident("sql_type") {
+                    attrs.parse_diesel_meta(meta)
+                } else {
+                    attrs.parse_diesel_json_meta(meta)
+                }
+            });
+            parser.parse2(input.parse()?)?;
+        }
+        attrs.apply_default_format()?;
+        Ok(Self {
+            ty,
+            generics,
+            attrs,
+        })
+    }
+}
+
+/// The where clause of `impl_diesel_jsonb!`. The options after it are
+/// separated by a comma too, the predicates end at the first comma that
+/// isn't followed by another predicate (options have no `:`).
+fn parse_where_clause(input: ParseStream) -> syn::Result<WhereClause> {
+    let mut where_clause = WhereClause {
+        where_token: input.parse()?,
+        predicates: Punctuated::new(),
+    };
+    loop {
+        where_clause
+            .predicates
+            .push(input.parse::<WherePredicate>()?);
+        let fork = input.fork();
+        if fork.parse::<Token![,]>().is_err() || fork.parse::<WherePredicate>().is_err() {
+            return Ok(where_clause);
+        }
+        input.parse::<Token![,]>()?;
+    }
+}
+
+/// The input of `diesel_jsonb_types!`: a comma separated list of types that
+/// get the default impls.
+pub(crate) struct TypesInput {
+    pub(crate) types: Vec<Path>,
+}
+
+impl Parse for TypesInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let types = Punctuated::<Path, Token![,]>::parse_terminated(input)?;
+        Ok(Self {
+            types: types.into_iter().collect(),
+        })
+    }
+}
+
+/// The sql type of a model's field marked with `#[diesel_jsonb]`, for
+/// `#[diesel_jsonb_model]`: `Jsonb`, or the one of
+/// `#[diesel_jsonb(sql_type = Json)]`.
+pub(crate) fn field_sql_type(attr: &Attribute) -> syn::Result<SqlType> {
+    let mut sql_type = SqlType::Jsonb;
+    if let syn::Meta::List(_) = attr.meta {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("sql_type") {
+                sql_type = SqlType::from_path(&meta.value()?.parse()?)?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown option, expected `sql_type`"))
+            }
+        })?;
+    }
+    Ok(sql_type)
+}
+
+/// The environment variable read for the crate-wide default format.
+const DEFAULT_FORMAT_VAR: &str = "DIESEL_JSONB_DEFAULT_FORMAT";
+
+/// The format `DIESEL_JSONB_DEFAULT_FORMAT` names, if it is set and not
+/// empty. The error is at the call site, there is no attribute to point at.
+fn default_format() -> syn::Result<Option<Format>> {
+    let Ok(value) = std::env::var(DEFAULT_FORMAT_VAR) else {
+        return Ok(None);
+    };
+    let error = |message: String| syn::Error::new(Span::call_site(), message);
+    match value.as_str() {
+        "" => Ok(None),
+        "json" => Ok(Some(Format::Json)),
+        "msgpack" if cfg!(feature = "messagepack") => Ok(Some(Format::MessagePack)),
+        "msgpack" => Err(error(format!(
+            "{DEFAULT_FORMAT_VAR}=msgpack needs the `messagepack` feature of diesel-json-derive"
+        ))),
+        "cbor" if cfg!(feature = "cbor") => Ok(Some(Format::Cbor)),
+        "cbor" => Err(error(format!(
+            "{DEFAULT_FORMAT_VAR}=cbor needs the `cbor` feature of diesel-json-derive"
+        ))),
+        _ => Err(error(format!(
+            "{DEFAULT_FORMAT_VAR} is {value:?}, expected \"json\", \"msgpack\" or \"cbor\""
+        ))),
+    }
+}
+
+fn parse_format(meta: &ParseNestedMeta) -> syn::Result<Format> {
+    let lit: LitStr = meta.value()?.parse()?;
+    match lit.value().as_str() {
+        "json" => Ok(Format::Json),
+        "msgpack" if cfg!(feature = "messagepack") => Ok(Format::MessagePack),
+        "msgpack" => Err(syn::Error::new_spanned(
+            lit,
+            "format = \"msgpack\" needs the `messagepack` feature of diesel-json-derive",
+        )),
+        "cbor" if cfg!(feature = "cbor") => Ok(Format::Cbor),
+        "cbor" => Err(syn::Error::new_spanned(
+            lit,
+            "format = \"cbor\" needs the `cbor` feature of diesel-json-derive",
+        )),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "expected \"json\", \"msgpack\" or \"cbor\"",
+        )),
+    }
+}
+
+/// A path given either as is or, like serde's `with` paths, as a string.
+fn parse_path(meta: &ParseNestedMeta) -> syn::Result<Path> {
+    let value = meta.value()?;
+    if value.peek(LitStr) {
+        value.parse::<LitStr>()?.parse()
+    } else {
+        value.parse()
+    }
+}
+
+/// A cfg predicate given as a string, e.g. `"feature = \"pg\""`.
+fn parse_cfg(meta: &ParseNestedMeta) -> syn::Result<syn::Meta> {
+    meta.value()?.parse::<LitStr>()?.parse()
+}
+
+/// Where predicates given as a string like serde's `bound`, separated by
+/// commas. An empty string bounds nothing.
+fn parse_bound(meta: &ParseNestedMeta) -> syn::Result<Vec<WherePredicate>> {
+    let lit: LitStr = meta.value()?.parse()?;
+    let predicates = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+    Ok(predicates.into_iter().collect())
+}
+
+fn parse_u8(meta: &ParseNestedMeta) -> syn::Result<u8> {
+    parse_int(meta, "expected a `u8` literal")
+}
+
+fn parse_usize(meta: &ParseNestedMeta) -> syn::Result<usize> {
+    parse_int(meta, "expected a `usize` literal")
+}
+
+fn parse_int<N>(meta: &ParseNestedMeta, expected: &str) -> syn::Result<N>
+where
+    N: std::str::FromStr,
+    N::Err: std::fmt::Display,
+{
+    let lit: Lit = meta.value()?.parse()?;
+    match &lit {
+        Lit::Int(int) => int.base10_parse(),
+        _ => Err(syn::Error::new_spanned(&lit, expected)),
+    }
+    .map_err(|e| syn::Error::new(e.span(), expected))
+}
+
+/// Consumes the `= value` or `(...)` part of a nested meta item we don't
+/// care about.
+fn skip_meta_value(input: ParseStream) -> syn::Result<()> {
+    while !input.is_empty() && !input.peek(Token![,]) {
+        input.parse::<TokenTree>()?;
+    }
+    Ok(())
+}
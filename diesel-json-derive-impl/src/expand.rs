@@ -0,0 +1,2784 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::visit_mut::VisitMut;
+use syn::{
+    parse_quote, Data, DeriveInput, Field, Fields, GenericArgument, GenericParam, Generics, Ident,
+    Lifetime, Member, Path, PathArguments, Type, Variant, WherePredicate,
+};
+
+use crate::attrs::{field_sql_type, Attrs, Format, ImplInput, SqlType, TypesInput};
+
+pub(crate) fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    if let Data::Union(_) = input.data {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "DieselJsonb cannot be derived for unions; serde does not support them",
+        ));
+    }
+    let attrs = Attrs::from_attributes(&input.attrs)?;
+    if attrs.reads() {
+        reject_borrowed_fields(input)?;
+    }
+    if attrs.strict {
+        reject_non_object_struct(input)?;
+    }
+    if !attrs.instantiate.is_empty() {
+        return instantiated_impls(input, &attrs);
+    }
+    let transparent = if attrs.transparent_value {
+        Some(transparent_field(input)?)
+    } else {
+        None
+    };
+    let mut target = Target::new(input, transparent);
+    target.raw_json = attrs.has_raw_value || has_raw_value_field(input);
+    if attrs.enum_discriminant {
+        target.use_discriminants(input)?;
+    }
+    target.replace_bounds(&input.generics, &attrs);
+    if attrs.owned_read {
+        target.read_owned();
+    }
+    target.require_default(&attrs);
+    impls(&target, &attrs)
+}
+
+/// `instantiate`: the impls of each listed instantiation of a generic type,
+/// like `impl_diesel_jsonb!` generates them for a type named by path, instead
+/// of impls for every instantiation.
+fn instantiated_impls(input: &DeriveInput, attrs: &Attrs) -> syn::Result<TokenStream> {
+    if input.generics.type_params().next().is_none()
+        && input.generics.const_params().next().is_none()
+    {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "instantiate needs a type with type or const parameters",
+        ));
+    }
+    let unsupported = [
+        (attrs.transparent_value, "transparent_value"),
+        (attrs.enum_discriminant, "enum_discriminant"),
+        (attrs.owned_read, "owned_read"),
+        (attrs.serialize_bound.is_some(), "serialize_bound"),
+        (attrs.deserialize_bound.is_some(), "deserialize_bound"),
+    ];
+    if let Some((_, name)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            format!("instantiate cannot be combined with {name}"),
+        ));
+    }
+    let mut expanded = TokenStream::new();
+    for ty in &attrs.instantiate {
+        let last = ty.segments.last().expect("a path has at least one segment");
+        if last.ident != input.ident || last.arguments.is_empty() {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "expected an instantiation of `{}`, e.g. `{}<u32>`",
+                    input.ident, input.ident
+                ),
+            ));
+        }
+        let mut target = Target::from_path(ty, &Generics::default());
+        target.raw_json = attrs.has_raw_value || has_raw_value_field(input);
+        target.require_default(attrs);
+        expanded.extend(impls(&target, attrs)?);
+    }
+    Ok(expanded)
+}
+
+/// `FromSql` needs `DeserializeOwned`, which a type with references can't
+/// implement. Without this the error is about serde's `Deserialize<'de>` impl
+/// not being general enough.
+fn reject_borrowed_fields(input: &DeriveInput) -> syn::Result<()> {
+    let fields: Vec<&Field> = match &input.data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => data.variants.iter().flat_map(|v| &v.fields).collect(),
+        Data::Union(_) => Vec::new(),
+    };
+    match fields.iter().find_map(|field| borrowed_type(&field.ty)) {
+        Some(reference) => Err(syn::Error::new_spanned(
+            reference,
+            "FromSql needs `serde::de::DeserializeOwned`, so the value cannot borrow from \
+             the database row: use an owned type or a `Cow`, or \
+             #[diesel_json(serialize_only)] if the type is only written",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// `strict` only reads objects, but serde writes a unit struct as `null` and
+/// a tuple struct with other than one field as an array, so every value
+/// would be rejected. serde attributes like `into` may change the shape, with
+/// any of them the type is left alone.
+fn reject_non_object_struct(input: &DeriveInput) -> syn::Result<()> {
+    if input.attrs.iter().any(|attr| attr.path().is_ident("serde")) {
+        return Ok(());
+    }
+    let shape = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => "a unit struct is written as `null`",
+            Fields::Unnamed(fields) if fields.unnamed.len() != 1 => {
+                "a tuple struct is written as an array"
+            }
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    Err(syn::Error::new(
+        input.ident.span(),
+        format!("strict only reads JSON objects, but {shape}"),
+    ))
+}
+
+/// Whether a field holds serde_json's `RawValue`, e.g. as
+/// `Option<Box<RawValue>>`. It only works with serde_json's own parser and
+/// serializer. Only the field types are looked at, a `RawValue` inside
+/// another type, an alias or a generic parameter needs `raw_value`.
+fn has_raw_value_field(input: &DeriveInput) -> bool {
+    fn names_raw_value(tokens: TokenStream) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == "RawValue",
+            TokenTree::Group(group) => names_raw_value(group.stream()),
+            _ => false,
+        })
+    }
+    match &input.data {
+        Data::Struct(data) => data.fields.iter().any(|field| {
+            let ty = &field.ty;
+            names_raw_value(quote!(#ty))
+        }),
+        Data::Enum(data) => data.variants.iter().flat_map(|v| &v.fields).any(|field| {
+            let ty = &field.ty;
+            names_raw_value(quote!(#ty))
+        }),
+        Data::Union(_) => false,
+    }
+}
+
+/// The first reference in `ty`, e.g. the `&'a str` of `Option<&'a str>`.
+fn borrowed_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(_) => Some(ty),
+        Type::Array(array) => borrowed_type(&array.elem),
+        Type::Slice(slice) => borrowed_type(&slice.elem),
+        Type::Paren(paren) => borrowed_type(&paren.elem),
+        Type::Group(group) => borrowed_type(&group.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(borrowed_type),
+        Type::Path(path) => path.path.segments.iter().find_map(|segment| {
+            let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                return None;
+            };
+            args.args.iter().find_map(|arg| match arg {
+                GenericArgument::Type(ty) => borrowed_type(ty),
+                _ => None,
+            })
+        }),
+        _ => None,
+    }
+}
+
+/// `impl_diesel_jsonb!`: the derive's impls for a type named by path.
+pub(crate) fn impl_macro(input: &ImplInput) -> syn::Result<TokenStream> {
+    if input.attrs.transparent_value {
+        return Err(syn::Error::new_spanned(
+            &input.ty,
+            "transparent_value needs the definition of the type, use the derive",
+        ));
+    }
+    if input.attrs.enum_discriminant {
+        return Err(syn::Error::new_spanned(
+            &input.ty,
+            "enum_discriminant needs the variants of the enum, use the derive",
+        ));
+    }
+    if input.attrs.owned_read {
+        return Err(syn::Error::new_spanned(
+            &input.ty,
+            "owned_read needs the definition of the type, name the type with 'static \
+             lifetimes instead, e.g. `Doc<'static>`",
+        ));
+    }
+    if !input.attrs.instantiate.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ty,
+            "instantiate is for the derive, call the macro once for each instantiation",
+        ));
+    }
+    reject_foreign_type(&input.ty)?;
+    let mut target = Target::from_path(&input.ty, &input.generics);
+    target.raw_json = input.attrs.has_raw_value;
+    target.replace_bounds(&input.generics, &input.attrs);
+    target.require_default(&input.attrs);
+    impls(&target, &input.attrs)
+}
+
+/// Collections and shared pointers of the standard library are never local,
+/// the orphan rule forbids the impls even for `Vec<LocalType>` or
+/// `Arc<LocalType>`. rustc's error doesn't say what to do instead. `Box` is
+/// `#[fundamental]`, so `Box<LocalType>` is local and allowed.
+fn reject_foreign_type(ty: &Path) -> syn::Result<()> {
+    let last = ty.segments.last().expect("a path has at least one segment");
+    if last.ident == "Arc" || last.ident == "Rc" {
+        let pointee = match &last.arguments {
+            PathArguments::AngleBracketed(args) => args.args.first().map(|arg| quote!(#arg)),
+            _ => None,
+        };
+        let pointee =
+            pointee.map_or_else(|| "T".to_string(), |arg| arg.to_string().replace(' ', ""));
+        let pointer = &last.ident;
+        return Err(syn::Error::new(
+            last.ident.span(),
+            format!(
+                "`{pointer}` is defined in the standard library, so Rust's orphan rule forbids \
+                 implementing diesel's traits for it, implement them for `{pointee}` instead and \
+                 load `{pointer}<{pointee}>` fields with `#[diesel(deserialize_as = {pointee})]`, \
+                 binding `&*value` to write them"
+            ),
+        ));
+    }
+    let collections = [
+        "Vec", "VecDeque", "HashMap", "BTreeMap", "HashSet", "BTreeSet",
+    ];
+    if collections.iter().any(|name| last.ident == name) {
+        let ty = quote!(#ty).to_string().replace(' ', "");
+        return Err(syn::Error::new(
+            last.ident.span(),
+            format!(
+                "`{ty}` is defined in the standard library, so Rust's orphan rule forbids \
+                 implementing diesel's traits for it, use a newtype like `struct Items({ty})` \
+                 with the derive instead"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// `diesel_jsonb_types!`: `impl_diesel_jsonb!` with the default options for
+/// every type of the list.
+pub(crate) fn types_macro(input: &TypesInput) -> syn::Result<TokenStream> {
+    let mut expanded = TokenStream::new();
+    for ty in &input.types {
+        reject_foreign_type(ty)?;
+        let target = Target::from_path(ty, &Generics::default());
+        expanded.extend(impls(&target, &Attrs::default())?);
+    }
+    Ok(expanded)
+}
+
+/// The field a `transparent_value` type is stored as, the only field of a
+/// struct.
+fn transparent_field(input: &DeriveInput) -> syn::Result<(Member, Type)> {
+    if let Data::Struct(data) = &input.data {
+        if let Fields::Named(_) | Fields::Unnamed(_) = data.fields {
+            if data.fields.len() == 1 {
+                let field = data.fields.iter().next().expect("one field");
+                let member = match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(0.into()),
+                };
+                return Ok((member, field.ty.clone()));
+            }
+        }
+    }
+    Err(syn::Error::new(
+        input.ident.span(),
+        "transparent_value needs a struct with exactly one field, e.g. `struct Doc(serde_json::Value)`",
+    ))
+}
+
+fn impls(target: &Target, attrs: &Attrs) -> syn::Result<TokenStream> {
+    let mut expanded = if attrs.sql_types.len() <= 1 {
+        sql_type_impls(target, attrs)?
+    } else {
+        let mut expanded = TokenStream::new();
+        for &sql_type in &attrs.sql_types {
+            let attrs = Attrs {
+                sql_type,
+                ..attrs.clone()
+            };
+            expanded.extend(sql_type_impls(target, &attrs)?);
+        }
+        expanded
+    };
+    expanded.extend(assert_diesel_sql_types(attrs));
+    if attrs.value_conversions {
+        expanded.extend(value_conversions(target, attrs));
+    }
+    if attrs.inherent_methods {
+        expanded.extend(inherent_methods(target, attrs)?);
+    }
+    Ok(expanded)
+}
+
+/// Checks that the paths of the `#[diesel(sql_type = ...)]` attributes are
+/// diesel's sql types, which the derive knows by their last segment only. A
+/// custom `SqlType` named e.g. `Jsonb` would get `AsExpression` impls of its
+/// own and the derive's impls for diesel's, failing somewhere inside diesel.
+fn assert_diesel_sql_types(attrs: &Attrs) -> TokenStream {
+    if attrs.sql_type_paths.is_empty() {
+        return TokenStream::new();
+    }
+    let diesel = &attrs.diesel_path;
+    let asserts = attrs.sql_type_paths.iter().map(|(sql_type, path)| {
+        let expected = quote!(::std::marker::PhantomData<#diesel::sql_types::#sql_type>);
+        let span = path.segments.last().expect("a sql type").ident.span();
+        quote_spanned! {span=>
+            let _: #expected = ::std::marker::PhantomData::<#path>;
+        }
+    });
+    quote! {
+        const _: () = {
+            #(#asserts)*
+        };
+    }
+}
+
+/// `#[diesel_json(inherent_methods)]`: `to_jsonb_bytes` and `from_jsonb_bytes`
+/// on the type, writing and reading the bytes of a JSONB value like the
+/// Postgres impls do, without a connection.
+fn inherent_methods(target: &Target, attrs: &Attrs) -> syn::Result<TokenStream> {
+    if attrs.sql_type != SqlType::Jsonb && !attrs.sql_types.contains(&SqlType::Jsonb) {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "inherent_methods writes JSONB values, add #[diesel(sql_type = Jsonb)]",
+        ));
+    }
+    let attrs = &Attrs {
+        sql_type: SqlType::Jsonb,
+        ..attrs.clone()
+    };
+    let diesel = &attrs.diesel_path;
+    let ty = &target.ty;
+    let from_sql_ty = &target.from_sql_ty;
+
+    let to_bytes = attrs.writes().then(|| {
+        let (impl_generics, _, where_clause) = target.to_sql_generics.split_for_impl();
+        let pre_serialize = pre_serialize(target, attrs);
+        let write_jsonb = write_jsonb(target, attrs);
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #ty #where_clause {
+                /// The bytes `ToSql<Jsonb, Pg>` binds for the value, the
+                /// format version byte followed by the JSON.
+                pub fn to_jsonb_bytes(
+                    &self,
+                ) -> ::core::result::Result<
+                    ::std::vec::Vec<u8>,
+                    ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync>,
+                > {
+                    let write = |out: &mut ::std::vec::Vec<u8>| -> #diesel::serialize::Result {
+                        #pre_serialize
+                        #write_jsonb
+                    };
+                    let mut buf = ::std::vec::Vec::new();
+                    write(&mut buf)?;
+                    ::core::result::Result::Ok(buf)
+                }
+            }
+        }
+    });
+    let from_bytes = attrs.reads().then(|| {
+        let (impl_generics, _, where_clause) = target.from_sql_generics.split_for_impl();
+        let read_jsonb = read_jsonb(target, attrs);
+        let read_jsonb = with_context(target, attrs, read_jsonb);
+        let read_jsonb = log_error(target, attrs, quote!(bytes), read_jsonb);
+        let read_jsonb = or_default(attrs, read_jsonb);
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #from_sql_ty #where_clause {
+                /// Reads the bytes of a JSONB value like `FromSql<Jsonb, Pg>`
+                /// does, the format version byte followed by the JSON.
+                pub fn from_jsonb_bytes(bytes: &[u8]) -> #diesel::deserialize::Result<Self> {
+                    #read_jsonb
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[allow(unused_qualifications)]
+        const _: () = {
+            #to_bytes
+            #from_bytes
+        };
+    })
+}
+
+/// `#[diesel_json(value_conversions)]`: `From<T> for serde_json::Value` and
+/// `TryFrom<serde_json::Value> for T`, once for all sql types. Like
+/// `serde_json::json!`, `from` panics for values JSON can't represent, e.g.
+/// maps with non-string keys.
+fn value_conversions(target: &Target, attrs: &Attrs) -> TokenStream {
+    let serde_json = &attrs.serde_json_path;
+    let ty = &target.ty;
+    let from_sql_ty = &target.from_sql_ty;
+    let type_name = target.name();
+    let (field, wrap) = match &target.transparent {
+        Some((member, _)) => (
+            quote!(.#member),
+            quote!(.map(|value| Self { #member: value })),
+        ),
+        None => (quote!(), quote!()),
+    };
+
+    let into_value = attrs.writes().then(|| {
+        let (impl_generics, _, where_clause) = target.to_sql_generics.split_for_impl();
+        let message = format!("{type_name} can be represented as a serde_json::Value");
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::convert::From<#ty> for #serde_json::Value #where_clause {
+                fn from(value: #ty) -> Self {
+                    ::core::result::Result::expect(#serde_json::to_value(&value #field), #message)
+                }
+            }
+        }
+    });
+    let from_value = attrs.reads().then(|| {
+        let (impl_generics, _, where_clause) = target.from_sql_generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::convert::TryFrom<#serde_json::Value> for #from_sql_ty #where_clause {
+                type Error = #serde_json::Error;
+
+                fn try_from(value: #serde_json::Value) -> ::core::result::Result<Self, Self::Error> {
+                    #serde_json::from_value(value) #wrap
+                }
+            }
+        }
+    });
+
+    quote! {
+        #into_value
+        #from_value
+    }
+}
+
+/// The impls for `attrs.sql_type`.
+fn sql_type_impls(target: &Target, attrs: &Attrs) -> syn::Result<TokenStream> {
+    if attrs.format != Format::Json && !matches!(attrs.sql_type, SqlType::Binary) {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            format!(
+                "{} is not JSON, use #[diesel(sql_type = Binary)]",
+                attrs.format_description()
+            ),
+        ));
+    }
+
+    for (set, name) in [
+        (attrs.ignore_trailing, "ignore_trailing"),
+        (attrs.strict, "strict"),
+        (attrs.lenient, "lenient"),
+        (attrs.allow_unknown_fields, "allow_unknown_fields"),
+        (attrs.null_sentinel.is_some(), "null_sentinel"),
+        (attrs.formatter.is_some(), "formatter"),
+        (attrs.sort_keys, "sort_keys"),
+        (attrs.canonical, "canonical"),
+        (attrs.max_depth.is_some(), "max_depth"),
+        (attrs.schema_version.is_some(), "schema_version"),
+        (attrs.checksum, "checksum"),
+        (attrs.compress, "compress"),
+        (attrs.validate_on_write, "validate_on_write"),
+    ] {
+        if set && attrs.format != Format::Json {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                format!(
+                    "{name} only applies to JSON, not {}",
+                    attrs.format_description()
+                ),
+            ));
+        }
+    }
+
+    if attrs.checksum && !matches!(attrs.sql_type, SqlType::Binary) {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "checksum needs #[diesel(sql_type = Binary)]: JSON and JSONB columns are \
+             validated and, for JSONB, normalized by the database, so they can't hold \
+             the checksum or keep the bytes it was computed from",
+        ));
+    }
+
+    if attrs.compress {
+        if !matches!(attrs.sql_type, SqlType::Binary) {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "compress needs #[diesel(sql_type = Binary)]: JSON and JSONB values are \
+                 parsed by the database, so they cannot be stored compressed. Use a `bytea` \
+                 column or the column's own compression, e.g. \
+                 `ALTER TABLE ... ALTER COLUMN ... SET COMPRESSION lz4` on Postgres",
+            ));
+        }
+        for (set, name) in [
+            (attrs.checksum, "checksum"),
+            (attrs.reuse_buffer, "reuse_buffer"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("compress cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.tagged_format {
+        if !matches!(attrs.sql_type, SqlType::Binary) {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "tagged_format needs #[diesel(sql_type = Binary)]: JSON and JSONB columns \
+                 can only hold JSON",
+            ));
+        }
+        for (set, name) in [
+            (attrs.enum_discriminant, "enum_discriminant"),
+            (attrs.checksum, "checksum"),
+            (attrs.empty_as_default, "empty_as_default"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("tagged_format cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.pretty && attrs.formatter.is_some() {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "pretty and formatter cannot be combined, use serde_json::ser::PrettyFormatter",
+        ));
+    }
+
+    if attrs.canonical {
+        for (set, name) in [
+            (attrs.pretty, "pretty"),
+            (attrs.formatter.is_some(), "formatter"),
+            (attrs.sort_keys, "sort_keys"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("canonical cannot be combined with {name}, it fixes how the JSON is written"),
+                ));
+            }
+        }
+    }
+
+    if attrs.reuse_buffer {
+        for (set, name) in [
+            (attrs.atomic_write, "atomic_write"),
+            (attrs.size_hint.is_some(), "size_hint"),
+            (attrs.checksum, "checksum"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("reuse_buffer cannot be combined with {name}, which buffers the value itself"),
+                ));
+            }
+        }
+    }
+
+    if attrs.enum_discriminant {
+        if attrs.sql_type != SqlType::Binary {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "enum_discriminant needs #[diesel(sql_type = Binary)]: the discriminant \
+                 byte is no JSON, so JSON and JSONB columns reject it",
+            ));
+        }
+        for (set, name) in [
+            (attrs.format != Format::Json, "format"),
+            (attrs.schema_version.is_some(), "schema_version"),
+            (attrs.lenient, "lenient"),
+            (attrs.strict, "strict"),
+            (attrs.null_sentinel.is_some(), "null_sentinel"),
+            (attrs.sort_keys, "sort_keys"),
+            (attrs.canonical, "canonical"),
+            (attrs.max_depth.is_some(), "max_depth"),
+            (attrs.streaming, "streaming"),
+            (attrs.ignore_trailing, "ignore_trailing"),
+            (attrs.checksum, "checksum"),
+            (attrs.compress, "compress"),
+            (attrs.atomic_write, "atomic_write"),
+            (attrs.reuse_buffer, "reuse_buffer"),
+            (attrs.size_hint.is_some(), "size_hint"),
+            (attrs.max_bytes.is_some(), "max_bytes"),
+            (attrs.validate_on_write, "validate_on_write"),
+            (attrs.detect_version_byte, "detect_version_byte"),
+            (attrs.owned_read, "owned_read"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("enum_discriminant cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.lenient {
+        for (set, name) in [
+            (attrs.transparent_value, "transparent_value"),
+            (attrs.schema_version.is_some(), "schema_version"),
+            (attrs.streaming, "streaming"),
+            (attrs.ignore_trailing, "ignore_trailing"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("lenient cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.allow_unknown_fields {
+        if target.raw_json {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "allow_unknown_fields reads the JSON through a serde_json::Value, \
+                 which has no RawValue",
+            ));
+        }
+        for (set, name) in [
+            (attrs.enum_discriminant, "enum_discriminant"),
+            (attrs.lenient, "lenient"),
+            (attrs.schema_version.is_some(), "schema_version"),
+            (attrs.streaming, "streaming"),
+            (attrs.ignore_trailing, "ignore_trailing"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("allow_unknown_fields cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.schema_version.is_some() {
+        for (set, name) in [
+            (attrs.transparent_value, "transparent_value"),
+            (attrs.streaming, "streaming"),
+            (attrs.ignore_trailing, "ignore_trailing"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("schema_version cannot be combined with {name}"),
+                ));
+            }
+        }
+    } else if attrs.migrate.is_some() {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "migrate needs a schema_version to migrate to",
+        ));
+    }
+
+    if attrs.detect_version_byte {
+        if let Some(version) = attrs.version.filter(|&version| could_start_json(version)) {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                format!(
+                    "detect_version_byte cannot tell version {version} apart from the start \
+                     of JSON without a version byte"
+                ),
+            ));
+        }
+    }
+
+    if attrs.accept_any_version && attrs.version_check.is_some() {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "accept_any_version and version_check cannot be combined",
+        ));
+    }
+
+    if attrs.accept_any_version && attrs.default_on_unknown_version {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "unknown_version = \"default\" cannot be combined with accept_any_version, \
+             which accepts every version",
+        ));
+    }
+
+    if attrs.serialize_only && attrs.deserialize_only {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "serialize_only and deserialize_only cannot be combined",
+        ));
+    }
+
+    if attrs.owned_read {
+        for (set, name) in [
+            (attrs.serialize_only, "serialize_only"),
+            (attrs.transparent_value, "transparent_value"),
+        ] {
+            if set {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!("owned_read cannot be combined with {name}"),
+                ));
+            }
+        }
+    }
+
+    if attrs.serialize_bound.is_some() && attrs.deserialize_only {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "serialize_bound bounds the `ToSql` impl, which deserialize_only leaves out",
+        ));
+    }
+    if attrs.deserialize_bound.is_some() && attrs.serialize_only {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "deserialize_bound bounds the `FromSql` impl, which serialize_only leaves out",
+        ));
+    }
+
+    if attrs.nullable && attrs.serialize_only {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "nullable only adds a `FromSql` impl, it cannot be combined with serialize_only; \
+             `AsExpression` already writes the type to nullable columns",
+        ));
+    }
+
+    match (&attrs.backend, &attrs.raw_value) {
+        (Some(_), None) => {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "backend needs the raw value type of the backend, e.g. `value = MyValue`",
+            ));
+        }
+        (None, Some(_)) => {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "value needs the backend it belongs to, e.g. `backend = MyBackend`",
+            ));
+        }
+        (Some(_), Some(_)) if attrs.generic_backend => {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "backend and generic_backend cannot be combined",
+            ));
+        }
+        _ => {}
+    }
+
+    if attrs.backend_cfg.per_backend() {
+        if attrs.backend.is_some() || attrs.generic_backend {
+            return Err(syn::Error::new(
+                target.type_name.span(),
+                "backend_cfg(...) gates the impls of the backend features, \
+                 use backend_cfg = \"...\" for those of backend or generic_backend",
+            ));
+        }
+        for (cfg, feature, enabled) in [
+            (
+                &attrs.backend_cfg.postgres,
+                "postgres",
+                cfg!(feature = "postgres"),
+            ),
+            (
+                &attrs.backend_cfg.sqlite,
+                "sqlite",
+                cfg!(feature = "sqlite"),
+            ),
+            (&attrs.backend_cfg.mysql, "mysql", cfg!(feature = "mysql")),
+        ] {
+            if cfg.is_some() && !enabled {
+                return Err(syn::Error::new(
+                    target.type_name.span(),
+                    format!(
+                        "backend_cfg has a cfg for {feature}, enable the `{feature}` feature \
+                         of diesel-json-derive for its impls"
+                    ),
+                ));
+            }
+        }
+    }
+
+    if attrs.require_default && !attrs.needs_default() {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "require_default needs empty_as_default, null_sentinel, default_on_error, \
+             unknown_version = \"default\" or lenient",
+        ));
+    }
+
+    let assert_serde = target.assert_serde(attrs);
+    let assert_default = target.assert_default(attrs);
+
+    let mut backend_impls = Vec::new();
+    if let (Some(backend), Some(raw_value)) = (&attrs.backend, &attrs.raw_value) {
+        backend_impls.push(custom_impls(target, attrs, backend, raw_value));
+    } else if attrs.generic_backend {
+        backend_impls.push(generic_impls(target, attrs)?);
+    } else {
+        let cfgs = &attrs.backend_cfg;
+        if cfg!(feature = "postgres") {
+            backend_impls.push(with_cfg(&cfgs.postgres, pg_impls(target, attrs)));
+        }
+        if cfg!(feature = "sqlite") {
+            backend_impls.push(with_cfg(&cfgs.sqlite, sqlite_impls(target, attrs)));
+        }
+        // diesel has no `Jsonb` for MySQL, no query could use the impls
+        if cfg!(feature = "mysql") && attrs.sql_type != SqlType::Jsonb {
+            backend_impls.push(with_cfg(&cfgs.mysql, mysql_impls(target, attrs)));
+        }
+    }
+    let cfg = attrs
+        .backend_cfg
+        .all
+        .as_ref()
+        .map(|cfg| quote!(#[cfg(#cfg)]));
+    // An anonymous const keeps the helpers local without having to name (and
+    // possibly collide with) a module. Nothing is imported and every path is
+    // spelled out in full, so that a type of the user's named like a diesel
+    // item, e.g. `Text`, is never shadowed and lints in strict crates don't
+    // complain. The doc comment tells the blocks apart in `cargo expand`
+    // output.
+    let sql_type = attrs.sql_type;
+    let doc = format!(
+        " diesel-json-derive impls of `{}` for `{}`.",
+        target.name(),
+        quote!(#sql_type)
+    );
+    let expanded = quote! {
+        #[doc = #doc]
+        #cfg
+        #[allow(unused_qualifications)]
+        const _: () = {
+            #assert_serde
+            #assert_default
+
+            #(#backend_impls)*
+        };
+    };
+    if attrs.debug {
+        eprintln!("diesel_json: impls for {}:\n{}", target.type_name, expanded);
+    }
+    Ok(expanded)
+}
+
+/// `impls` in a block of their own behind the user's `cfg`, if there is one.
+fn with_cfg(cfg: &Option<syn::Meta>, impls: TokenStream) -> TokenStream {
+    match cfg {
+        Some(cfg) => quote! {
+            #[cfg(#cfg)]
+            const _: () = {
+                #impls
+            };
+        },
+        None => impls,
+    }
+}
+
+/// `#[diesel_jsonb]`: adds the `AsExpression` and `FromSqlRow` derives (just
+/// one of them for `serialize_only` and `deserialize_only`) and the
+/// `sql_type` attribute diesel needs, then generates the same impls as the
+/// derive.
+pub(crate) fn attribute(args: TokenStream, input: &DeriveInput) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(syn::Error::new_spanned(
+            args,
+            "diesel_jsonb takes no arguments, use #[diesel_json(...)] to configure it",
+        ));
+    }
+
+    let attrs = Attrs::from_attributes(&input.attrs)?;
+    let diesel = &attrs.diesel_path;
+    let sql_type_attr = if attrs.has_sql_type_attr {
+        quote! {}
+    } else if attrs.sql_types.is_empty() {
+        quote! { #[diesel(sql_type = #diesel::sql_types::Jsonb)] }
+    } else {
+        let sql_types = &attrs.sql_types;
+        quote! { #(#[diesel(sql_type = #diesel::sql_types::#sql_types)])* }
+    };
+
+    let as_expression = attrs.writes().then(|| quote!(#diesel::AsExpression));
+    let from_sql_row = attrs.reads().then(|| quote!(#diesel::FromSqlRow));
+    let derives = as_expression.into_iter().chain(from_sql_row);
+
+    let impls = derive(input)?;
+
+    // the helper attributes are only known to the derive
+    let mut item = input.clone();
+    item.attrs
+        .retain(|attr| !attr.path().is_ident("diesel_json"));
+
+    Ok(quote! {
+        #[derive(#(#derives),*)]
+        #sql_type_attr
+        #item
+
+        #impls
+    })
+}
+
+/// `#[diesel_jsonb_model]`: replaces `#[diesel_jsonb]` on the fields of a
+/// model with the `#[diesel(sql_type = ...)]` that `QueryableByName` reads
+/// them by, `Nullable<...>` for `Option` fields. Attribute macros can't be put
+/// on fields, the one on the struct removes theirs before the derives see
+/// them.
+pub(crate) fn model(args: TokenStream, input: &DeriveInput) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(syn::Error::new_spanned(
+            args,
+            "diesel_jsonb_model takes no arguments, mark the fields with #[diesel_jsonb]",
+        ));
+    }
+    let mut item = input.clone();
+    let Data::Struct(data) = &mut item.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "diesel_jsonb_model only applies to structs",
+        ));
+    };
+    let diesel = &Attrs::default().diesel_path;
+    let mut marked = 0;
+    for field in data.fields.iter_mut() {
+        let Some(index) = field
+            .attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("diesel_jsonb"))
+        else {
+            continue;
+        };
+        let attr = field.attrs.remove(index);
+        let sql_type = field_sql_type(&attr)?;
+        if let Some(diesel_attr) = field.attrs.iter().find(|attr| has_sql_type(attr)) {
+            return Err(syn::Error::new_spanned(
+                diesel_attr,
+                "the field has a sql_type already, #[diesel_jsonb] adds it",
+            ));
+        }
+        let sql_type = if option_type(&field.ty) {
+            quote!(#diesel::sql_types::Nullable<#diesel::sql_types::#sql_type>)
+        } else {
+            quote!(#diesel::sql_types::#sql_type)
+        };
+        field
+            .attrs
+            .push(parse_quote!(#[diesel(sql_type = #sql_type)]));
+        marked += 1;
+    }
+    if marked == 0 {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "no field is marked with #[diesel_jsonb]",
+        ));
+    }
+    Ok(quote!(#item))
+}
+
+/// Whether `attr` is `#[diesel(...)]` with a `sql_type`.
+fn has_sql_type(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("diesel")
+        && attr.meta.require_list().is_ok_and(|list| {
+            list.tokens
+                .clone()
+                .into_iter()
+                .any(|token| matches!(token, TokenTree::Ident(ident) if ident == "sql_type"))
+        })
+}
+
+/// Whether the field's type is an `Option`, which diesel loads from a
+/// nullable column.
+fn option_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// The type the impls are generated for. The generics of the `ToSql` and
+/// `FromSql` impls carry the bounds those impls need (serde and, for `ToSql`,
+/// `Debug`).
+struct Target {
+    type_name: Ident,
+    ty: TokenStream,
+    /// The type `FromSql` is implemented for, `ty` with `'static` lifetimes
+    /// for `owned_read`.
+    from_sql_ty: TokenStream,
+    to_sql_generics: Generics,
+    from_sql_generics: Generics,
+    serde_generics: Generics,
+    /// With `transparent_value`, the field that is stored instead of the
+    /// type itself.
+    transparent: Option<(Member, Type)>,
+    /// With `enum_discriminant`, the variants of the enum, in the order of
+    /// their discriminants.
+    variants: Vec<Variant>,
+    /// Whether a field is a `RawValue`, or `raw_value` says the type holds
+    /// one, for which the `simd-json` and `sonic-rs` features are ignored.
+    raw_json: bool,
+}
+
+impl Target {
+    fn new(input: &DeriveInput, transparent: Option<(Member, Type)>) -> Self {
+        let type_name = input.ident.clone();
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        let ty = quote!(#type_name #ty_generics);
+        Self::with_generics(type_name, ty, &input.generics, transparent)
+    }
+
+    /// A type named by path, e.g. `other::Bar` or `Envelope<Bar>`, with the
+    /// generics of `impl_diesel_jsonb!(impl<T> for Foo<T>)`, if any.
+    fn from_path(path: &Path, generics: &Generics) -> Self {
+        let type_name = path
+            .segments
+            .last()
+            .expect("a path has at least one segment")
+            .ident
+            .clone();
+        Self::with_generics(type_name, quote!(#path), generics, None)
+    }
+
+    fn with_generics(
+        type_name: Ident,
+        ty: TokenStream,
+        generics: &Generics,
+        transparent: Option<(Member, Type)>,
+    ) -> Self {
+        let serialized = match &transparent {
+            Some((_, field_ty)) => quote!(#field_ty),
+            None => ty.clone(),
+        };
+        let to_sql_generics = with_bounds(
+            generics,
+            [
+                quote!(#serialized: ::serde::Serialize),
+                quote!(#ty: ::core::fmt::Debug),
+            ],
+        );
+        let from_sql_generics = with_bounds(
+            generics,
+            [quote!(#serialized: ::serde::de::DeserializeOwned)],
+        );
+        let serde_generics = with_bounds(
+            generics,
+            [quote!(#serialized: ::serde::Serialize + ::serde::de::DeserializeOwned)],
+        );
+        Self {
+            type_name,
+            from_sql_ty: ty.clone(),
+            ty,
+            to_sql_generics,
+            from_sql_generics,
+            serde_generics,
+            transparent,
+            variants: Vec::new(),
+            raw_json: false,
+        }
+    }
+
+    /// Keeps the variants for `enum_discriminant`, which stores the fields of
+    /// a variant rather than the enum. The impls are bounded by the types of
+    /// the fields, the enum needs no serde impls itself.
+    fn use_discriminants(&mut self, input: &DeriveInput) -> syn::Result<()> {
+        let Data::Enum(data) = &input.data else {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "enum_discriminant only applies to enums",
+            ));
+        };
+        if data.variants.is_empty() || data.variants.len() > 256 {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "enum_discriminant stores the variant in one byte, so it needs an enum \
+                 with 1 to 256 variants",
+            ));
+        }
+        // tuples implement serde's traits up to 16 elements
+        if let Some(variant) = data.variants.iter().find(|v| v.fields.len() > 16) {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                "enum_discriminant stores the fields of a variant as a tuple, which can \
+                 have at most 16 fields",
+            ));
+        }
+        let field_types: Vec<&Type> = data
+            .variants
+            .iter()
+            .flat_map(|variant| &variant.fields)
+            .map(|field| &field.ty)
+            .collect();
+        let ty = &self.ty;
+        let bounded = |bounds: TokenStream| {
+            field_types
+                .iter()
+                .map(move |field_ty| quote!(#field_ty: #bounds))
+                .collect::<Vec<_>>()
+        };
+        let mut to_sql_bounds = bounded(quote!(::serde::Serialize));
+        to_sql_bounds.push(quote!(#ty: ::core::fmt::Debug));
+        self.to_sql_generics = with_bounds(&input.generics, to_sql_bounds);
+        self.from_sql_generics = with_bounds(
+            &input.generics,
+            bounded(quote!(::serde::de::DeserializeOwned)),
+        );
+        self.serde_generics = with_bounds(
+            &input.generics,
+            bounded(quote!(::serde::Serialize + ::serde::de::DeserializeOwned)),
+        );
+        self.variants = data.variants.iter().cloned().collect();
+        Ok(())
+    }
+
+    /// `serialize_bound` and `deserialize_bound`: the user's predicates
+    /// instead of the serde bounds the derive infers, like serde's `bound`.
+    /// `ToSql` still needs the type to be `Debug`.
+    fn replace_bounds(&mut self, generics: &Generics, attrs: &Attrs) {
+        let bounded = |predicates: &[WherePredicate]| {
+            let mut generics = generics.clone();
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(predicates.iter().cloned());
+            generics
+        };
+        if let Some(predicates) = &attrs.serialize_bound {
+            let ty = &self.ty;
+            self.to_sql_generics = bounded(predicates);
+            self.to_sql_generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#ty: ::core::fmt::Debug));
+        }
+        if let Some(predicates) = &attrs.deserialize_bound {
+            self.from_sql_generics = bounded(predicates);
+        }
+        // the serde impls are asserted under the bounds of both impls,
+        // without repeating the type's own where clause
+        if attrs.serialize_bound.is_some() || attrs.deserialize_bound.is_some() {
+            let own = generics
+                .where_clause
+                .as_ref()
+                .map_or(0, |where_clause| where_clause.predicates.len());
+            let predicates: Vec<WherePredicate> = [&self.to_sql_generics, &self.from_sql_generics]
+                .into_iter()
+                .filter_map(|generics| generics.where_clause.as_ref())
+                .flat_map(|where_clause| where_clause.predicates.iter().skip(own).cloned())
+                .collect();
+            self.serde_generics = bounded(&predicates);
+        }
+    }
+
+    /// The name of the type in errors, without the `r#` of a raw identifier
+    /// like serde and `std::any::type_name` leave it out.
+    fn name(&self) -> String {
+        self.type_name.unraw().to_string()
+    }
+
+    /// The name read errors give the type, followed by the `context`, if any.
+    fn error_name(&self, attrs: &Attrs) -> String {
+        match &attrs.context {
+            Some(context) => format!("{} in column {}", self.name(), context.value()),
+            None => self.name(),
+        }
+    }
+
+    /// Implements `FromSql` for the type with `'static` for every lifetime
+    /// parameter, for `owned_read`.
+    fn read_owned(&mut self) {
+        let mut to_static = StaticLifetimes(
+            self.from_sql_generics
+                .lifetimes()
+                .map(|param| param.lifetime.clone())
+                .collect(),
+        );
+        let generics = &mut self.from_sql_generics;
+        generics.params = generics
+            .params
+            .iter()
+            .filter(|param| !matches!(param, GenericParam::Lifetime(_)))
+            .cloned()
+            .collect();
+        to_static.visit_generics_mut(generics);
+        let ty = &self.ty;
+        let mut ty: Type = parse_quote!(#ty);
+        to_static.visit_type_mut(&mut ty);
+        self.from_sql_ty = quote!(#ty);
+    }
+
+    /// Adds `#ty: Default` to the `FromSql` impl, for `empty_as_default`,
+    /// `null_sentinel`, `default_on_error`, `unknown_version` and `lenient`, which also needs
+    /// `#ty: Serialize` to serialize the default value and fill in missing
+    /// fields. With `require_default`, `assert_default` checks `Default`
+    /// instead, the impl's other bounds have to imply it.
+    fn require_default(&mut self, attrs: &Attrs) {
+        let ty = &self.from_sql_ty;
+        let mut bounds = Vec::new();
+        if attrs.needs_default() && !attrs.require_default {
+            bounds.push(quote!(::core::default::Default));
+        }
+        if attrs.lenient {
+            bounds.push(quote!(::serde::Serialize));
+        }
+        if !bounds.is_empty() {
+            self.from_sql_generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#ty: #(#bounds)+*));
+        }
+    }
+
+    /// For `require_default`, requires `Default` in a function of its own,
+    /// spanned at the type name like `assert_serde`, so that a missing impl
+    /// is an error naming the type rather than one about the bounds of the
+    /// generated `FromSql` impl.
+    fn assert_default(&self, attrs: &Attrs) -> TokenStream {
+        if !attrs.require_default || !attrs.reads() {
+            return quote!();
+        }
+        let ty = &self.from_sql_ty;
+        let (impl_generics, _, where_clause) = self.from_sql_generics.split_for_impl();
+        let assert = quote_spanned! {self.type_name.span()=>
+            __assert_default::<#ty>();
+        };
+        quote! {
+            #[allow(dead_code)]
+            fn __assert_default<T: ::core::default::Default>() {}
+
+            #[allow(dead_code)]
+            fn __assert_default_impl #impl_generics () #where_clause {
+                #assert
+            }
+        }
+    }
+
+    /// Requires the serde impls in a function of its own, spanned at the type
+    /// name. Without it a missing derive only shows up as errors inside the
+    /// generated `to_sql`/`from_sql` bodies. `serialize_only` and
+    /// `deserialize_only` types only need the one impl.
+    fn assert_serde(&self, attrs: &Attrs) -> TokenStream {
+        if attrs.enum_discriminant {
+            return quote!();
+        }
+        let ty = match &self.transparent {
+            Some((_, field_ty)) => quote!(#field_ty),
+            None => self.ty.clone(),
+        };
+        let serialize = quote!(::serde::Serialize);
+        let deserialize = quote!(::serde::de::DeserializeOwned);
+        if !attrs.reads() {
+            self.assert_impls(&self.to_sql_generics, &ty, serialize)
+        } else if !attrs.writes() {
+            self.assert_impls(&self.from_sql_generics, &self.from_sql_ty, deserialize)
+        } else if attrs.owned_read {
+            // `Deserialize` is only needed for the `'static` type
+            let assert_serialize = self.assert_impls(&self.to_sql_generics, &ty, serialize);
+            let assert_deserialize =
+                self.assert_impls(&self.from_sql_generics, &self.from_sql_ty, deserialize);
+            quote! {
+                const _: () = { #assert_serialize };
+                const _: () = { #assert_deserialize };
+            }
+        } else {
+            self.assert_impls(&self.serde_generics, &ty, quote!(#serialize + #deserialize))
+        }
+    }
+
+    /// `assert_serde` for one type and set of bounds.
+    fn assert_impls(
+        &self,
+        generics: &Generics,
+        ty: &TokenStream,
+        bounds: TokenStream,
+    ) -> TokenStream {
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let assert = quote_spanned! {self.type_name.span()=>
+            __assert_serde::<#ty>();
+        };
+        quote! {
+            #[allow(dead_code)]
+            fn __assert_serde<T: #bounds>() {}
+
+            #[allow(dead_code)]
+            fn __assert_serde_impls #impl_generics () #where_clause {
+                #assert
+            }
+        }
+    }
+
+    /// `#[automatically_derived] impl<..> ToSql<#sql_type, #backend> for #ty where ..`
+    fn impl_to_sql(
+        &self,
+        diesel: &Path,
+        sql_type: TokenStream,
+        backend: TokenStream,
+    ) -> TokenStream {
+        let ty = &self.ty;
+        let (impl_generics, _, where_clause) = self.to_sql_generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #diesel::serialize::ToSql<#sql_type, #backend> for #ty #where_clause
+        }
+    }
+
+    /// `#[automatically_derived] impl<..> FromSql<#sql_type, #backend> for #ty where ..`
+    fn impl_from_sql(
+        &self,
+        diesel: &Path,
+        sql_type: TokenStream,
+        backend: TokenStream,
+    ) -> TokenStream {
+        let ty = &self.from_sql_ty;
+        let (impl_generics, _, where_clause) = self.from_sql_generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #diesel::deserialize::FromSql<#sql_type, #backend> for #ty #where_clause
+        }
+    }
+
+    /// Reference to what is serialized, `self` or the `transparent_value`
+    /// field, with `schema_version` wrapped in a `Versioned` and with
+    /// `sort_keys` turned into a `serde_json::Value` with sorted objects.
+    fn value_ref(&self, attrs: &Attrs) -> TokenStream {
+        let krate = &attrs.crate_path;
+        let value = match &self.transparent {
+            Some((member, _)) => quote!(&self.#member),
+            None => quote!(self),
+        };
+        let value = match attrs.schema_version {
+            Some(schema_version) => {
+                quote!(&#krate::__private::Versioned::new(#schema_version, #value))
+            }
+            None => value,
+        };
+        if attrs.sort_keys {
+            quote!(&#krate::__private::to_sorted_value(#value)?)
+        } else {
+            value
+        }
+    }
+
+    /// Closure for `map_err` turning a decoding error into the deserialize
+    /// error, naming the type so that the failing column can be found when a
+    /// row contains several JSON values.
+    fn invalid_value(&self, attrs: &Attrs) -> TokenStream {
+        let krate = &attrs.crate_path;
+        let type_name = self.name();
+        let format = attrs.format.name();
+        quote! {
+            |e| ::core::convert::Into::into(#krate::DieselJsonError::Deserialize {
+                type_name: #type_name,
+                format: #format,
+                source: ::core::convert::Into::into(e),
+            })
+        }
+    }
+}
+
+/// Replaces the given lifetimes with `'static`.
+struct StaticLifetimes(Vec<Lifetime>);
+
+impl VisitMut for StaticLifetimes {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if self.0.contains(lifetime) {
+            *lifetime = parse_quote!('static);
+        }
+    }
+}
+
+/// Adds `predicates` to the where clause, keeping the user's bounds. They
+/// bound the type itself, e.g. `Holder<T>: Serialize`, not every type
+/// parameter: serde's derive knows which parameters need its traits, and a
+/// field of `T::Assoc` or `PhantomData<T>` doesn't need them on `T`. Types
+/// without type parameters get no bounds, `assert_serde` reports what they
+/// are missing.
+fn with_bounds(generics: &Generics, predicates: impl IntoIterator<Item = TokenStream>) -> Generics {
+    let mut generics = generics.clone();
+    if generics.type_params().next().is_none() {
+        return generics;
+    }
+    let where_clause = generics.make_where_clause();
+    for predicate in predicates {
+        where_clause.predicates.push(parse_quote!(#predicate));
+    }
+    generics
+}
+
+/// Postgres stores JSONB values with a leading format version byte (currently
+/// always `JSONB_VERSION`, configurable with `#[diesel_json(version = N)]`)
+/// followed by the textual JSON. JSON values are just the text.
+fn pg_impls(target: &Target, attrs: &Attrs) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    raw_bytes_impls(
+        target,
+        attrs,
+        quote!(#diesel::pg::Pg),
+        quote!(#diesel::pg::PgValue),
+        true,
+    )
+}
+
+/// `#[diesel_json(backend = ..., value = ...)]`: the Postgres impls for
+/// another backend. Its bind collector has to be diesel's
+/// `RawBytesBindCollector`, so that the output is a `Write`, and the raw
+/// value has to implement the facade's `JsonbRawValue` like `PgValue`.
+/// `Jsonb` gets the version byte as for Postgres.
+fn custom_impls(target: &Target, attrs: &Attrs, backend: &Path, raw_value: &Path) -> TokenStream {
+    raw_bytes_impls(target, attrs, quote!(#backend), quote!(#raw_value), false)
+}
+
+/// The impls for a backend writing into and reading from raw bytes,
+/// `PgValue` or the like. Only Postgres itself (`pg`) reads through
+/// `jsonb_from_pg_value`.
+fn raw_bytes_impls(
+    target: &Target,
+    attrs: &Attrs,
+    backend: TokenStream,
+    raw_value: TokenStream,
+    pg: bool,
+) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    let sql_type = attrs.sql_type;
+    let krate = &attrs.crate_path;
+    let impl_to_sql = target.impl_to_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+    let impl_from_sql = target.impl_from_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+    let write_value = write_value(target, attrs, quote!(out));
+    let raw_bytes = quote!(#krate::JsonbRawValue::jsonb_bytes(&bytes));
+
+    let (write_payload, read_payload) = match sql_type {
+        SqlType::Jsonb => {
+            let read_payload = if pg && reads_default_jsonb(target, attrs) {
+                quote! {
+                    #krate::jsonb_from_pg_value(bytes)
+                }
+            } else {
+                let read_jsonb = read_jsonb(target, attrs);
+                quote! {
+                    let bytes = #raw_bytes;
+                    #read_jsonb
+                }
+            };
+            (write_jsonb(target, attrs), read_payload)
+        }
+        SqlType::Json | SqlType::Text | SqlType::Binary => (
+            quote! {
+                #write_value
+                ::core::result::Result::Ok(#diesel::serialize::IsNull::No)
+            },
+            read_value(target, attrs, raw_bytes.clone()),
+        ),
+    };
+
+    let pre_serialize = pre_serialize(target, attrs);
+    let to_sql = attrs.writes().then(|| {
+        quote! {
+            #impl_to_sql {
+                fn to_sql<'__b>(&'__b self, out: &mut #diesel::serialize::Output<'__b, '_, #backend>) -> #diesel::serialize::Result {
+                    #pre_serialize
+                    #write_payload
+                }
+            }
+        }
+    });
+    let read_payload = with_context(target, attrs, read_payload);
+    let read_payload = log_error(target, attrs, raw_bytes, read_payload);
+    let read_payload = or_default(attrs, read_payload);
+    let from_sql = attrs.reads().then(|| {
+        quote! {
+            #impl_from_sql {
+                fn from_sql(bytes: #raw_value<'_>) -> #diesel::deserialize::Result<Self> {
+                    #read_payload
+                }
+            }
+        }
+    });
+
+    let nullable = nullable_from_sql(target, attrs, backend);
+
+    quote! {
+        #to_sql
+        #from_sql
+        #nullable
+    }
+}
+
+/// `#[diesel_json(nullable)]`: `FromSql<Nullable<#sql_type>>` for the type,
+/// reading what isn't `NULL` like `FromSql<#sql_type>`. diesel implements it
+/// for `Option<T>` only, which stays the way to load `NULL`s, and its
+/// `AsExpression` derive already covers `ToSql`. A `NULL` fails with an
+/// error naming the type instead of diesel's `UnexpectedNullError`.
+fn nullable_from_sql(target: &Target, attrs: &Attrs, backend: TokenStream) -> Option<TokenStream> {
+    let diesel = &attrs.diesel_path;
+    let sql_type = attrs.sql_type;
+    let impl_from_sql = target.impl_from_sql(
+        diesel,
+        quote!(#diesel::sql_types::Nullable<#diesel::sql_types::#sql_type>),
+        backend.clone(),
+    );
+    nullable_impl(target, attrs, impl_from_sql, backend)
+}
+
+/// The body of the `nullable` impl behind `impl_from_sql`.
+fn nullable_impl(
+    target: &Target,
+    attrs: &Attrs,
+    impl_from_sql: TokenStream,
+    backend: TokenStream,
+) -> Option<TokenStream> {
+    if !attrs.nullable || !attrs.reads() {
+        return None;
+    }
+    let sql_type = attrs.sql_type;
+    let krate = &attrs.crate_path;
+    let diesel = &attrs.diesel_path;
+    let type_name = target.error_name(attrs);
+    Some(quote! {
+        #impl_from_sql {
+            fn from_sql(value: <#backend as #diesel::backend::Backend>::RawValue<'_>) -> #diesel::deserialize::Result<Self> {
+                <Self as #diesel::deserialize::FromSql<#diesel::sql_types::#sql_type, #backend>>::from_sql(value)
+            }
+
+            fn from_nullable_sql(
+                value: ::core::option::Option<<#backend as #diesel::backend::Backend>::RawValue<'_>>,
+            ) -> #diesel::deserialize::Result<Self> {
+                match value {
+                    ::core::option::Option::Some(value) => {
+                        <Self as #diesel::deserialize::FromSql<#diesel::sql_types::#sql_type, #backend>>::from_sql(value)
+                    }
+                    ::core::option::Option::None => ::core::result::Result::Err(
+                        ::core::convert::Into::into(#krate::DieselJsonError::UnexpectedNull {
+                            type_name: #type_name,
+                        }),
+                    ),
+                }
+            }
+        }
+    })
+}
+
+/// The version byte of a JSONB value, `version` or the facade's
+/// `JSONB_VERSION`.
+fn jsonb_version(attrs: &Attrs) -> TokenStream {
+    let krate = &attrs.crate_path;
+    match attrs.version {
+        Some(version) => quote!(#version),
+        None => quote!(#krate::JSONB_VERSION),
+    }
+}
+
+/// Statements writing `self` as a JSONB value into the writer `out`, the
+/// version byte and the JSON, evaluating to a `serialize::Result`.
+fn write_jsonb(target: &Target, attrs: &Attrs) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    let krate = &attrs.crate_path;
+    let version = jsonb_version(attrs);
+    let value = target.value_ref(attrs);
+    if writes_default_jsonb(target, attrs) {
+        return quote! {
+            #krate::to_jsonb(#value, out)
+        };
+    }
+    // The buffered paths put the version byte into the buffer as well, so
+    // that the output is allocated once at its full size. Written on its own,
+    // the byte gets an allocation that the JSON then has to grow.
+    let check_size = check_size(target, attrs, quote!(buf));
+    let check_json = check_json(target, attrs, quote!(&buf[1..]));
+    let write_value = if attrs.reuse_buffer {
+        let to_writer = to_writer(target, attrs, quote!(&mut *buf), value);
+        quote! {
+            #krate::__private::with_scratch_buffer(|buf| -> ::core::result::Result<
+                (),
+                ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync>,
+            > {
+                buf.push(#version);
+                #to_writer?;
+                #check_size
+                #check_json
+                ::std::io::Write::write_all(out, buf)?;
+                ::core::result::Result::Ok(())
+            })?;
+        }
+    } else if attrs.atomic_write
+        || attrs.size_hint.is_some()
+        || attrs.max_bytes.is_some()
+        || attrs.validate_on_write
+    {
+        // 128 is what `serde_json::to_vec` starts with
+        let capacity = match attrs.size_hint {
+            Some(size_hint) => quote!(1 + #size_hint),
+            None => quote!(128),
+        };
+        let to_writer = to_writer(target, attrs, quote!(&mut buf), value);
+        quote! {
+            let mut buf = ::std::vec::Vec::with_capacity(#capacity);
+            buf.push(#version);
+            #to_writer?;
+            #check_size
+            #check_json
+            ::std::io::Write::write_all(out, &buf)?;
+        }
+    } else {
+        let write_value = write_value(target, attrs, quote!(out));
+        quote! {
+            ::std::io::Write::write_all(out, &[#version])?;
+            #write_value
+        }
+    };
+    quote! {
+        #write_value
+        ::core::result::Result::Ok(#diesel::serialize::IsNull::No)
+    }
+}
+
+/// Statements reading `Self` from the bytes of a JSONB value, `bytes: &[u8]`,
+/// version byte included, evaluating to a `deserialize::Result<Self>`.
+fn read_jsonb(target: &Target, attrs: &Attrs) -> TokenStream {
+    let krate = &attrs.crate_path;
+    let on_empty = attrs.empty_as_default.then(|| {
+        quote! {
+            if bytes.is_empty() {
+                return ::core::result::Result::Ok(<Self as ::core::default::Default>::default());
+            }
+        }
+    });
+    // just a version byte that is accepted counts as empty too, any other
+    // single byte fails like the version byte of a value would
+    let on_version_only = attrs.empty_as_default.then(|| {
+        // `version_check` has been called already
+        let accepted = if attrs.accept_any_version || attrs.version_check.is_some() {
+            quote!()
+        } else {
+            let version = jsonb_version(attrs);
+            quote!(&& bytes[0] == #version)
+        };
+        quote! {
+            if bytes.len() == 1 #accepted {
+                return ::core::result::Result::Ok(<Self as ::core::default::Default>::default());
+            }
+        }
+    });
+    let check_version = match &attrs.version_check {
+        // rejected versions are read as the default
+        _ if attrs.default_on_unknown_version => {
+            let accepted = match &attrs.version_check {
+                Some(check) => quote!(#check(found)),
+                None => {
+                    let version = jsonb_version(attrs);
+                    quote!(found == #version)
+                }
+            };
+            quote! {
+                if let ::core::option::Option::Some(&found) = bytes.first() {
+                    if !(#accepted) {
+                        return ::core::result::Result::Ok(<Self as ::core::default::Default>::default());
+                    }
+                }
+            }
+        }
+        Some(check) => quote! {
+            if let ::core::option::Option::Some(&found) = bytes.first() {
+                if !#check(found) {
+                    return ::core::result::Result::Err(::core::convert::Into::into(
+                        #krate::DieselJsonError::UnsupportedVersion(found),
+                    ));
+                }
+            }
+        },
+        None => quote! {},
+    };
+    let version = if attrs.accept_any_version || attrs.version_check.is_some() {
+        quote!(::core::option::Option::None)
+    } else {
+        let version = jsonb_version(attrs);
+        quote!(::core::option::Option::Some(#version))
+    };
+    let read_value = read_value(target, attrs, quote!(bytes));
+    if attrs.detect_version_byte {
+        return quote! {
+            let unprefixed = #krate::__private::lacks_version_byte(bytes, #version);
+            #on_empty
+            let bytes = if unprefixed {
+                bytes
+            } else {
+                #check_version
+                #on_version_only
+                #krate::jsonb_payload(bytes, #version)?
+            };
+            #read_value
+        };
+    }
+    quote! {
+        #on_empty
+        #check_version
+        #on_version_only
+        let bytes = #krate::jsonb_payload(bytes, #version)?;
+        #read_value
+    }
+}
+
+/// The bytes the facade's `lacks_version_byte` takes for the start of JSON.
+fn could_start_json(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n' | b' ' | b'\t' | b'\n' | b'\r'
+    )
+}
+
+/// Whether the version byte is the facade's `JSONB_VERSION`, which is 1.
+fn is_default_version(attrs: &Attrs) -> bool {
+    matches!(attrs.version, None | Some(1))
+}
+
+/// Whether `to_jsonb` writes the value the way the options ask for. It
+/// streams the JSON straight into diesel's output, which is a `Vec` itself,
+/// so nothing is buffered twice. `examples/write_memory.rs` of the test crate
+/// measures this against `atomic_write` and `size_hint`: buffering first
+/// needs about a fourth more memory at its peak, unless the hint fits.
+fn writes_default_jsonb(target: &Target, attrs: &Attrs) -> bool {
+    attrs.format == Format::Json
+        && is_default_version(attrs)
+        && !attrs.pretty
+        && attrs.formatter.is_none()
+        && !attrs.sort_keys
+        && !attrs.canonical
+        && !attrs.atomic_write
+        && !attrs.reuse_buffer
+        && attrs.size_hint.is_none()
+        && attrs.max_bytes.is_none()
+        && !attrs.validate_on_write
+        && (target.raw_json || !cfg!(feature = "sonic-rs"))
+}
+
+/// Whether `jsonb_from_pg_value` reads the value the way the options ask for.
+fn reads_default_jsonb(target: &Target, attrs: &Attrs) -> bool {
+    attrs.format == Format::Json
+        && is_default_version(attrs)
+        && !attrs.accept_any_version
+        && attrs.version_check.is_none()
+        && !attrs.default_on_unknown_version
+        && !attrs.detect_version_byte
+        && !attrs.streaming
+        && !attrs.intern_strings
+        && !attrs.ignore_trailing
+        && !attrs.empty_as_default
+        && attrs.null_sentinel.is_none()
+        && !attrs.strict
+        && !attrs.lenient
+        && !attrs.allow_unknown_fields
+        && attrs.max_depth.is_none()
+        && attrs.validate.is_none()
+        && attrs.schema_version.is_none()
+        && target.transparent.is_none()
+        && (target.raw_json || !cfg!(feature = "simd-json") && !cfg!(feature = "sonic-rs"))
+}
+
+/// SQLite has no version prefix. JSONB and binary values are bound as blobs,
+/// JSON and text as text, and all of them are read as blobs.
+fn sqlite_impls(target: &Target, attrs: &Attrs) -> TokenStream {
+    let sql_type = attrs.sql_type;
+    let diesel = &attrs.diesel_path;
+    let backend = quote!(#diesel::sqlite::Sqlite);
+    let impl_to_sql = target.impl_to_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+    let impl_from_sql = target.impl_from_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+
+    let krate = &attrs.crate_path;
+    let type_name = target.name();
+    let value_vec = value_vec(target, attrs);
+    let check_size = check_size(target, attrs, quote!(buf));
+    let write_value = match sql_type {
+        // SQLite's JSONB is a binary encoding of its own, which its JSON
+        // functions and diesel's `serde_json::Value` read
+        SqlType::Jsonb => quote! {
+            let buf = #value_vec;
+            #check_size
+            out.set_value(#krate::__private::sqlite_jsonb_from_json(&buf, #type_name)?);
+        },
+        SqlType::Binary => quote! {
+            let buf = #value_vec;
+            #check_size
+            out.set_value(buf);
+        },
+        SqlType::Json | SqlType::Text => quote! {
+            let buf = #value_vec;
+            #check_size
+            out.set_value(::std::string::String::from_utf8(buf)?);
+        },
+    };
+    // Text is read as a blob as well: SQLite replaces what isn't UTF-8 when
+    // reading text, the bytes as they are are left for `read_value` to check.
+    let read_value = read_value(target, attrs, quote!(&bytes));
+    let read_value = with_context(target, attrs, read_value);
+    let read_value = log_error(target, attrs, quote!(&bytes), read_value);
+    let decode_jsonb = (sql_type == SqlType::Jsonb).then(|| {
+        quote! {
+            let bytes = #krate::__private::sqlite_jsonb_to_json(&bytes, #type_name)?;
+        }
+    });
+    let read_payload = quote! {
+        let bytes = <::std::vec::Vec<u8> as #diesel::deserialize::FromSql<#diesel::sql_types::Binary, #backend>>::from_sql(value)?;
+        #decode_jsonb
+        #read_value
+    };
+
+    let pre_serialize = pre_serialize(target, attrs);
+    let to_sql = attrs.writes().then(|| {
+        quote! {
+            #impl_to_sql {
+                fn to_sql<'__b>(&'__b self, out: &mut #diesel::serialize::Output<'__b, '_, #backend>) -> #diesel::serialize::Result {
+                    #pre_serialize
+                    #write_value
+                    ::core::result::Result::Ok(#diesel::serialize::IsNull::No)
+                }
+            }
+        }
+    });
+    let read_payload = or_default(attrs, read_payload);
+    let from_sql = attrs.reads().then(|| {
+        quote! {
+            #impl_from_sql {
+                fn from_sql(value: #diesel::sqlite::SqliteValue<'_, '_, '_>) -> #diesel::deserialize::Result<Self> {
+                    #read_payload
+                }
+            }
+        }
+    });
+
+    let nullable = nullable_from_sql(target, attrs, backend);
+
+    quote! {
+        #to_sql
+        #from_sql
+        #nullable
+    }
+}
+
+/// MySQL has no version prefix either, the value is the raw JSON. Only for
+/// `Json`, `Text` and `Binary`, MySQL has no `Jsonb`.
+fn mysql_impls(target: &Target, attrs: &Attrs) -> TokenStream {
+    let sql_type = attrs.sql_type;
+    let diesel = &attrs.diesel_path;
+    let backend = quote!(#diesel::mysql::Mysql);
+    let impl_to_sql = target.impl_to_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+    let impl_from_sql = target.impl_from_sql(
+        diesel,
+        quote!(#diesel::sql_types::#sql_type),
+        backend.clone(),
+    );
+    let write_value = write_value(target, attrs, quote!(out));
+    let krate = &attrs.crate_path;
+    let raw_bytes = quote!(#krate::JsonbRawValue::jsonb_bytes(&value));
+    let read_value = read_value(target, attrs, raw_bytes.clone());
+    let read_value = with_context(target, attrs, read_value);
+    let read_value = log_error(target, attrs, raw_bytes, read_value);
+    let pre_serialize = pre_serialize(target, attrs);
+    let to_sql = attrs.writes().then(|| {
+        quote! {
+            #impl_to_sql {
+                fn to_sql<'__b>(&'__b self, out: &mut #diesel::serialize::Output<'__b, '_, #backend>) -> #diesel::serialize::Result {
+                    #pre_serialize
+                    #write_value
+                    ::core::result::Result::Ok(#diesel::serialize::IsNull::No)
+                }
+            }
+        }
+    });
+    let read_value = or_default(attrs, read_value);
+    let from_sql = attrs.reads().then(|| {
+        quote! {
+            #impl_from_sql {
+                fn from_sql(value: #diesel::mysql::MysqlValue<'_>) -> #diesel::deserialize::Result<Self> {
+                    #read_value
+                }
+            }
+        }
+    });
+
+    let nullable = nullable_from_sql(target, attrs, backend);
+
+    quote! {
+        #to_sql
+        #from_sql
+        #nullable
+    }
+}
+
+/// `#[diesel_json(generic_backend)]`: impls generic over the backend, for
+/// every backend that collects binds as raw bytes (Postgres, MySQL and custom
+/// backends, but not SQLite). Nothing but the JSON is written, so this only
+/// works for `Json`, `Text` and `Binary`, Postgres needs the version prefix
+/// for `Jsonb`.
+fn generic_impls(target: &Target, attrs: &Attrs) -> syn::Result<TokenStream> {
+    if let SqlType::Jsonb = attrs.sql_type {
+        return Err(syn::Error::new(
+            target.type_name.span(),
+            "generic_backend writes no JSONB version prefix, use #[diesel(sql_type = Json)]",
+        ));
+    }
+
+    let ty = &target.ty;
+    let from_sql_ty = &target.from_sql_ty;
+    let sql_type = attrs.sql_type;
+    let diesel = &attrs.diesel_path;
+    let write_value = write_value(target, attrs, quote!(out));
+    let read_value = read_value(target, attrs, quote!(&bytes));
+    let read_value = with_context(target, attrs, read_value);
+    let read_value = log_error(target, attrs, quote!(&bytes), read_value);
+
+    let mut to_sql_generics = target.to_sql_generics.clone();
+    to_sql_generics.params.push(parse_quote!(__DB));
+    to_sql_generics.make_where_clause().predicates.push(parse_quote! {
+        for<'__c> __DB: #diesel::backend::Backend<
+            BindCollector<'__c> = #diesel::query_builder::bind_collector::RawBytesBindCollector<__DB>,
+        >
+    });
+    let (to_sql_impl_generics, _, to_sql_where_clause) = to_sql_generics.split_for_impl();
+
+    let mut from_sql_generics = target.from_sql_generics.clone();
+    from_sql_generics.params.push(parse_quote!(__DB));
+    let where_clause = from_sql_generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(__DB: #diesel::backend::Backend));
+    where_clause
+        .predicates
+        .push(parse_quote!(::std::vec::Vec<u8>: #diesel::deserialize::FromSql<#diesel::sql_types::Binary, __DB>));
+    let (from_sql_impl_generics, _, from_sql_where_clause) = from_sql_generics.split_for_impl();
+
+    let pre_serialize = pre_serialize(target, attrs);
+    let to_sql = attrs.writes().then(|| {
+        quote! {
+            #[automatically_derived]
+            impl #to_sql_impl_generics #diesel::serialize::ToSql<#diesel::sql_types::#sql_type, __DB> for #ty #to_sql_where_clause {
+                fn to_sql<'__b>(&'__b self, out: &mut #diesel::serialize::Output<'__b, '_, __DB>) -> #diesel::serialize::Result {
+                    #pre_serialize
+                    #write_value
+                    ::core::result::Result::Ok(#diesel::serialize::IsNull::No)
+                }
+            }
+        }
+    });
+    let read_value = or_default(
+        attrs,
+        quote! {
+            let bytes = <::std::vec::Vec<u8> as #diesel::deserialize::FromSql<#diesel::sql_types::Binary, __DB>>::from_sql(value)?;
+            #read_value
+        },
+    );
+    let from_sql = attrs.reads().then(|| {
+        quote! {
+            #[automatically_derived]
+            impl #from_sql_impl_generics #diesel::deserialize::FromSql<#diesel::sql_types::#sql_type, __DB> for #from_sql_ty #from_sql_where_clause {
+                fn from_sql(value: <__DB as #diesel::backend::Backend>::RawValue<'_>) -> #diesel::deserialize::Result<Self> {
+                    #read_value
+                }
+            }
+        }
+    });
+
+    let nullable = nullable_impl(
+        target,
+        attrs,
+        quote! {
+            #[automatically_derived]
+            impl #from_sql_impl_generics #diesel::deserialize::FromSql<#diesel::sql_types::Nullable<#diesel::sql_types::#sql_type>, __DB> for #from_sql_ty #from_sql_where_clause
+        },
+        quote!(__DB),
+    );
+
+    Ok(quote! {
+        #to_sql
+        #from_sql
+        #nullable
+    })
+}
+
+/// The first statement of `to_sql` with `pre_serialize`, rejecting the value
+/// before anything is written.
+fn pre_serialize(target: &Target, attrs: &Attrs) -> TokenStream {
+    let Some(check) = &attrs.pre_serialize else {
+        return quote!();
+    };
+    let krate = &attrs.crate_path;
+    let type_name = target.name();
+    quote! {
+        #check(self).map_err(|e| #krate::DieselJsonError::Rejected {
+            type_name: #type_name,
+            message: ::std::string::ToString::to_string(&e),
+        })?;
+    }
+}
+
+/// `read` logging its error with `log_read_error` of the facade, with the
+/// `tracing` feature. `bytes` are all bytes of the value, version byte
+/// included.
+fn log_error(target: &Target, attrs: &Attrs, bytes: TokenStream, read: TokenStream) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    if !cfg!(feature = "tracing") {
+        return read;
+    }
+    let krate = &attrs.crate_path;
+    let type_name = target.error_name(attrs);
+    quote! {
+        let read = || -> #diesel::deserialize::Result<Self> { #read };
+        read().map_err(|e| {
+            #krate::__private::log_read_error(#type_name, #bytes, &*e);
+            e
+        })
+    }
+}
+
+/// `read` naming the `context` after the type in its errors, with
+/// `with_context` of the facade.
+fn with_context(target: &Target, attrs: &Attrs, read: TokenStream) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    if attrs.context.is_none() {
+        return read;
+    }
+    let krate = &attrs.crate_path;
+    let type_name = target.error_name(attrs);
+    quote! {
+        let read = || -> #diesel::deserialize::Result<Self> { #read };
+        read().map_err(|e| #krate::__private::with_context(e, #type_name))
+    }
+}
+
+/// The body of `from_sql`, turning any error into the default value with
+/// `default_on_error`.
+fn or_default(attrs: &Attrs, read: TokenStream) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    if !attrs.default_on_error {
+        return read;
+    }
+    quote! {
+        let read = move || -> #diesel::deserialize::Result<Self> { #read };
+        ::core::result::Result::Ok(read().unwrap_or_default())
+    }
+}
+
+/// Statements serializing `self` into the writer `out`. With `atomic_write`
+/// (and `size_hint`) the value is serialized into a buffer first, so nothing
+/// is written if serialization fails. `checksum` and `compress` need the
+/// whole JSON first as well. With `reuse_buffer` the value is serialized into the thread's
+/// scratch buffer and copied from there.
+fn write_value(target: &Target, attrs: &Attrs, out: TokenStream) -> TokenStream {
+    if attrs.enum_discriminant {
+        return write_discriminant(target, attrs, out);
+    }
+    if attrs.reuse_buffer {
+        let krate = &attrs.crate_path;
+        let write_value = write_value(
+            target,
+            &Attrs {
+                reuse_buffer: false,
+                max_bytes: None,
+                validate_on_write: false,
+                tagged_format: false,
+                ..attrs.clone()
+            },
+            quote!(&mut *buf),
+        );
+        let check_size = check_size(target, attrs, quote!(buf));
+        let (push_tag, json) = match format_tag(attrs) {
+            Some(tag) => (quote!(buf.push(#tag);), quote!(&buf[1..])),
+            None => (quote!(), quote!(buf)),
+        };
+        let check_json = check_json(target, attrs, json);
+        return quote! {
+            #krate::__private::with_scratch_buffer(|buf| -> ::core::result::Result<
+                (),
+                ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync>,
+            > {
+                #push_tag
+                #write_value
+                #check_size
+                #check_json
+                ::std::io::Write::write_all(#out, buf)?;
+                ::core::result::Result::Ok(())
+            })?;
+        };
+    }
+    if attrs.atomic_write
+        || attrs.checksum
+        || attrs.compress
+        || attrs.max_bytes.is_some()
+        || attrs.validate_on_write
+    {
+        let value_vec = value_vec(target, attrs);
+        let check_size = check_size(target, attrs, quote!(buf));
+        return quote! {
+            let buf = #value_vec;
+            #check_size
+            ::std::io::Write::write_all(#out, &buf)?;
+        };
+    }
+
+    let value = target.value_ref(attrs);
+    let write_tag = format_tag(attrs).map(|tag| {
+        quote! {
+            ::std::io::Write::write_all(#out, &[#tag])?;
+        }
+    });
+    match attrs.format {
+        Format::Json => {}
+        Format::MessagePack => {
+            return quote! {
+                #write_tag
+                ::rmp_serde::encode::write(#out, #value)?;
+            }
+        }
+        Format::Cbor => {
+            return quote! {
+                #write_tag
+                ::ciborium::into_writer(#value, #out)?;
+            }
+        }
+    }
+
+    if attrs.size_hint.is_some() {
+        let value_vec = value_vec(target, attrs);
+        quote! {
+            let buf = #value_vec;
+            ::std::io::Write::write_all(#out, &buf)?;
+        }
+    } else {
+        let to_writer = to_writer(target, attrs, out, value);
+        quote! {
+            #write_tag
+            #to_writer?;
+        }
+    }
+}
+
+/// With `tagged_format`, the expression of the byte naming the format that is
+/// written in front of the value. Gzipped JSON brings its own.
+fn format_tag(attrs: &Attrs) -> Option<TokenStream> {
+    if !attrs.tagged_format || attrs.compress {
+        return None;
+    }
+    let krate = &attrs.crate_path;
+    let format = match attrs.format {
+        Format::Json => quote!(Json),
+        Format::MessagePack => quote!(MessagePack),
+        Format::Cbor => quote!(Cbor),
+    };
+    Some(quote!(#krate::TaggedFormat::#format.tag()))
+}
+
+/// With `max_bytes`, the statement failing `to_sql` (or the `reuse_buffer`
+/// closure) if the buffered bytes in `buf` are more than that, before any of
+/// them is written.
+fn check_size(target: &Target, attrs: &Attrs, buf: TokenStream) -> TokenStream {
+    let Some(max_bytes) = attrs.max_bytes else {
+        return quote!();
+    };
+    let krate = &attrs.crate_path;
+    let type_name = target.name();
+    quote! {
+        if #buf.len() > #max_bytes {
+            return ::core::result::Result::Err(::core::convert::Into::into(
+                #krate::DieselJsonError::TooLarge {
+                    type_name: #type_name,
+                    size: #buf.len(),
+                    max_bytes: #max_bytes,
+                },
+            ));
+        }
+    }
+}
+
+/// With `validate_on_write`, the statement failing `to_sql` (or the
+/// `reuse_buffer` closure) if the buffered JSON `json` doesn't parse.
+fn check_json(target: &Target, attrs: &Attrs, json: TokenStream) -> TokenStream {
+    if !attrs.validate_on_write {
+        return quote!();
+    }
+    let krate = &attrs.crate_path;
+    let type_name = target.name();
+    quote! {
+        #krate::__private::validate_json(#json, #type_name)?;
+    }
+}
+
+/// The variants of an `enum_discriminant` enum with a pattern binding their
+/// fields, the names of the bindings and how the fields are stored: the value
+/// of the only field, or a tuple of all of them.
+fn discriminant_arms(target: &Target) -> Vec<(u8, TokenStream, Vec<Ident>, Vec<&Type>)> {
+    let mut arms = Vec::new();
+    for (index, variant) in target.variants.iter().enumerate() {
+        let discriminant = u8::try_from(index).expect("checked by use_discriminants");
+        let ident = &variant.ident;
+        let types: Vec<&Type> = variant.fields.iter().map(|field| &field.ty).collect();
+        let (pattern, names) = match &variant.fields {
+            Fields::Unit => (quote!(Self::#ident), Vec::new()),
+            Fields::Unnamed(_) => {
+                let names: Vec<Ident> = (0..types.len()).map(|i| format_ident!("__f{i}")).collect();
+                (quote!(Self::#ident(#(#names),*)), names)
+            }
+            Fields::Named(fields) => {
+                let names: Vec<Ident> = fields
+                    .named
+                    .iter()
+                    .map(|field| field.ident.clone().expect("named field"))
+                    .collect();
+                (quote!(Self::#ident { #(#names),* }), names)
+            }
+        };
+        arms.push((discriminant, pattern, names, types));
+    }
+    arms
+}
+
+/// Statements writing an `enum_discriminant` enum into the writer `out`: the
+/// version byte, the discriminant of the variant, which is its index, and
+/// the JSON of the value of a variant with one field or an array of the
+/// fields of one with more. Unit variants have nothing after the
+/// discriminant.
+fn write_discriminant(target: &Target, attrs: &Attrs, out: TokenStream) -> TokenStream {
+    let version = jsonb_version(attrs);
+    let arms = discriminant_arms(target)
+        .into_iter()
+        .map(|(discriminant, pattern, names, _)| {
+            let value = match names.as_slice() {
+                [] => None,
+                [name] => Some(quote!(#name)),
+                names => Some(quote!(&(#(#names,)*))),
+            };
+            let write_fields = value.map(|value| {
+                let to_writer = to_writer(target, attrs, quote!(&mut *writer), value);
+                quote!(#to_writer?;)
+            });
+            quote! {
+                #pattern => {
+                    ::std::io::Write::write_all(writer, &[#version, #discriminant])?;
+                    #write_fields
+                }
+            }
+        });
+    quote! {
+        let writer = #out;
+        match self {
+            #(#arms)*
+        }
+    }
+}
+
+/// Expression reading an `enum_discriminant` enum from `bytes`, as written
+/// by `write_discriminant`, evaluating to a `deserialize::Result<Self>`.
+fn read_discriminant(target: &Target, attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    let krate = &attrs.crate_path;
+    let serde_json = &attrs.serde_json_path;
+    let version = jsonb_version(attrs);
+    let type_name = target.name();
+    let invalid_value = target.invalid_value(attrs);
+    let arms =
+        discriminant_arms(target)
+            .into_iter()
+            .map(
+                |(discriminant, pattern, names, types)| match types.as_slice() {
+                    [] => quote! {
+                        #discriminant if payload.is_empty() => ::core::result::Result::Ok(#pattern),
+                        #discriminant => ::core::result::Result::Err(::core::convert::Into::into(
+                            #krate::DieselJsonError::UnexpectedPayload {
+                                type_name: #type_name,
+                                discriminant: #discriminant,
+                            },
+                        )),
+                    },
+                    [ty] => quote! {
+                        #discriminant => #serde_json::from_slice::<#ty>(payload)
+                            .map(|#(#names)*| #pattern)
+                            .map_err(#invalid_value),
+                    },
+                    types => quote! {
+                        #discriminant => #serde_json::from_slice::<(#(#types,)*)>(payload)
+                            .map(|(#(#names,)*)| #pattern)
+                            .map_err(#invalid_value),
+                    },
+                },
+            );
+    quote! {{
+        let (discriminant, payload) = #krate::__private::discriminant_payload(#bytes, #version)?;
+        // with 256 variants every discriminant has its arm
+        #[allow(unreachable_patterns)]
+        match discriminant {
+            #(#arms)*
+            discriminant => ::core::result::Result::Err(::core::convert::Into::into(
+                #krate::DieselJsonError::UnknownDiscriminant {
+                    type_name: #type_name,
+                    discriminant,
+                },
+            )),
+        }
+    }}
+}
+
+/// Expression serializing `self` into a `Vec<u8>`, behind its checksum with
+/// `checksum`, gzipped with `compress` or behind its format tag with
+/// `tagged_format`.
+fn value_vec(target: &Target, attrs: &Attrs) -> TokenStream {
+    let value_vec = plain_value_vec(target, attrs);
+    if attrs.checksum {
+        let krate = &attrs.crate_path;
+        quote!(#krate::__private::with_checksum(&#value_vec))
+    } else if attrs.compress {
+        let krate = &attrs.crate_path;
+        quote!(#krate::__private::gzip_json(&#value_vec)?)
+    } else if let Some(tag) = format_tag(attrs) {
+        quote! {{
+            let value = #value_vec;
+            let mut buf = ::std::vec::Vec::with_capacity(1 + value.len());
+            buf.push(#tag);
+            buf.extend_from_slice(&value);
+            buf
+        }}
+    } else {
+        value_vec
+    }
+}
+
+/// `value_vec` without the checksum.
+fn plain_value_vec(target: &Target, attrs: &Attrs) -> TokenStream {
+    if attrs.enum_discriminant {
+        let write_discriminant = write_discriminant(target, attrs, quote!(&mut buf));
+        return quote! {{
+            let mut buf = ::std::vec::Vec::new();
+            #write_discriminant
+            buf
+        }};
+    }
+    let value = target.value_ref(attrs);
+    match attrs.format {
+        Format::Json => {}
+        Format::MessagePack => {
+            return quote! {
+                ::rmp_serde::to_vec(#value)?
+            }
+        }
+        Format::Cbor => {
+            return quote! {{
+                let mut buf = ::std::vec::Vec::new();
+                ::ciborium::into_writer(#value, &mut buf)?;
+                buf
+            }}
+        }
+    }
+
+    let serde_json = &attrs.serde_json_path;
+    let to_writer = to_writer(target, attrs, quote!(&mut buf), value.clone());
+    let value_vec = match attrs.size_hint {
+        Some(size_hint) => quote! {{
+            let mut buf = ::std::vec::Vec::with_capacity(#size_hint);
+            #to_writer?;
+            buf
+        }},
+        None if attrs.formatter.is_some() || attrs.canonical => quote! {{
+            let mut buf = ::std::vec::Vec::new();
+            #to_writer?;
+            buf
+        }},
+        None if attrs.pretty => quote! {
+            #serde_json::to_vec_pretty(#value)?
+        },
+        None => quote! {
+            #serde_json::to_vec(#value)?
+        },
+    };
+    if attrs.validate_on_write {
+        let check_json = check_json(target, attrs, quote!(&buf));
+        quote! {{
+            let buf = #value_vec;
+            #check_json
+            buf
+        }}
+    } else {
+        value_vec
+    }
+}
+
+/// Expression writing the JSON of `value` into `out` with
+/// `serde_json::to_writer`, `serde_json::to_writer_pretty` for `pretty` or a
+/// `Serializer` with the `formatter` and `to_canonical_writer` for
+/// `canonical`. With the `sonic-rs` feature plain JSON is written by sonic-rs,
+/// except for types with a `RawValue`.
+fn to_writer(target: &Target, attrs: &Attrs, out: TokenStream, value: TokenStream) -> TokenStream {
+    let serde_json = &attrs.serde_json_path;
+    if attrs.canonical {
+        let krate = &attrs.crate_path;
+        quote!(#krate::to_canonical_writer(#out, #value))
+    } else if let Some(formatter) = &attrs.formatter {
+        quote! {
+            ::serde::Serialize::serialize(
+                #value,
+                &mut #serde_json::Serializer::with_formatter(#out, #formatter()),
+            )
+        }
+    } else if attrs.pretty {
+        quote!(#serde_json::to_writer_pretty(#out, #value))
+    } else if cfg!(feature = "sonic-rs") && !target.raw_json {
+        // sonic-rs writes into its own `WriteExt`, the buffered writer passes
+        // every chunk it formatted on to `out`
+        quote!(::sonic_rs::to_writer(::sonic_rs::writer::BufferedWriter::new(#out), #value))
+    } else {
+        quote!(#serde_json::to_writer(#out, #value))
+    }
+}
+
+/// Expression deserializing `Self` from the byte slice `bytes`, evaluating to
+/// a `deserialize::Result<Self>`. With the `simd-json` feature the bytes of
+/// JSON values are copied into a buffer simd-json can parse in place, with
+/// `sonic-rs` they are parsed by sonic-rs, unless the type has a `RawValue`,
+/// `streaming` asks for serde_json's reader or `ignore_trailing` for its
+/// `Deserializer`. With `empty_as_default` empty bytes are read as the
+/// default value, as is the JSON of `null_sentinel`, with `strict` anything
+/// but an object is rejected. With `checksum` the CRC32 in front of the JSON
+/// is checked first, with `compress` gzipped values are decompressed first,
+/// for `Json` and `Text` columns that the value is UTF-8.
+/// With the `arbitrary-precision` feature values serde_json fails to decode
+/// are decoded once more from a `serde_json::Value`, see
+/// `from_slice_via_value`.
+fn read_value(target: &Target, attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    if matches!(attrs.sql_type, SqlType::Json | SqlType::Text) {
+        let krate = &attrs.crate_path;
+        let type_name = target.name();
+        let read_value = unchecked_read_value(target, attrs, quote!(bytes));
+        return quote! {{
+            let bytes: &[u8] = #bytes;
+            #krate::__private::check_utf8(bytes, #type_name)?;
+            #read_value
+        }};
+    }
+    if attrs.checksum {
+        let krate = &attrs.crate_path;
+        let read_value = unchecked_read_value(target, attrs, quote!(bytes));
+        return quote! {{
+            let bytes: &[u8] = #krate::__private::checksum_payload(#bytes)?;
+            #read_value
+        }};
+    }
+    if attrs.tagged_format {
+        return read_tagged(target, attrs, bytes);
+    }
+    if attrs.compress {
+        let krate = &attrs.crate_path;
+        let type_name = target.name();
+        let read_value = unchecked_read_value(target, attrs, quote!(bytes));
+        return quote! {{
+            let bytes = #krate::__private::gunzip_payload(#bytes, #type_name)?;
+            let bytes: &[u8] = &bytes;
+            #read_value
+        }};
+    }
+    unchecked_read_value(target, attrs, bytes)
+}
+
+/// `read_value` for `tagged_format`, dispatching on the format tag. Every
+/// format the derive was built with is read, whatever the type writes, so a
+/// column can hold values written with different `format`s over time. The
+/// JSON options apply to the JSON values only, gzipped or not.
+fn read_tagged(target: &Target, attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    let krate = &attrs.crate_path;
+    let type_name = target.name();
+    let read_json = unchecked_read_value(
+        target,
+        &Attrs {
+            format: Format::Json,
+            ..attrs.clone()
+        },
+        quote!(bytes),
+    );
+    let mut arms = vec![quote! {
+        #krate::TaggedFormat::Json => #read_json,
+    }];
+    if attrs.compress || cfg!(feature = "compression") {
+        arms.push(quote! {
+            #krate::TaggedFormat::GzipJson => {
+                let json = #krate::__private::gunzip(bytes, #type_name)?;
+                let bytes: &[u8] = &json;
+                #read_json
+            }
+        });
+    }
+    for (format, variant, enabled) in [
+        (
+            Format::MessagePack,
+            quote!(MessagePack),
+            cfg!(feature = "messagepack"),
+        ),
+        (Format::Cbor, quote!(Cbor), cfg!(feature = "cbor")),
+    ] {
+        if attrs.format == format || enabled {
+            let parse_value = parse_value(
+                target,
+                &Attrs {
+                    format,
+                    ..attrs.clone()
+                },
+                quote!(bytes),
+            );
+            arms.push(quote! {
+                #krate::TaggedFormat::#variant => #parse_value,
+            });
+        }
+    }
+    quote! {{
+        let (format, bytes) = #krate::__private::tagged_payload(#bytes, #type_name)?;
+        match format {
+            #(#arms)*
+            format => ::core::result::Result::Err(::core::convert::Into::into(
+                #krate::DieselJsonError::UnsupportedFormat {
+                    type_name: #type_name,
+                    format: format.name(),
+                },
+            )),
+        }
+    }}
+}
+
+/// `read_value` without the checksum check.
+fn unchecked_read_value(target: &Target, attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    if !attrs.empty_as_default
+        && attrs.null_sentinel.is_none()
+        && !attrs.strict
+        && attrs.max_depth.is_none()
+    {
+        return parse_value(target, attrs, bytes);
+    }
+
+    let parse_value = parse_value(target, attrs, quote!(bytes));
+    let parse_value = match attrs.max_depth {
+        Some(max_depth) => {
+            let krate = &attrs.crate_path;
+            let type_name = target.name();
+            quote! {
+                #krate::__private::check_depth(bytes, #max_depth, #type_name)?;
+                #parse_value
+            }
+        }
+        None => parse_value,
+    };
+    let parse_value = if attrs.strict {
+        // Whitespace is the only thing that can come before the `{` of an
+        // object, anything else is an array or a scalar.
+        let krate = &attrs.crate_path;
+        let type_name = target.name();
+        quote! {
+            let first = ::core::iter::Iterator::find(&mut bytes.iter(), |b| !b.is_ascii_whitespace());
+            if first != ::core::option::Option::Some(&b'{') {
+                return ::core::result::Result::Err(::core::convert::Into::into(
+                    #krate::DieselJsonError::NotAnObject {
+                        type_name: #type_name,
+                    },
+                ));
+            }
+            #parse_value
+        }
+    } else {
+        parse_value
+    };
+    // before `strict`, the sentinel may well be no object, e.g. `"null"`
+    let parse_value = match &attrs.null_sentinel {
+        Some(sentinel) => {
+            let krate = &attrs.crate_path;
+            quote! {
+                if #krate::__private::is_null_sentinel(bytes, #sentinel) {
+                    ::core::result::Result::Ok(<Self as ::core::default::Default>::default())
+                } else {
+                    #parse_value
+                }
+            }
+        }
+        None => parse_value,
+    };
+    if attrs.empty_as_default {
+        quote! {{
+            let bytes: &[u8] = #bytes;
+            if bytes.is_empty() {
+                ::core::result::Result::Ok(<Self as ::core::default::Default>::default())
+            } else {
+                #parse_value
+            }
+        }}
+    } else {
+        quote! {{
+            let bytes: &[u8] = #bytes;
+            #parse_value
+        }}
+    }
+}
+
+/// `serde_json::from_slice` of `bytes`, evaluating to a `serde_json::Result`.
+/// With `arbitrary_precision` serde_json hands numbers that are not integers
+/// to `deserialize_any` as a map holding their text, and serde buffers the
+/// values of `#[serde(flatten)]` fields, internally tagged and untagged enums
+/// with `deserialize_any`, so e.g. an `f64` of a flattened struct fails with
+/// "invalid type: map, expected f64". A `serde_json::Value` hands them out as
+/// `f64` if that keeps their digits, the value is decoded from one if the
+/// bytes fail to decode directly. Its error is dropped for the first one,
+/// which has the position.
+fn from_slice_via_value(attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    let serde_json = &attrs.serde_json_path;
+    quote! {{
+        let bytes: &[u8] = #bytes;
+        #serde_json::from_slice(bytes).or_else(|error: #serde_json::Error| {
+            if !error.is_data() {
+                return ::core::result::Result::Err(error);
+            }
+            #serde_json::from_slice::<#serde_json::Value>(bytes)
+                .and_then(#serde_json::from_value)
+                .map_err(|_| error)
+        })
+    }}
+}
+
+/// `read_value` without the `empty_as_default`, `null_sentinel` and `strict`
+/// checks.
+fn parse_value(target: &Target, attrs: &Attrs, bytes: TokenStream) -> TokenStream {
+    let diesel = &attrs.diesel_path;
+    let invalid_value = target.invalid_value(attrs);
+    let from_slice = match attrs.format {
+        Format::Json if attrs.enum_discriminant => read_discriminant(target, attrs, bytes),
+        // The envelope is parsed into a `serde_json::Value` first, to find the
+        // schema version, so the parser options don't apply.
+        Format::Json if attrs.schema_version.is_some() => {
+            let krate = &attrs.crate_path;
+            let schema_version = attrs.schema_version.expect("checked by the guard");
+            let migrate = match &attrs.migrate {
+                Some(migrate) => quote!(::core::option::Option::Some(#migrate)),
+                None => quote!(::core::option::Option::None),
+            };
+            quote! {
+                #krate::__private::from_versioned_json(#bytes, #schema_version, #migrate)
+            }
+        }
+        // Parsed into a `serde_json::Value` as well, laid over the default.
+        Format::Json if attrs.lenient => {
+            let krate = &attrs.crate_path;
+            quote! {
+                #krate::__private::from_lenient_json(#bytes)
+            }
+        }
+        // Parsed into a `serde_json::Value` too, the unknown fields are dropped
+        // from it.
+        Format::Json if attrs.allow_unknown_fields => {
+            let krate = &attrs.crate_path;
+            quote! {
+                #krate::__private::from_json_allowing_unknown_fields(#bytes)
+            }
+        }
+        // Unlike `from_slice` and `from_reader`, deserializing from a
+        // `Deserializer` directly doesn't check for trailing data.
+        Format::Json if attrs.ignore_trailing => {
+            let serde_json = &attrs.serde_json_path;
+            let deserializer = if attrs.streaming {
+                quote!(from_reader(::std::io::Cursor::new(#bytes)))
+            } else {
+                quote!(from_slice(#bytes))
+            };
+            quote! {
+                ::serde::Deserialize::deserialize(&mut #serde_json::Deserializer::#deserializer)
+                    .map_err(#invalid_value)
+            }
+        }
+        // `from_reader` copies strings through a scratch buffer instead of
+        // borrowing from the slice, which is slower. It saves no memory, the
+        // bytes are in memory either way since diesel hands out the whole
+        // value.
+        Format::Json if attrs.streaming => {
+            let serde_json = &attrs.serde_json_path;
+            quote! {
+                #serde_json::from_reader(::std::io::Cursor::new(#bytes)).map_err(#invalid_value)
+            }
+        }
+        Format::Json if cfg!(feature = "sonic-rs") && !target.raw_json => quote! {
+            ::sonic_rs::from_slice(#bytes).map_err(#invalid_value)
+        },
+        Format::Json if cfg!(feature = "simd-json") && !target.raw_json => quote! {{
+            let mut buf = <[u8]>::to_vec(#bytes);
+            ::simd_json::from_slice(&mut buf).map_err(#invalid_value)
+        }},
+        // The shared function keeps the generated code small. It names the
+        // type it parses in errors, which is the field's for `transparent_value`.
+        Format::Json if target.transparent.is_none() => {
+            let krate = &attrs.crate_path;
+            quote! {
+                #krate::from_json(#bytes)
+            }
+        }
+        Format::Json if cfg!(feature = "arbitrary-precision") => {
+            let from_slice = from_slice_via_value(attrs, bytes);
+            quote!((#from_slice).map_err(#invalid_value))
+        }
+        Format::Json => {
+            let serde_json = &attrs.serde_json_path;
+            quote! {
+                #serde_json::from_slice(#bytes).map_err(#invalid_value)
+            }
+        }
+        Format::MessagePack => quote! {
+            ::rmp_serde::from_slice(#bytes).map_err(#invalid_value)
+        },
+        Format::Cbor => quote! {{
+            let bytes: &[u8] = #bytes;
+            ::ciborium::from_reader(bytes).map_err(#invalid_value)
+        }},
+    };
+    let from_slice = match &target.transparent {
+        Some((member, _)) => quote! {
+            (#from_slice).map(|value| Self { #member: value })
+        },
+        None => from_slice,
+    };
+    let from_slice = if attrs.intern_strings {
+        let krate = &attrs.crate_path;
+        quote!(#krate::with_interner(|| #from_slice))
+    } else {
+        from_slice
+    };
+
+    match &attrs.validate {
+        Some(validate) => {
+            let krate = &attrs.crate_path;
+            let type_name = target.name();
+            quote! {{
+                let value: #diesel::deserialize::Result<Self> = #from_slice;
+                let value = value?;
+                #validate(&value).map_err(|e| #krate::DieselJsonError::Invalid {
+                    type_name: #type_name,
+                    message: ::std::string::ToString::to_string(&e),
+                })?;
+                ::core::result::Result::Ok(value)
+            }}
+        }
+        None => from_slice,
+    }
+}
@@ -0,0 +1,111 @@
+//! The proc macros of [diesel-json-derive](https://crates.io/crates/diesel-json-derive),
+//! use that crate instead of depending on this one directly.
+
+#[cfg(all(feature = "simd-json", feature = "sonic-rs"))]
+compile_error!(
+    "the `simd-json` and `sonic-rs` features both replace serde_json's parser, enable only one"
+);
+
+mod attrs;
+mod expand;
+
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use syn::DeriveInput;
+
+#[proc_macro_derive(DieselJsonb, attributes(diesel_json))]
+pub fn diesel_jsonb_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = match syn::parse::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(error) => return compile_error(error).into(),
+    };
+    expand::derive(&input).unwrap_or_else(compile_error).into()
+}
+
+/// Like `#[derive(DieselJsonb)]` but also adds `#[derive(AsExpression,
+/// FromSqlRow)]` and `#[diesel(sql_type = Jsonb)]` (unless a `sql_type` is
+/// given), so only the serde derives are left to write.
+#[proc_macro_attribute]
+pub fn diesel_jsonb(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = match syn::parse::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(error) => return compile_error(error).into(),
+    };
+    expand::attribute(args.into(), &input)
+        .unwrap_or_else(compile_error)
+        .into()
+}
+
+/// On a model struct, e.g. one loaded with `sql_query`, turns `#[diesel_jsonb]`
+/// on its fields into `#[diesel(sql_type = Jsonb)]` (`Nullable<Jsonb>` for
+/// `Option` fields), which `QueryableByName` reads them by.
+/// `#[diesel_jsonb(sql_type = Json)]` picks another sql type.
+#[proc_macro_attribute]
+pub fn diesel_jsonb_model(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = match syn::parse::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(error) => return compile_error(error).into(),
+    };
+    expand::model(args.into(), &input)
+        .unwrap_or_else(compile_error)
+        .into()
+}
+
+/// Generates the `#[derive(DieselJsonb)]` impls for a type named by path,
+/// e.g. for types that are generated by another macro:
+/// `impl_diesel_jsonb!(path::to::Bar)`. The `sql_type` and `#[diesel_json(...)]`
+/// options follow the type: `impl_diesel_jsonb!(Bar, sql_type = Json, version = 2)`.
+///
+/// Generic types take the generics and where clause of the impls like an impl
+/// header, `impl_diesel_jsonb!(impl<T: Clone> for Foo<T> where T: Debug)`. The
+/// impls also require `Foo<T>` to implement the serde traits and `Debug`.
+///
+/// The orphan rule still applies: the type has to be local to the calling crate,
+/// types of other crates need a local newtype.
+#[proc_macro]
+pub fn impl_diesel_jsonb(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = match syn::parse::<attrs::ImplInput>(input) {
+        Ok(input) => input,
+        Err(error) => return compile_error(error).into(),
+    };
+    expand::impl_macro(&input)
+        .unwrap_or_else(compile_error)
+        .into()
+}
+
+/// `impl_diesel_jsonb!` for a list of types at once, with the default options:
+/// `diesel_jsonb_types! { Foo, Bar, path::to::Baz }`. Every type needs the
+/// serde impls and diesel's `AsExpression` and `FromSqlRow` derives with
+/// `#[diesel(sql_type = Jsonb)]`.
+#[proc_macro]
+pub fn diesel_jsonb_types(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = match syn::parse::<attrs::TypesInput>(input) {
+        Ok(input) => input,
+        Err(error) => return compile_error(error).into(),
+    };
+    expand::types_macro(&input)
+        .unwrap_or_else(compile_error)
+        .into()
+}
+
+/// syn's `into_compile_error`, calling `::std::compile_error!` instead of
+/// `::core::compile_error!`: the errors are spanned at the input, and in a
+/// crate on the 2015 edition `::core` only resolves with an `extern crate
+/// core` in the crate root, while `std` is always there.
+fn compile_error(error: syn::Error) -> TokenStream {
+    error
+        .into_compile_error()
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) if ident == "core" => {
+                TokenTree::Ident(Ident::new("std", ident.span()))
+            }
+            token => token,
+        })
+        .collect()
+}
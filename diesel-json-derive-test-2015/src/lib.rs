@@ -0,0 +1,75 @@
+//! Types deriving `DieselJsonb` in a crate on the 2015 edition, where paths
+//! starting with `::` name items of the crate root and the extern crates are
+//! only reachable through the `extern crate` items there. The types are in a
+//! module of their own, where `diesel` and `serde_json` aren't in scope
+//! without being imported, to check that the generated code doesn't rely on
+//! the imports of the module it is expanded in.
+
+#[macro_use]
+extern crate diesel;
+extern crate diesel_json_derive;
+#[macro_use]
+extern crate serde;
+extern crate serde_json;
+
+pub mod records {
+    use diesel::sql_types::{Binary, Json, Jsonb};
+    use diesel_json_derive::{diesel_jsonb, DieselJsonb};
+
+    /// All of the sql types, with the default codegen.
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel(sql_type = Json)]
+    #[diesel(sql_type = Binary)]
+    #[diesel_json(sql_types(Jsonb, Json, Binary))]
+    pub struct Plain {
+        pub name: String,
+        pub tags: Vec<String>,
+    }
+
+    #[diesel_jsonb]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[diesel_json(reuse_buffer, max_bytes = 1024, validate_on_write)]
+    pub struct Buffered {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Binary)]
+    #[diesel_json(checksum = "crc32")]
+    pub struct Checked {
+        pub x: i32,
+    }
+
+    /// Stored with its schema version, version 1 named the field `n`.
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel_json(schema_version = 2, migrate = "migrate_counter")]
+    pub struct Counter {
+        pub count: u32,
+    }
+
+    fn migrate_counter(version: u8, value: ::serde_json::Value) -> Result<Counter, String> {
+        match version {
+            0 | 1 => Ok(Counter {
+                count: value["n"].as_u64().ok_or("no n")? as u32,
+            }),
+            _ => Err(format!("unknown version {}", version)),
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel_json(allow_unknown_fields)]
+    pub struct Partial {
+        pub id: u32,
+    }
+
+    #[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Binary)]
+    #[diesel_json(enum_discriminant)]
+    pub enum Slot<T> {
+        Empty,
+        Full(T),
+    }
+}
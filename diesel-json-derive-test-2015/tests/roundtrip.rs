@@ -0,0 +1,67 @@
+//! Binds the values of the 2015 edition types and reads them back from the
+//! bytes, like `diesel-json-derive-test`'s property tests.
+
+extern crate diesel;
+extern crate diesel_json_derive;
+extern crate diesel_json_derive_test_2015;
+
+use diesel::deserialize::FromSql;
+use diesel::pg::Pg;
+use diesel::sql_types::{Json, Jsonb};
+use diesel_json_derive::test_util::{json_value, jsonb_value, to_sql_bytes};
+use diesel_json_derive::DieselJsonError;
+use diesel_json_derive_test_2015::records::{Buffered, Counter, Partial, Plain};
+
+fn from_jsonb<T: FromSql<Jsonb, Pg>>(bytes: &[u8]) -> diesel::deserialize::Result<T> {
+    T::from_sql(jsonb_value(bytes))
+}
+
+#[test]
+fn plain() {
+    let value = Plain {
+        name: "a".to_string(),
+        tags: vec!["b".to_string()],
+    };
+    let bytes = to_sql_bytes::<Jsonb, _>(&value).unwrap();
+    assert_eq!(bytes, b"\x01{\"name\":\"a\",\"tags\":[\"b\"]}");
+    assert_eq!(from_jsonb::<Plain>(&bytes).unwrap(), value);
+
+    let bytes = to_sql_bytes::<Json, _>(&value).unwrap();
+    let read: Plain = FromSql::<Json, Pg>::from_sql(json_value(&bytes)).unwrap();
+    assert_eq!(read, value);
+}
+
+#[test]
+fn buffered() {
+    let bytes = to_sql_bytes::<Jsonb, _>(&Buffered { x: 3 }).unwrap();
+    assert_eq!(bytes, b"\x01{\"x\":3}");
+    assert_eq!(from_jsonb::<Buffered>(&bytes).unwrap(), Buffered { x: 3 });
+}
+
+#[test]
+fn schema_version() {
+    let bytes = to_sql_bytes::<Jsonb, _>(&Counter { count: 2 }).unwrap();
+    assert_eq!(bytes, b"\x01{\"schema_version\":2,\"data\":{\"count\":2}}");
+    assert_eq!(from_jsonb::<Counter>(&bytes).unwrap(), Counter { count: 2 });
+    assert_eq!(
+        from_jsonb::<Counter>(b"\x01{\"n\":5}").unwrap(),
+        Counter { count: 5 }
+    );
+}
+
+#[test]
+fn unknown_fields() {
+    assert_eq!(
+        from_jsonb::<Partial>(b"\x01{\"id\":1,\"extra\":true}").unwrap(),
+        Partial { id: 1 }
+    );
+    let error = from_jsonb::<Partial>(b"\x01[]").unwrap_err();
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    match *error {
+        DieselJsonError::Deserialize {
+            type_name: "Partial",
+            ..
+        } => {}
+        ref error => panic!("{}", error),
+    }
+}
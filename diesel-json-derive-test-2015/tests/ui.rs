@@ -0,0 +1,11 @@
+//! The derive's compile errors on the 2015 edition, where they used to be
+//! hidden behind "cannot find `core` in the crate root". Regenerate the
+//! `.stderr` files with `TRYBUILD=overwrite cargo test -p diesel-json-derive-test-2015`.
+
+extern crate trybuild;
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,17 @@
+#[macro_use]
+extern crate diesel;
+extern crate diesel_json_derive;
+#[macro_use]
+extern crate serde;
+
+use diesel::sql_types::Jsonb;
+use diesel_json_derive::DieselJsonb;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(checksum = "crc32")]
+pub struct Bar {
+    pub x: i32,
+}
+
+fn main() {}
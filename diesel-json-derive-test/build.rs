@@ -0,0 +1,52 @@
+//! Writes types using the derive into `OUT_DIR`, like code generated from a
+//! schema, for `src/generated.rs` and `pg.rs` to `include!`. Sets the
+//! `db_postgres` cfg, which `backend_cfg` gates impls on, and declares
+//! `db_sqlite` without setting it.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Spelled out with full paths, as generated code is: it can't know what the
+/// module it is included into imports.
+const TYPES: &str = r#"
+#[derive(
+    Debug,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::diesel::AsExpression,
+    ::diesel::FromSqlRow,
+    ::diesel_json_derive::DieselJsonb,
+)]
+#[diesel(sql_type = ::diesel::sql_types::Jsonb)]
+pub struct GeneratedRecord {
+    pub id: u32,
+    pub name: ::std::string::String,
+}
+
+#[derive(
+    Debug,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::diesel::AsExpression,
+    ::diesel::FromSqlRow,
+    ::diesel_json_derive::DieselJsonb,
+)]
+#[diesel(sql_type = ::diesel::sql_types::Json)]
+#[diesel_json(inherent_methods, sql_types(Json, Jsonb))]
+#[diesel(sql_type = ::diesel::sql_types::Jsonb)]
+pub enum GeneratedKind {
+    Plain,
+    Tagged(::std::string::String),
+}
+"#;
+
+fn main() {
+    let out_dir = env::var_os("OUT_DIR").expect("cargo sets OUT_DIR");
+    fs::write(Path::new(&out_dir).join("generated_types.rs"), TYPES).expect("OUT_DIR is writable");
+    println!("cargo::rustc-check-cfg=cfg(db_postgres, db_sqlite)");
+    println!("cargo::rustc-cfg=db_postgres");
+    println!("cargo::rerun-if-changed=build.rs");
+}
@@ -0,0 +1,107 @@
+//! Compares the memory a value read with `from_sql` keeps when its strings
+//! are `String`s and when they are `Interned` ones read with
+//! `#[diesel_json(intern_strings)]`, for a document of 10000 records whose
+//! keys and most of whose values repeat. Doesn't need a database, run it with
+//! `just bench-intern`.
+//!
+//! ```text
+//! value:        560012 bytes of JSON
+//! strings      6193215 bytes kept in 70001 allocations
+//! interned     4393384 bytes kept in 20008 allocations
+//! ```
+//!
+//! Every `String` is an allocation of its own. As `Interned` strings the keys
+//! and the repeated values are allocated once for the whole document, and
+//! each of them takes 8 bytes less in the map's nodes. What is left is mostly
+//! the nodes and the ids, which are unique.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use diesel::deserialize::FromSql;
+use diesel::pg::Pg;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::test_util::{jsonb_bytes, jsonb_value};
+use diesel_json_derive::{DieselJsonb, Interned};
+use serde::{Deserialize, Serialize};
+
+/// Counts the bytes currently allocated and the allocations.
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        CURRENT.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        CURRENT.fetch_add(new_size, Ordering::Relaxed);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+struct Strings {
+    records: Vec<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(intern_strings)]
+struct Interning {
+    records: Vec<BTreeMap<Interned, Interned>>,
+}
+
+/// The bytes and the allocations the value read from `bytes` keeps.
+fn kept<T: FromSql<Jsonb, Pg>>(bytes: &[u8]) -> (usize, usize) {
+    let before = (
+        CURRENT.load(Ordering::Relaxed),
+        ALLOCATIONS.load(Ordering::Relaxed),
+    );
+    let value = T::from_sql(jsonb_value(bytes)).expect("reading succeeds");
+    let kept = (
+        CURRENT.load(Ordering::Relaxed) - before.0,
+        ALLOCATIONS.load(Ordering::Relaxed) - before.1,
+    );
+    drop(value);
+    kept
+}
+
+fn main() {
+    let colors = ["red", "green", "blue"];
+    let records = (0..10_000)
+        .map(|i| {
+            BTreeMap::from([
+                ("id".to_string(), format!("record-{i:06}")),
+                ("color".to_string(), colors[i % colors.len()].to_string()),
+                ("status".to_string(), "active".to_string()),
+            ])
+        })
+        .collect();
+    let bytes = jsonb_bytes(&Strings { records });
+
+    println!("value:    {:>10} bytes of JSON", bytes.len() - 1);
+    for (name, (size, allocations)) in [
+        ("strings", kept::<Strings>(&bytes)),
+        ("interned", kept::<Interning>(&bytes)),
+    ] {
+        println!("{name:<9} {size:>10} bytes kept in {allocations} allocations");
+    }
+}
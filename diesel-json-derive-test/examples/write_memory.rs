@@ -0,0 +1,196 @@
+//! Compares the memory `to_sql` needs for a large JSONB value when the JSON is
+//! written straight into diesel's output and when it is serialized into a
+//! buffer first (`atomic_write`, `size_hint`, `reuse_buffer`). Needs a
+//! Postgres connection for the bind collector, run it with `just bench-memory`.
+//!
+//! The bind collector gives `to_sql` an empty `Vec` to write into, so the
+//! direct path allocates that `Vec` and nothing else. For a 10 MB value:
+//!
+//! ```text
+//! value:      10300001 bytes
+//! direct      22020096 bytes peak, 2.14x the value
+//! buffered    27077228 bytes peak, 2.63x the value
+//! hinted      21300013 bytes peak, 2.07x the value
+//! ```
+//!
+//! Most of the direct path's peak is the `Vec` growing: it doubles, and while
+//! it is copied the old and the new block both exist. `atomic_write` has the
+//! same growing buffer and then copies it into the output, half the value
+//! more. With a `size_hint` that fits, the buffer never grows, so the copy
+//! costs about what the growth would have.
+//!
+//! For many small values, as in a batch insert, it counts the allocations per
+//! bind instead, for values of about 1 KB:
+//!
+//! ```text
+//! direct          7.00 allocations per bind
+//! buffered        6.00 allocations per bind
+//! reused          1.00 allocations per bind
+//! ```
+//!
+//! The direct output and the `atomic_write` buffer grow from nothing for
+//! every value. `reuse_buffer` serializes the version byte and the JSON into
+//! a buffer that has grown before and copies it into the output, which is
+//! then allocated once at its full size. What is left is that allocation.
+//! `benches/batch_insert.rs` measures what this saves in time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use diesel::pg::{Pg, PgConnection, PgMetadataLookup};
+use diesel::query_builder::bind_collector::RawBytesBindCollector;
+use diesel::query_builder::BindCollector;
+use diesel::serialize::ToSql;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, Connection, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// Counts the bytes currently allocated and the peak since the last reset,
+/// and the allocations.
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn add(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        add(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    // a reallocation may have to copy, so the old and the new block count
+    // until it is done
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        add(new_size);
+        let new = System.realloc(ptr, layout, new_size);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+struct Direct {
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(atomic_write)]
+struct Buffered {
+    lines: Vec<String>,
+}
+
+/// The hint is about the size of the value below.
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(size_hint = 11_000_000)]
+struct Hinted {
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(reuse_buffer)]
+struct Reused {
+    lines: Vec<String>,
+}
+
+/// Peak bytes allocated while binding `value`, on top of what was allocated
+/// before.
+fn peak<T: ToSql<Jsonb, Pg>>(conn: &mut PgConnection, value: &T) -> usize {
+    let lookup: &mut dyn PgMetadataLookup = conn;
+    let mut collector = RawBytesBindCollector::<Pg>::new();
+    let before = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    collector
+        .push_bound_value::<Jsonb, T>(value, lookup)
+        .expect("binding succeeds");
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    drop(collector);
+    peak
+}
+
+/// Allocations per bind when binding `values` one after the other, as in a
+/// batch insert.
+fn allocations<T: ToSql<Jsonb, Pg>>(conn: &mut PgConnection, values: &[T]) -> f64 {
+    let lookup: &mut dyn PgMetadataLookup = conn;
+    let mut collector = RawBytesBindCollector::<Pg>::new();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for value in values {
+        collector
+            .push_bound_value::<Jsonb, T>(value, lookup)
+            .expect("binding succeeds");
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / values.len() as f64
+}
+
+fn main() {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set");
+        return;
+    };
+    let mut conn = PgConnection::establish(&database_url).expect("postgres connection");
+
+    let lines: Vec<String> = (0..100_000).map(|i| format!("{i:0>100}")).collect();
+    let size = serde_json::to_vec(&lines).unwrap().len();
+    let direct = peak(
+        &mut conn,
+        &Direct {
+            lines: lines.clone(),
+        },
+    );
+    let buffered = peak(
+        &mut conn,
+        &Buffered {
+            lines: lines.clone(),
+        },
+    );
+    let hinted = peak(&mut conn, &Hinted { lines });
+
+    println!("value:    {:>10} bytes", size);
+    for (name, peak) in [
+        ("direct", direct),
+        ("buffered", buffered),
+        ("hinted", hinted),
+    ] {
+        println!(
+            "{name:<9} {peak:>10} bytes peak, {:.2}x the value",
+            peak as f64 / size as f64
+        );
+    }
+
+    let small = |i: usize| vec![format!("{i:0>100}"); 10];
+    let count = 10_000;
+    let direct: Vec<_> = (0..count).map(|i| Direct { lines: small(i) }).collect();
+    let buffered: Vec<_> = (0..count).map(|i| Buffered { lines: small(i) }).collect();
+    let reused: Vec<_> = (0..count).map(|i| Reused { lines: small(i) }).collect();
+    println!();
+    println!(
+        "{count} binds of {} bytes:",
+        serde_json::to_vec(&small(0)).unwrap().len()
+    );
+    for (name, allocations) in [
+        ("direct", allocations(&mut conn, &direct)),
+        ("buffered", allocations(&mut conn, &buffered)),
+        ("reused", allocations(&mut conn, &reused)),
+    ] {
+        println!("{name:<9} {allocations:>10.2} allocations per bind");
+    }
+}
@@ -0,0 +1,23 @@
+//! `JSONB[]` columns. diesel's `Array` impls for `Vec<T>` read and write every
+//! element with the impls of `T`, the derive's included, so arrays of a
+//! derived type need nothing more.
+
+use diesel::prelude::*;
+
+use crate::Bar;
+
+diesel::table! {
+    bar_lists (id) {
+        id -> Text,
+        bars -> Array<Jsonb>,
+        maybe_bars -> Array<Nullable<Jsonb>>,
+    }
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = bar_lists)]
+pub(crate) struct BarList {
+    pub(crate) id: String,
+    pub(crate) bars: Vec<Bar>,
+    pub(crate) maybe_bars: Vec<Option<Bar>>,
+}
@@ -0,0 +1,508 @@
+//! Types using `#[diesel_jsonb]` or configured with `#[diesel_json(...)]`.
+
+use diesel::prelude::*;
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::{diesel_jsonb, DieselJsonb, Interned};
+use serde::{Deserialize, Serialize};
+
+#[diesel_jsonb]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AttributeMacro {
+    pub x: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(version = 2)]
+pub struct VersionTwo {
+    pub x: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(accept_any_version)]
+pub struct AnyVersion {
+    pub x: i32,
+}
+
+/// The same as `AnyVersion`, `skip_version_check` is another name for it.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(skip_version_check)]
+pub struct SkipVersionCheck {
+    pub x: i32,
+}
+
+/// Reads both encodings during a migration from version 1 to 2.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(version_check = "one_or_two")]
+pub struct Migrating {
+    pub x: i32,
+}
+
+fn one_or_two(version: u8) -> bool {
+    matches!(version, 1 | 2)
+}
+
+/// Reads rows a future writer wrote with another version as the default.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(unknown_version = "default")]
+pub struct Degrading {
+    pub x: i32,
+}
+
+/// The same with `version_check`, and `"error"`, which is what happens
+/// without `unknown_version`.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(version_check = "one_or_two", unknown_version = "default")]
+pub struct DegradingMigrating {
+    pub x: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(unknown_version = "error")]
+pub struct Failing {
+    pub x: i32,
+}
+
+/// Stands in for a crate re-exporting diesel and serde_json.
+pub mod facade {
+    pub use diesel;
+    pub use serde_json;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(diesel_path = crate::attributes::facade::diesel)]
+pub struct FacadeDiesel {
+    pub x: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(serde_json_path = crate::attributes::facade::serde_json)]
+pub struct FacadeSerdeJson {
+    pub x: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(size_hint = 1024)]
+pub struct SizeHinted {
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(validate = "check_percent")]
+pub struct Percent {
+    pub value: u8,
+}
+
+fn check_percent(percent: &Percent) -> Result<(), String> {
+    if percent.value > 100 {
+        return Err(format!("{} is not a percentage", percent.value));
+    }
+    Ok(())
+}
+
+/// Never written with more used than allowed.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(pre_serialize = "check_quota")]
+pub struct Quota {
+    pub used: u32,
+    pub limit: u32,
+}
+
+/// Never written with more than 32 bytes, the version byte of `Jsonb`
+/// included.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sql_types(Jsonb, Json), max_bytes = 32)]
+pub struct Capped {
+    pub items: Vec<u8>,
+}
+
+/// Fixtures and caches in the bytes Postgres stores, without a connection.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(inherent_methods)]
+pub struct Fixture {
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+/// The same with options of their own, and a `Json` column besides.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Json)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(inherent_methods, sort_keys, empty_as_default)]
+pub struct SortedFixture {
+    pub b: u32,
+    pub a: u32,
+}
+
+/// Serialized into the thread's scratch buffer, for batch inserts.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(reuse_buffer, inherent_methods)]
+pub struct Batched {
+    pub id: u32,
+    pub payload: String,
+}
+
+fn check_quota(quota: &Quota) -> Result<(), String> {
+    if quota.used > quota.limit {
+        return Err(format!("{} used of {}", quota.used, quota.limit));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(streaming)]
+pub struct Streaming {
+    pub lines: Vec<String>,
+}
+
+/// The Postgres impls are behind the `db_postgres` cfg of `build.rs`, the
+/// SQLite ones behind `db_sqlite`, which is never set.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(backend_cfg(postgres = "db_postgres", sqlite = "db_sqlite"))]
+pub struct PgOnly {
+    pub x: i32,
+}
+
+/// All impls behind a cfg that holds.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(
+    sql_types(Jsonb, Json),
+    backend_cfg = "all(db_postgres, not(db_sqlite))"
+)]
+pub struct CfgGated {
+    pub x: i32,
+}
+
+/// The keys and values repeated in the entries share their allocations when
+/// read.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(intern_strings)]
+pub struct Catalog {
+    pub entries: Vec<std::collections::BTreeMap<Interned, Interned>>,
+}
+
+/// The same as `Streaming`, `reader` is another name for it.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(reader)]
+pub struct Reader {
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(ignore_trailing)]
+pub struct Lenient {
+    pub x: i32,
+}
+
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(empty_as_default)]
+pub struct Settings {
+    pub dark_mode: bool,
+}
+
+/// Reads corrupt values as the default instead of failing.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(default_on_error)]
+pub struct Tolerant {
+    pub retries: u32,
+}
+
+/// Written as `{"name":...}` before `theme` and `notifications.push` were
+/// added. The recommended way to read such rows: every field added later has
+/// `#[serde(default)]` (here the whole struct), which needs nothing from the
+/// derive.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[serde(default)]
+pub struct Compatible {
+    pub name: String,
+    pub theme: String,
+}
+
+/// The same with `lenient`, which fills in missing fields from the `Default`
+/// impl instead, in nested structs too. `require_default` checks that impl
+/// apart from the `FromSql` impls.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(lenient, require_default)]
+pub struct Evolved {
+    pub name: String,
+    pub theme: String,
+    pub notifications: Notifications,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Notifications {
+    pub email: bool,
+    pub push: bool,
+}
+
+impl Default for Evolved {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            theme: "light".to_string(),
+            notifications: Notifications {
+                email: false,
+                push: true,
+            },
+        }
+    }
+}
+
+/// Older rows store `{}` for no value instead of SQL `NULL`.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(null_sentinel = "{}")]
+pub struct Legacy {
+    pub name: String,
+    pub retries: u32,
+}
+
+/// Denies unknown fields when deserialized elsewhere, rows written by a newer
+/// version with more fields are still read.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(deny_unknown_fields)]
+#[diesel_json(allow_unknown_fields)]
+pub struct Forward {
+    pub name: String,
+    #[serde(rename = "retryCount", alias = "retries")]
+    pub retry_count: u32,
+}
+
+/// Fields added after rows were written, filled in by functions of another
+/// module when they are missing. serde's derive resolves the paths where the
+/// type is defined, the derive's impls don't get in between.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Upgraded {
+    pub name: String,
+    #[serde(default = "defaults::retries")]
+    pub retries: u32,
+    #[serde(default = "crate::attributes::defaults::region")]
+    pub region: String,
+}
+
+pub mod defaults {
+    pub fn retries() -> u32 {
+        3
+    }
+
+    pub fn region() -> String {
+        "eu".to_string()
+    }
+}
+
+/// Stored in `users.settings`, which read errors name after the type.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(context = "users.settings")]
+pub struct Located {
+    pub theme: String,
+}
+
+/// Some rows were written by a tool that left out the version byte.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(detect_version_byte)]
+pub struct Imported {
+    pub name: String,
+}
+
+/// The same for a number, where the JSON can be a single byte.
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(detect_version_byte, empty_as_default)]
+#[serde(transparent)]
+pub struct ImportedCount(pub u32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(strict)]
+pub struct ObjectOnly(pub serde_json::Value);
+
+/// Stored as the `Value` itself, the wrapper needs no serde impls.
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(transparent_value)]
+pub struct Dynamic(pub serde_json::Value);
+
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(transparent_value)]
+pub struct DynamicNamed {
+    pub value: serde_json::Value,
+}
+
+/// Converts to and from `serde_json::Value` as well.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(value_conversions)]
+pub struct Converted {
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+/// Any JSON, but at most 16 arrays or objects deep.
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(transparent_value, max_depth = 16)]
+pub struct Shallow(pub serde_json::Value);
+
+/// Converts its field, the wrapper has no serde impls.
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(transparent_value, value_conversions)]
+pub struct ConvertedDynamic(pub serde_json::Value);
+
+/// Writes the start of an object, then fails.
+#[derive(Debug, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct FailsMidway {
+    pub x: i32,
+}
+
+#[derive(Debug, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(atomic_write)]
+pub struct AtomicFailsMidway {
+    pub x: i32,
+}
+
+fn serialize_midway<S: serde::Serializer>(x: i32, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::{Error, SerializeMap};
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("x", &x)?;
+    Err(S::Error::custom("failed midway"))
+}
+
+impl Serialize for FailsMidway {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_midway(self.x, serializer)
+    }
+}
+
+impl Serialize for AtomicFailsMidway {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_midway(self.x, serializer)
+    }
+}
+
+/// Only ever written, e.g. to an audit log.
+#[derive(Debug, Serialize, AsExpression, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(serialize_only)]
+pub struct AuditEntry<'a> {
+    pub action: &'a str,
+    pub user_id: i32,
+}
+
+/// Only ever read.
+#[derive(Debug, PartialEq, Deserialize, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(deserialize_only)]
+pub struct AuditRecord {
+    pub action: String,
+    pub user_id: i32,
+}
+
+#[diesel_jsonb]
+#[derive(Debug, Serialize)]
+#[diesel_json(serialize_only)]
+pub struct AuditMessage {
+    pub text: String,
+}
+
+/// Stored with its schema version. Version 1 had a single `name`, rows
+/// written before the type had a schema version (version 0) too.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(schema_version = 2, migrate = "migrate_profile")]
+pub struct Profile {
+    pub first_name: String,
+    pub last_name: String,
+}
+
+fn migrate_profile(version: u8, value: serde_json::Value) -> Result<Profile, String> {
+    match version {
+        0 | 1 => {
+            let name = value["name"].as_str().ok_or("no name")?;
+            let (first_name, last_name) = name.split_once(' ').unwrap_or((name, ""));
+            Ok(Profile {
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+            })
+        }
+        _ => Err(format!("unknown version {version}")),
+    }
+}
+
+/// The columns of `foo`, also stored as a JSON value elsewhere, e.g. in an
+/// audit log. The derive only reads `sql_type` from the `#[diesel(...)]`
+/// attributes and skips the ones for the other derives. (`Queryable` would
+/// conflict with the `Queryable` impl of `FromSqlRow`.)
+#[derive(
+    Debug,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    Selectable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    AsExpression,
+    FromSqlRow,
+    DieselJsonb,
+)]
+#[diesel(table_name = crate::schema::foo, primary_key(id))]
+#[diesel(check_for_backend(diesel::pg::Pg, diesel::sqlite::Sqlite))]
+#[diesel(treat_none_as_null = true)]
+#[diesel(sql_type = Jsonb)]
+pub(crate) struct FooSnapshot {
+    pub(crate) id: String,
+    pub(crate) bar: crate::Bar,
+}
@@ -0,0 +1,254 @@
+//! A type stored as plain JSON bytes in a `BYTEA`/`BLOB` column.
+
+use diesel::prelude::*;
+use diesel::sql_types::Binary;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+diesel::table! {
+    binary_foo (id) {
+        id -> Text,
+        quux -> Binary,
+    }
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = binary_foo)]
+pub struct BinaryFoo {
+    pub id: String,
+    pub quux: Quux,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+pub struct Quux {
+    pub w: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(format = "msgpack")]
+pub struct Packed {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(format = "cbor")]
+pub struct Cbor {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+/// Holds text `Jsonb` can't store, e.g. NUL characters.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+pub struct Note {
+    pub text: String,
+}
+
+/// Stored behind a CRC32 of its JSON.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(checksum = "crc32")]
+pub struct Guarded {
+    pub w: bool,
+}
+
+/// `Quux` gzipped, rows written before it was are read as they are.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(compress = "gzip")]
+pub struct Compressed {
+    pub w: bool,
+}
+
+/// Written as JSON behind its format tag, read from values in any format.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(tagged_format)]
+pub struct Layered {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+/// `Layered` as it was written before, as MessagePack.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(tagged_format, format = "msgpack")]
+pub struct LayeredPacked {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+/// `Layered` as CBOR.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(tagged_format, format = "cbor")]
+pub struct LayeredCbor {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+/// `Layered` as gzipped JSON.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(tagged_format, compress = "gzip")]
+pub struct LayeredGzip {
+    pub id: u32,
+    pub tags: Vec<String>,
+}
+
+pub(crate) fn cbor_vec<T: Serialize>(
+    value: &T,
+) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut buf = Vec::new();
+    ciborium::into_writer(value, &mut buf)?;
+    Ok(buf)
+}
+
+/// Binds `$value` as a `Binary` parameter, checks that it was encoded as by
+/// `$encode` and selects it back as `$ty`.
+macro_rules! assert_binary_roundtrip {
+    ($conn:expr, $ty:ty, $value:expr, $encode:expr) => {{
+        use diesel::dsl::sql;
+        use diesel::sql_types::Binary;
+
+        let value: $ty = $value;
+        let bytes = diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+            .get_result::<Vec<u8>>($conn)?;
+        assert_eq!(bytes, $encode(&value).unwrap());
+        let loaded =
+            diesel::select(sql::<Binary>("").bind::<Binary, _>(&value)).get_result::<$ty>($conn)?;
+        assert_eq!(loaded, value);
+    }};
+}
+
+pub(crate) use assert_binary_roundtrip;
+
+/// The formats other than JSON, to be called with a connection of any backend.
+macro_rules! assert_formats_roundtrip {
+    ($conn:expr) => {{
+        use crate::binary::{assert_binary_roundtrip, cbor_vec, Cbor, Packed};
+
+        assert_binary_roundtrip!(
+            $conn,
+            Packed,
+            Packed {
+                id: 1,
+                tags: vec!["a".to_string(), "b".to_string()]
+            },
+            rmp_serde::to_vec
+        );
+        assert_binary_roundtrip!(
+            $conn,
+            Cbor,
+            Cbor {
+                id: 1,
+                tags: vec!["a".to_string(), "b".to_string()]
+            },
+            cbor_vec
+        );
+    }};
+}
+
+pub(crate) use assert_formats_roundtrip;
+
+/// Values of all the formats in the same column, behind their format tags, to
+/// be called with a connection of any backend with the `binary_foo` table.
+macro_rules! assert_tagged_roundtrip {
+    ($conn:expr) => {{
+        use crate::binary::{binary_foo, Layered, LayeredCbor, LayeredGzip, LayeredPacked};
+
+        let tags = || vec!["a".to_string(), "b".to_string()];
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("tagged 1"),
+                binary_foo::quux.eq(Layered {
+                    id: 1,
+                    tags: tags(),
+                }),
+            ))
+            .execute($conn)?;
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("tagged 2"),
+                binary_foo::quux.eq(LayeredPacked {
+                    id: 2,
+                    tags: tags(),
+                }),
+            ))
+            .execute($conn)?;
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("tagged 3"),
+                binary_foo::quux.eq(LayeredCbor {
+                    id: 3,
+                    tags: tags(),
+                }),
+            ))
+            .execute($conn)?;
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("tagged 4"),
+                binary_foo::quux.eq(LayeredGzip {
+                    id: 4,
+                    tags: tags(),
+                }),
+            ))
+            .execute($conn)?;
+        let tagged = binary_foo::table
+            .filter(binary_foo::id.like("tagged %"))
+            .order(binary_foo::id);
+        let loaded = tagged.select(binary_foo::quux).load::<Layered>($conn)?;
+        assert_eq!(loaded, [1, 2, 3, 4].map(|id| Layered { id, tags: tags() }));
+        let loaded = tagged.select(binary_foo::quux).load::<LayeredGzip>($conn)?;
+        assert_eq!(
+            loaded,
+            [1, 2, 3, 4].map(|id| LayeredGzip { id, tags: tags() })
+        );
+        let bytes = tagged.select(binary_foo::quux).load::<Vec<u8>>($conn)?;
+        assert_eq!(bytes[0], b"\x01{\"id\":1,\"tags\":[\"a\",\"b\"]}");
+        assert_eq!(bytes[1][0], 3);
+        assert_eq!(bytes[2][0], 4);
+        assert_eq!(bytes[3][..3], [2, 0x1f, 0x8b]);
+        diesel::delete(binary_foo::table.filter(binary_foo::id.like("tagged %"))).execute($conn)?;
+    }};
+}
+
+pub(crate) use assert_tagged_roundtrip;
+
+/// Gzipped values next to the uncompressed ones written before, to be called
+/// with a connection of any backend with the `binary_foo` table.
+macro_rules! assert_compressed_roundtrip {
+    ($conn:expr) => {{
+        use crate::binary::{binary_foo, Compressed, Quux};
+
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("gzip 1"),
+                binary_foo::quux.eq(Quux { w: true }),
+            ))
+            .execute($conn)?;
+        diesel::insert_into(binary_foo::table)
+            .values((
+                binary_foo::id.eq("gzip 2"),
+                binary_foo::quux.eq(Compressed { w: false }),
+            ))
+            .execute($conn)?;
+        let rows = binary_foo::table
+            .filter(binary_foo::id.like("gzip %"))
+            .order(binary_foo::id);
+        let loaded = rows.select(binary_foo::quux).load::<Compressed>($conn)?;
+        assert_eq!(loaded, [Compressed { w: true }, Compressed { w: false }]);
+        let bytes = rows.select(binary_foo::quux).load::<Vec<u8>>($conn)?;
+        assert_eq!(bytes[0], b"{\"w\":true}");
+        // the tag of gzipped JSON and the magic number of gzip
+        assert_eq!(bytes[1][..3], [2, 0x1f, 0x8b]);
+        diesel::delete(binary_foo::table.filter(binary_foo::id.like("gzip %"))).execute($conn)?;
+    }};
+}
+
+pub(crate) use assert_compressed_roundtrip;
@@ -0,0 +1,42 @@
+//! An enum of another crate, without serde impls, (de)serialized with serde's
+//! remote derive. The local types get the impls, the remote definition
+//! doesn't implement `Serialize` itself.
+
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// Stands in for the other crate.
+pub mod external {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Priority {
+        Low,
+        High,
+        Custom(u8),
+    }
+}
+
+use external::Priority;
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Priority")]
+enum PriorityDef {
+    Low,
+    High,
+    Custom(u8),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sql_types(Jsonb, Json))]
+pub struct LocalPriority(#[serde(with = "PriorityDef")] pub Priority);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Ticket {
+    pub title: String,
+    #[serde(with = "PriorityDef")]
+    pub priority: Priority,
+}
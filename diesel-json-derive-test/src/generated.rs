@@ -0,0 +1,8 @@
+//! The types `build.rs` generates, included into this module and into one
+//! nested in it. `pg.rs` includes them into a function as well.
+
+include!(concat!(env!("OUT_DIR"), "/generated_types.rs"));
+
+pub mod nested {
+    include!(concat!(env!("OUT_DIR"), "/generated_types.rs"));
+}
@@ -0,0 +1,238 @@
+//! Generic types deriving `DieselJsonb`.
+
+#![allow(dead_code)]
+
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+use crate::Bar;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Envelope<T> {
+    pub payload: T,
+    pub version: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Labeled<T>
+where
+    T: Clone,
+{
+    pub label: String,
+    pub value: T,
+}
+
+/// The payload derives `DieselJsonb` itself.
+pub type BarEnvelope = Envelope<Bar>;
+
+/// `Cow` fields without `#[serde(borrow)]` are always deserialized as owned.
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Doc<'a> {
+    pub title: std::borrow::Cow<'a, str>,
+}
+
+/// The const parameter only matters to code elsewhere, it is carried over to
+/// the impls like the other parameters.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct FixedVec<const N: usize> {
+    pub items: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Mixed<'a, T, const N: usize> {
+    pub name: std::borrow::Cow<'a, str>,
+    pub values: Vec<T>,
+}
+
+/// The default is not repeated in the impls' generics, where it isn't
+/// allowed.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Cache<T = serde_json::Value> {
+    pub data: T,
+}
+
+pub trait Unit {
+    const NAME: &'static str;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Meters;
+
+impl Unit for Meters {
+    const NAME: &'static str = "m";
+}
+
+/// The user's bounds, in the parameter list and in the where clause, are kept
+/// next to the ones the derive adds.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Measured<U: Unit, T>
+where
+    T: Copy + PartialOrd,
+{
+    pub unit: U,
+    pub value: T,
+}
+
+impl<U: Unit, T> Measured<U, T>
+where
+    T: Copy + PartialOrd,
+{
+    pub fn unit_name(&self) -> &'static str {
+        U::NAME
+    }
+}
+
+pub trait Schema {
+    type Record;
+}
+
+/// Neither serializable nor deserializable itself, only its record is.
+#[derive(Debug, PartialEq)]
+pub struct V1;
+
+impl Schema for V1 {
+    type Record = Entry;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub x: i32,
+}
+
+/// The impls are bounded by `Holder<T>: Serialize` and the like, not by
+/// `T: Serialize`, which `V1` isn't. serde's derive bounds `T::Record`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Holder<T: Schema> {
+    pub val: T::Record,
+}
+
+/// The same for a parameter that is only a marker.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Tagged<T> {
+    pub id: u32,
+    #[serde(skip)]
+    pub kind: std::marker::PhantomData<T>,
+}
+
+/// Only ever a type argument. The std derives of `Marked` bound the marker,
+/// so for comparing values it needs `Debug` and `PartialEq`.
+#[derive(Debug, PartialEq)]
+pub struct NonSerializableTag;
+
+/// serde stores the marker as `null` and bounds nothing for it, so neither
+/// does the derive.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Marked<Tag> {
+    pub value: i32,
+    pub _marker: std::marker::PhantomData<Tag>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    pub revision: u32,
+}
+
+/// Stored on its own and as the body of a `Wrapper`. Its impls are for
+/// `Body`, the wrapper's for `Wrapper<Body>`, so they don't overlap.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Body {
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Wrapper<T> {
+    pub meta: Meta,
+    pub body: T,
+}
+
+/// Stores the value as a string, with serde's bounds repeated for the impls:
+/// `T: Display` to write and `T: FromStr` to read, instead of the inferred
+/// `Shown<T>: Serialize` and `Shown<T>: DeserializeOwned`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(bound(
+    serialize = "T: std::fmt::Display",
+    deserialize = "T: std::str::FromStr, T::Err: std::fmt::Display"
+))]
+#[diesel_json(
+    serialize_bound = "T: std::fmt::Display",
+    deserialize_bound = "T: std::str::FromStr, T::Err: std::fmt::Display"
+)]
+pub struct Shown<T> {
+    #[serde(
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    pub value: T,
+}
+
+fn serialize_display<T: std::fmt::Display, S: serde::Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+    D: serde::Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    text.parse().map_err(serde::de::Error::custom)
+}
+
+/// Only the listed instantiations get impls, concrete ones without bounds,
+/// `Reading<bool>` has none.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(instantiate(Reading<u32>, Reading<String>))]
+pub struct Reading<T> {
+    pub value: T,
+    pub unit: String,
+}
+
+/// Borrows its strings, so it can only be written.
+#[derive(Debug, Serialize, AsExpression, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(serialize_only)]
+pub struct Ref<'a, 'b: 'a> {
+    pub first: &'a str,
+    pub second: &'b str,
+}
+
+/// The same with `Cow`s, deserialized as owned strings.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Owned<'a, 'b: 'a> {
+    pub first: std::borrow::Cow<'a, str>,
+    pub second: std::borrow::Cow<'b, str>,
+}
+
+/// Only `Label<'static>` can be deserialized, so `owned_read` implements
+/// `FromSql` for it alone. Writing works for any lifetime.
+#[derive(Debug, PartialEq, Serialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(owned_read)]
+pub struct Label<'a>(pub std::borrow::Cow<'a, str>);
+
+impl<'de> Deserialize<'de> for Label<'static> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|text| Label(text.into()))
+    }
+}
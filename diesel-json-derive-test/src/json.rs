@@ -0,0 +1,182 @@
+//! A type stored in a `JSON` (not `JSONB`) column.
+
+use diesel::prelude::*;
+use diesel::sql_types::{Binary, Json, Jsonb, Text};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::{diesel_jsonb, DieselJsonb};
+use serde::{Deserialize, Serialize};
+
+diesel::table! {
+    json_foo (id) {
+        id -> Text,
+        baz -> Json,
+    }
+}
+
+diesel::table! {
+    both_foo (id) {
+        id -> Text,
+        as_json -> Json,
+        as_jsonb -> Jsonb,
+    }
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = json_foo)]
+#[diesel(check_for_backend(diesel::pg::Pg, diesel::sqlite::Sqlite))]
+pub struct JsonFoo {
+    pub id: String,
+    pub baz: Baz,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+pub struct Baz {
+    pub y: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(generic_backend, nullable)]
+pub struct AnyBackend {
+    pub y: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(pretty)]
+pub struct Pretty {
+    pub y: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(formatter = "ascii_formatter")]
+pub struct Escaped {
+    pub y: String,
+}
+
+/// Written with a formatter that doesn't escape control characters, which
+/// `validate_on_write` catches before the invalid JSON is stored.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel(sql_type = Text)]
+#[diesel_json(
+    sql_types(Binary, Text),
+    formatter = "unescaped_formatter",
+    validate_on_write
+)]
+pub struct Unescaped {
+    pub y: String,
+}
+
+/// Written the same way however the map was built.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sort_keys)]
+pub struct Sorted {
+    pub version: u32,
+    pub counts: std::collections::HashMap<String, u32>,
+    pub attributes: Vec<serde_json::Value>,
+}
+
+/// Written as the canonical JSON of RFC 8785, the same bytes for equal values
+/// however their maps were built and whatever their numbers are stored as.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(canonical)]
+pub struct Canonical {
+    pub name: String,
+    pub ratio: f64,
+    pub attributes: std::collections::HashMap<String, serde_json::Value>,
+}
+
+/// `Canonical` with its fields declared the other way around.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(canonical)]
+pub struct CanonicalReordered {
+    pub attributes: std::collections::HashMap<String, serde_json::Value>,
+    pub ratio: f64,
+    pub name: String,
+}
+
+/// Escapes everything but ASCII, for readers that don't decode UTF-8.
+pub struct AsciiFormatter;
+
+impl serde_json::ser::Formatter for AsciiFormatter {
+    fn write_string_fragment<W: ?Sized + std::io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> std::io::Result<()> {
+        for c in fragment.chars() {
+            if c.is_ascii() {
+                writer.write_all(&[c as u8])?;
+            } else {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(writer, "\\u{unit:04x}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn ascii_formatter() -> AsciiFormatter {
+    AsciiFormatter
+}
+
+/// Writes control characters as they are instead of escaping them.
+pub struct UnescapedFormatter;
+
+impl serde_json::ser::Formatter for UnescapedFormatter {
+    fn write_char_escape<W: ?Sized + std::io::Write>(
+        &mut self,
+        writer: &mut W,
+        char_escape: serde_json::ser::CharEscape,
+    ) -> std::io::Result<()> {
+        use serde_json::ser::CharEscape;
+        let byte = match char_escape {
+            CharEscape::Quote => return writer.write_all(b"\\\""),
+            CharEscape::ReverseSolidus => return writer.write_all(b"\\\\"),
+            CharEscape::Solidus => b'/',
+            CharEscape::Backspace => b'\x08',
+            CharEscape::FormFeed => b'\x0c',
+            CharEscape::LineFeed => b'\n',
+            CharEscape::CarriageReturn => b'\r',
+            CharEscape::Tab => b'\t',
+            CharEscape::AsciiControl(byte) => byte,
+        };
+        writer.write_all(&[byte])
+    }
+}
+
+fn unescaped_formatter() -> UnescapedFormatter {
+    UnescapedFormatter
+}
+
+/// The same type in a `JSON` and a `JSONB` column.
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = both_foo)]
+pub struct BothFoo {
+    pub id: String,
+    pub as_json: Both,
+    pub as_jsonb: Both,
+}
+
+/// Every `sql_type` gets impls, `AsExpression` reads them as well.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel(sql_type = Jsonb)]
+pub struct Both {
+    pub y: String,
+}
+
+/// `#[diesel_jsonb]` adds one `sql_type` attribute for each of `sql_types`.
+#[diesel_jsonb]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[diesel_json(sql_types(Json, Jsonb))]
+pub struct BothAttribute {
+    pub y: String,
+}
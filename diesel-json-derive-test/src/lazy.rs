@@ -0,0 +1,33 @@
+//! Large documents with a part that is rarely needed, kept as unparsed JSON
+//! in a `Box<RawValue>` until it is.
+
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sql_types(Jsonb, Json))]
+pub struct Report {
+    pub title: String,
+    pub details: Box<RawValue>,
+}
+
+/// Reports with their `RawValue`, which the derive doesn't see in a
+/// `Vec<Report>`.
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(raw_value)]
+pub struct Archive {
+    pub reports: Vec<Report>,
+}
+
+/// What `details` holds, parsed when it is needed.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Details {
+    pub pages: u32,
+    pub authors: Vec<String>,
+}
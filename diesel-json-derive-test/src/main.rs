@@ -1,28 +1,40 @@
+mod arrays;
+mod attributes;
+mod binary;
+mod foreign;
+mod generated;
+mod generics;
+mod json;
+mod lazy;
+mod models;
+mod naming;
+mod no_prelude;
+mod nullable;
+mod pg;
+mod pg_async;
+#[cfg(feature = "arbitrary-precision")]
+mod precision;
+mod proxied;
+mod remote;
 mod schema;
+mod shapes;
+mod shared;
+mod sql_type;
+mod sqlx_wire;
+mod strict;
+mod text;
+mod wide;
 
-use diesel::prelude::*;
-use diesel::sql_types::Jsonb;
-use diesel::{FromSqlRow, AsExpression};
-use diesel_json_derive::DieselJsonb;
-use serde::{Deserialize, Serialize};
-
-#[derive(
-    Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Selectable,
-)]
-#[diesel(table_name = crate::schema::foo)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
-#[diesel(primary_key(id))]
-struct Foo {
-    id: String,
-    bar: Bar,
-}
-
-#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
-#[diesel(sql_type = Jsonb)]
-struct Bar {
-    x: i32,
-}
+use models::{Bar, Foo, FooView};
 
 fn main() {
-    unimplemented!()
+    if let Ok(database_url) = std::env::var("DATABASE_URL") {
+        pg::roundtrip(&database_url).unwrap();
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(pg_async::roundtrip(&database_url))
+            .unwrap();
+    }
 }
@@ -0,0 +1,51 @@
+//! The models of the `foo` table, the `JSONB` column every backend is tested
+//! with.
+
+use diesel::prelude::*;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// `check_for_backend` finds the impls of `Bar` for both backends, they are
+/// ordinary trait impls next to the type.
+#[derive(
+    Debug,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Selectable,
+)]
+#[diesel(table_name = crate::schema::foo)]
+#[diesel(check_for_backend(diesel::pg::Pg, diesel::sqlite::Sqlite))]
+#[diesel(primary_key(id))]
+pub struct Foo {
+    pub id: String,
+    pub bar: Bar,
+}
+
+/// `foo` with the JSONB column under another name and once more through a
+/// select expression, which `Selectable` reads with the impls of `Bar` (and
+/// of `Option<Bar>`) like any other field.
+#[derive(Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::foo)]
+#[diesel(check_for_backend(diesel::pg::Pg, diesel::sqlite::Sqlite))]
+pub struct FooView {
+    #[diesel(column_name = bar)]
+    pub payload: Bar,
+    #[diesel(
+        select_expression = crate::schema::foo::bar.nullable(),
+        select_expression_type = diesel::dsl::Nullable<crate::schema::foo::bar>,
+    )]
+    pub maybe_payload: Option<Bar>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Bar {
+    pub x: i32,
+}
@@ -0,0 +1,189 @@
+//! Types whose generated impls must not collide with each other.
+
+#![allow(clippy::upper_case_acronyms, non_camel_case_types)]
+
+use diesel::sql_types::{Binary, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+pub mod a {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Config {
+        pub a: i32,
+    }
+}
+
+pub mod b {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Config {
+        pub b: i32,
+    }
+}
+
+// The next three names are all `http_config` in snake case.
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct HttpConfig {
+    pub url: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct HTTPConfig {
+    pub url: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct http_config {
+    pub url: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct JSONRPCOverHTTPSURL {
+    pub url: String,
+}
+
+/// Fields with raw identifiers, serde writes them without the `r#`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Kinds {
+    pub r#type: String,
+    pub r#struct: u8,
+}
+
+/// A type with a raw identifier is named without the `r#` in errors.
+/// Diesel's derives fail on raw type names, so `Queryable` is implemented by
+/// hand, like `FromSqlRow` would.
+#[derive(Debug, PartialEq, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Jsonb))]
+pub struct r#Type {
+    pub r#type: String,
+}
+
+impl diesel::deserialize::Queryable<Jsonb, diesel::pg::Pg> for r#Type {
+    type Row = Self;
+
+    fn build(row: Self) -> diesel::deserialize::Result<Self> {
+        Ok(row)
+    }
+}
+
+/// Types named like diesel's sql types, which the impls name by their full
+/// paths.
+pub mod sql_type_names {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel(sql_type = Binary)]
+    #[diesel_json(sql_types(Jsonb, Binary), nullable)]
+    pub struct Text {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel(sql_type = diesel::sql_types::Json)]
+    #[diesel_json(sql_types(Jsonb, Json))]
+    pub struct Json {
+        pub y: String,
+    }
+}
+
+/// The generated code imports nothing and names every trait by its full path,
+/// so a type can have the name of one that `to_sql` uses, here
+/// `std::io::Write`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(sql_types(Jsonb, Binary), atomic_write, inherent_methods)]
+pub struct Write {
+    pub bytes: Vec<u8>,
+}
+
+/// Types named like the diesel traits and backends the impls use.
+pub mod trait_names {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct ToSql {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel_json(nullable)]
+    pub struct FromSql {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    #[diesel_json(inherent_methods)]
+    pub struct Pg {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Binary)]
+    pub struct Sqlite {
+        pub x: i32,
+    }
+}
+
+/// Stamps out models the way a crate generating them would, the derive sees
+/// tokens coming from the macro.
+macro_rules! models {
+    ($($name:ident),*) => {
+        $(
+            #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+            #[diesel(sql_type = Jsonb)]
+            pub struct $name {
+                pub key: String,
+            }
+        )*
+    };
+}
+
+// both are `api_key` in snake case
+models!(ApiKey, APIKey);
+
+pub mod defined {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Original {
+        pub x: i32,
+    }
+}
+
+/// The derive works on the definition, a re-export under another name gets
+/// the same impls and errors name the type as it is defined, `Original`.
+pub use defined::Original as Renamed;
+
+/// A module seeing `defined::Original` through a glob import and re-exporting
+/// it as `Exported` defines an `Original` of its own, which the impls of its
+/// derive are for.
+pub mod shadowing {
+    pub use super::defined::Original as Exported;
+    #[allow(unused_imports)]
+    use super::defined::*;
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Original {
+        pub y: String,
+    }
+}
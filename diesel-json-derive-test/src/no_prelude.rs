@@ -0,0 +1,70 @@
+//! The generated code names everything by its full path, so it works without
+//! the prelude. Diesel's own derives don't, they `use diesel`, so the types
+//! only get `ToSql` and `FromSql`, with the sql types given in
+//! `#[diesel_json(...)]`.
+
+#![no_implicit_prelude]
+#![allow(dead_code)]
+
+use ::diesel_json_derive::DieselJsonb;
+use ::serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Jsonb))]
+pub struct Plain {
+    pub x: i32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Jsonb))]
+#[diesel_json(
+    version = 2,
+    version_check = "any_version",
+    empty_as_default,
+    strict,
+    validate = "check",
+    size_hint = 64,
+    default_on_error
+)]
+pub struct Checked {
+    pub x: i32,
+}
+
+fn any_version(_: u8) -> bool {
+    true
+}
+
+fn check(_: &Checked) -> ::core::result::Result<(), &'static str> {
+    ::core::result::Result::Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Json, Text))]
+#[diesel_json(atomic_write, ignore_trailing, streaming)]
+pub struct Lenient {
+    pub x: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Json))]
+#[diesel_json(generic_backend)]
+pub struct Generic<T> {
+    pub value: T,
+}
+
+#[derive(Debug, DieselJsonb)]
+#[diesel_json(sql_types(Jsonb))]
+#[diesel_json(transparent_value)]
+pub struct Dynamic(pub ::serde_json::Value);
+
+#[derive(Debug, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Binary), format = "msgpack")]
+pub struct Packed {
+    pub x: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, DieselJsonb)]
+#[diesel_json(sql_types(Binary), format = "cbor", size_hint = 16)]
+pub struct Concise {
+    pub x: i32,
+}
@@ -0,0 +1,152 @@
+//! A nullable `JSONB` column. SQL `NULL` is handled by diesel's `Nullable`
+//! impls, the generated `from_sql` only ever sees actual values. And an
+//! optional value in a `NOT NULL` column, stored as the JSON `null`.
+//! `nullable` loads a type from the nullable column without the `Option`.
+
+use diesel::prelude::*;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+use crate::Bar;
+
+diesel::table! {
+    maybe_foo (id) {
+        id -> Text,
+        bar -> Nullable<Jsonb>,
+    }
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = maybe_foo)]
+pub(crate) struct MaybeFoo {
+    pub(crate) id: String,
+    pub(crate) bar: Option<Bar>,
+}
+
+/// `Bar` for rows of `maybe_foo` that are known to have one.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(nullable)]
+pub(crate) struct PresentBar {
+    pub(crate) x: i32,
+}
+
+#[derive(Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = maybe_foo)]
+pub(crate) struct PresentFoo {
+    pub(crate) id: String,
+    pub(crate) bar: PresentBar,
+}
+
+diesel::table! {
+    json_null_foo (id) {
+        id -> Text,
+        bar -> Jsonb,
+    }
+}
+
+/// `None` is written as `null`, which is read back as `None`.
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(transparent_value)]
+pub(crate) struct MaybeBar(pub(crate) Option<Bar>);
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = json_null_foo)]
+pub(crate) struct JsonNullFoo {
+    pub(crate) id: String,
+    pub(crate) bar: MaybeBar,
+}
+
+/// Inserts a row with and one without a value into `maybe_foo` and loads
+/// them back. A macro since the bounds for doing this generically over the
+/// backend don't resolve.
+macro_rules! assert_nullable_roundtrip {
+    ($conn:expr) => {{
+        use crate::nullable::{maybe_foo, MaybeFoo, PresentBar, PresentFoo};
+
+        let values = vec![
+            MaybeFoo {
+                id: "some".to_string(),
+                bar: Some(crate::Bar { x: 1 }),
+            },
+            MaybeFoo {
+                id: "none".to_string(),
+                bar: None,
+            },
+        ];
+        diesel::insert_into(maybe_foo::table)
+            .values(&values)
+            .execute($conn)?;
+        let loaded = maybe_foo::table
+            .order(maybe_foo::id.desc())
+            .select(MaybeFoo::as_select())
+            .load($conn)?;
+        assert_eq!(loaded, values);
+        let loaded = maybe_foo::table
+            .filter(maybe_foo::bar.is_null())
+            .select(maybe_foo::bar)
+            .load::<Option<crate::Bar>>($conn)?;
+        assert_eq!(loaded, vec![None]);
+
+        // `nullable` writes and reads the type itself, `Option` still works
+        diesel::insert_into(maybe_foo::table)
+            .values((
+                maybe_foo::id.eq("present"),
+                maybe_foo::bar.eq(PresentBar { x: 2 }),
+            ))
+            .execute($conn)?;
+        let loaded = maybe_foo::table
+            .filter(maybe_foo::bar.is_not_null())
+            .order(maybe_foo::id)
+            .select(PresentFoo::as_select())
+            .load($conn)?;
+        assert_eq!(
+            loaded,
+            vec![
+                PresentFoo {
+                    id: "present".to_string(),
+                    bar: PresentBar { x: 2 },
+                },
+                PresentFoo {
+                    id: "some".to_string(),
+                    bar: PresentBar { x: 1 },
+                },
+            ]
+        );
+        let loaded = maybe_foo::table
+            .order(maybe_foo::id)
+            .select(maybe_foo::bar)
+            .load::<Option<PresentBar>>($conn)?;
+        assert_eq!(
+            loaded,
+            vec![None, Some(PresentBar { x: 2 }), Some(PresentBar { x: 1 })]
+        );
+        let error = maybe_foo::table
+            .filter(maybe_foo::id.eq("none"))
+            .select(maybe_foo::bar)
+            .first::<PresentBar>($conn);
+        let Err(diesel::result::Error::DeserializationError(error)) = error else {
+            panic!("expected a deserialization error, got {error:?}");
+        };
+        let error = error
+            .downcast::<diesel::result::DeserializeFieldError>()
+            .expect("a field error")
+            .error
+            .downcast::<diesel_json_derive::DieselJsonError>()
+            .expect("a DieselJsonError");
+        assert!(
+            matches!(
+                *error,
+                diesel_json_derive::DieselJsonError::UnexpectedNull {
+                    type_name: "PresentBar"
+                }
+            ),
+            "{error}"
+        );
+    }};
+}
+
+pub(crate) use assert_nullable_roundtrip;
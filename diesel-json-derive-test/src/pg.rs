@@ -0,0 +1,2344 @@
+//! Round-trips `DieselJsonb` types through Postgres. The tables are created as
+//! temporary tables so this can run against any database, e.g. the one started
+//! with `just pg_start`.
+
+use std::sync::Arc;
+
+use diesel::deserialize::FromSqlRow;
+use diesel::dsl::sql;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::query_builder::bind_collector::RawBytesBindCollector;
+use diesel::query_builder::BindCollector;
+use diesel::serialize::ToSql;
+use diesel::sql_types::{Array, Binary, HasSqlType, Integer, Json, Jsonb, Nullable, Record, Text};
+use diesel_json_derive::{
+    crc32, diesel_jsonb_model, jsonb_merge, DieselJsonError, Interned, CHECKSUM_MARKER,
+    JSONB_VERSION,
+};
+use serde_json::value::RawValue;
+
+use crate::arrays::{bar_lists, BarList};
+use crate::attributes::{
+    AnyVersion, AtomicFailsMidway, AttributeMacro, AuditEntry, AuditMessage, AuditRecord, Batched,
+    Capped, Catalog, CfgGated, Compatible, Converted, ConvertedDynamic, Degrading,
+    DegradingMigrating, Dynamic, DynamicNamed, Evolved, Failing, FailsMidway, Fixture, FooSnapshot,
+    Forward, Imported, ImportedCount, Legacy, Lenient, Located, Migrating, Notifications,
+    ObjectOnly, Percent, PgOnly, Profile, Quota, Reader, Settings, Shallow, SizeHinted,
+    SkipVersionCheck, SortedFixture, Streaming, Tolerant, Upgraded, VersionTwo,
+};
+use crate::binary::{
+    assert_compressed_roundtrip, assert_formats_roundtrip, assert_tagged_roundtrip, binary_foo,
+    BinaryFoo, Compressed, Guarded, Layered, Note, Quux,
+};
+use crate::foreign::external::Priority;
+use crate::foreign::{LocalPriority, Ticket};
+use crate::generics::{
+    Body, Cache, Entry, FixedVec, Holder, Label, Marked, Measured, Meta, Meters, Mixed,
+    NonSerializableTag, Owned, Reading, Ref, Shown, Tagged, Wrapper, V1,
+};
+use crate::json::{
+    both_foo, json_foo, AnyBackend, Baz, Both, BothAttribute, BothFoo, Canonical,
+    CanonicalReordered, Escaped, JsonFoo, Pretty, Sorted, Unescaped,
+};
+use crate::lazy::{Archive, Details, Report};
+use crate::naming::{
+    http_config, r#Type, shadowing, APIKey, ApiKey, HTTPConfig, JSONRPCOverHTTPSURL, Kinds,
+    Renamed, Write,
+};
+use crate::nullable::{assert_nullable_roundtrip, json_null_foo, JsonNullFoo, MaybeBar};
+use crate::proxied::{proxied_foo, Payload, ProxiedFoo, Tag, Temperature};
+use crate::remote::elsewhere::{First, Labelled, Manual, Pair, Remote, RemoteJson, Second, Third};
+use crate::schema::foo;
+use crate::shapes::{
+    AdjacentlyTagged, ApiSettings, ApiSettingsPatch, Attributes, Bars, Compact, Counters, Counts,
+    Document, EmptyNamed, EmptyTuple, Event, Flattened, Gated, InternallyTagged, Named, Newtype,
+    Person, Slot, Sparse, Status, Transparent, TransparentNamed, Tuple, Unit,
+};
+use crate::shared::{BoxedFoo, NewFoo, SharedFoo};
+use crate::sql_type::{Layout, Setting, Widget};
+use crate::sqlx_wire::{sqlx_decode, Migrated, SqlxJson};
+use crate::text::{text_foo, Qux, TextFoo};
+use crate::wide::{Wide, WideAsString, WideSorted};
+use crate::{Bar, Foo, FooView};
+
+/// Binds `$value` as a `Jsonb` parameter and selects it back as `$ty`.
+macro_rules! assert_select_roundtrip {
+    ($conn:expr, $ty:ty, $value:expr) => {{
+        let value: $ty = $value;
+        let loaded =
+            diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value)).get_result::<$ty>($conn)?;
+        assert_eq!(loaded, value);
+    }};
+}
+
+/// Like `assert_select_roundtrip!` but for `Json`.
+macro_rules! assert_select_json_roundtrip {
+    ($conn:expr, $ty:ty, $value:expr) => {{
+        let value: $ty = $value;
+        let loaded =
+            diesel::select(sql::<Json>("").bind::<Json, _>(&value)).get_result::<$ty>($conn)?;
+        assert_eq!(loaded, value);
+    }};
+}
+
+/// Changeset binding the value by reference. diesel's `ToSql` impl for `&T`
+/// covers the generated impls, so nothing extra is needed for this.
+#[derive(AsChangeset)]
+#[diesel(table_name = foo)]
+struct BarChange<'a> {
+    bar: &'a Bar,
+}
+
+/// Loaded with `sql_query`, the field's `sql_type` picks the generated
+/// `FromSql` impl.
+#[derive(Debug, PartialEq, QueryableByName)]
+struct RawFoo {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = Jsonb)]
+    bar: Bar,
+}
+
+/// The same with the `sql_type`s added by `#[diesel_jsonb_model]`, a
+/// nullable one for the `Option` and another sql type.
+#[diesel_jsonb_model]
+#[derive(Debug, PartialEq, QueryableByName)]
+struct MarkedFoo {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel_jsonb]
+    bar: Bar,
+    #[diesel_jsonb]
+    missing: Option<Bar>,
+    #[diesel_jsonb(sql_type = Json)]
+    label: Baz,
+}
+
+/// `Queryable` and `Selectable` take the sql types from the table, the
+/// attribute changes nothing for them and `QueryableByName` reads the
+/// columns the fields aren't marked for from the table too.
+#[diesel_jsonb_model]
+#[derive(Debug, PartialEq, Queryable, Selectable, QueryableByName)]
+#[diesel(table_name = foo)]
+struct MarkedModel {
+    id: String,
+    #[diesel_jsonb]
+    bar: Bar,
+}
+
+pub fn roundtrip(database_url: &str) -> QueryResult<()> {
+    let mut conn = PgConnection::establish(database_url).expect("postgres connection");
+    diesel::sql_query("CREATE TEMPORARY TABLE foo (id TEXT PRIMARY KEY, bar JSONB NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query("CREATE TEMPORARY TABLE json_foo (id TEXT PRIMARY KEY, baz JSON NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE both_foo \
+         (id TEXT PRIMARY KEY, as_json JSON NOT NULL, as_jsonb JSONB NOT NULL)",
+    )
+    .execute(&mut conn)?;
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE proxied_foo \
+         (id TEXT PRIMARY KEY, tag TEXT NOT NULL, payload JSONB NOT NULL)",
+    )
+    .execute(&mut conn)?;
+    diesel::sql_query("CREATE TEMPORARY TABLE text_foo (id TEXT PRIMARY KEY, qux TEXT NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE binary_foo (id TEXT PRIMARY KEY, quux BYTEA NOT NULL)",
+    )
+    .execute(&mut conn)?;
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE json_null_foo (id TEXT PRIMARY KEY, bar JSONB NOT NULL)",
+    )
+    .execute(&mut conn)?;
+    diesel::sql_query("CREATE TEMPORARY TABLE maybe_foo (id TEXT PRIMARY KEY, bar JSONB)")
+        .execute(&mut conn)?;
+
+    let value = Foo {
+        id: "pg".to_string(),
+        bar: Bar { x: 42 },
+    };
+    diesel::insert_into(foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = foo::table
+        .find(&value.id)
+        .select(Foo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    let loaded = foo::table
+        .find(&value.id)
+        .select(FooView::as_select())
+        .first(&mut conn)?;
+    assert_eq!(
+        loaded,
+        FooView {
+            payload: Bar { x: 42 },
+            maybe_payload: Some(Bar { x: 42 }),
+        }
+    );
+
+    let bar = Bar { x: 43 };
+    diesel::update(foo::table.find(&value.id))
+        .set(BarChange { bar: &bar })
+        .execute(&mut conn)?;
+    let loaded = foo::table
+        .find(&value.id)
+        .select(foo::bar)
+        .first::<Bar>(&mut conn)?;
+    assert_eq!(loaded, bar);
+
+    let snapshot = FooSnapshot {
+        id: "snapshot".to_string(),
+        bar: Bar { x: 44 },
+    };
+    diesel::insert_into(foo::table)
+        .values(&snapshot)
+        .execute(&mut conn)?;
+    assert_select_roundtrip!(&mut conn, FooSnapshot, snapshot);
+
+    let loaded = diesel::sql_query("SELECT id, bar FROM foo WHERE id = $1")
+        .bind::<Text, _>(&value.id)
+        .get_result::<RawFoo>(&mut conn)?;
+    assert_eq!(
+        loaded,
+        RawFoo {
+            id: value.id.clone(),
+            bar,
+        }
+    );
+    let loaded = diesel::sql_query(
+        "SELECT id, bar, NULL::jsonb AS missing, '{\"y\":\"json\"}'::json AS label \
+         FROM foo WHERE id = $1",
+    )
+    .bind::<Text, _>(&value.id)
+    .get_result::<MarkedFoo>(&mut conn)?;
+    assert_eq!(
+        loaded,
+        MarkedFoo {
+            id: value.id.clone(),
+            bar: Bar { x: 43 },
+            missing: None,
+            label: Baz {
+                y: "json".to_string()
+            },
+        }
+    );
+    let model = MarkedModel {
+        id: value.id.clone(),
+        bar: Bar { x: 43 },
+    };
+    let loaded = foo::table
+        .find(&value.id)
+        .select(MarkedModel::as_select())
+        .get_result(&mut conn)?;
+    assert_eq!(loaded, model);
+    let loaded = diesel::sql_query("SELECT id, bar FROM foo WHERE id = $1")
+        .bind::<Text, _>(&value.id)
+        .get_result::<MarkedModel>(&mut conn)?;
+    assert_eq!(loaded, model);
+
+    let value = JsonFoo {
+        id: "pg".to_string(),
+        baz: Baz {
+            y: "json".to_string(),
+        },
+    };
+    diesel::insert_into(json_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = json_foo::table
+        .find(&value.id)
+        .select(JsonFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+
+    let value = ProxiedFoo {
+        id: "pg".to_string(),
+        tag: Tag::Red,
+        payload: Payload {
+            tag: "blue".to_string(),
+            weight: 3,
+        },
+    };
+    diesel::insert_into(proxied_foo::table)
+        .values(value.clone())
+        .execute(&mut conn)?;
+    let loaded = proxied_foo::table
+        .find(&value.id)
+        .select(ProxiedFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    let tag = proxied_foo::table
+        .find(&value.id)
+        .select(proxied_foo::tag)
+        .first::<String>(&mut conn)?;
+    assert_eq!(tag, "red");
+
+    let value = Temperature { kelvin: 373.15 };
+    let celsius = diesel::select(
+        sql::<Text>("(")
+            .bind::<Jsonb, _>(&value)
+            .sql(") ->> 'celsius'"),
+    )
+    .get_result::<String>(&mut conn)?;
+    assert_eq!(celsius, "100.0");
+    assert_select_roundtrip!(&mut conn, Temperature, value);
+    let error = read_raw::<Temperature>(&mut conn, b"\x01{\"celsius\":-300.0}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::Deserialize { .. }),
+        "{error}"
+    );
+    assert!(
+        error.to_string().contains("-300 °C is below absolute zero"),
+        "{error}"
+    );
+    // serde_json writes NaN as `null` without an error, reading it fails
+    let value = Temperature { kelvin: f64::NAN };
+    let bytes = bound_bytes(&mut conn, &value);
+    assert_eq!(bytes, b"\x01{\"celsius\":null}");
+    let error = read_raw::<Temperature>(&mut conn, &bytes).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Temperature",
+                ..
+            }
+        ),
+        "{error}"
+    );
+
+    let value = BothFoo {
+        id: "pg".to_string(),
+        as_json: Both {
+            y: "json".to_string(),
+        },
+        as_jsonb: Both {
+            y: "jsonb".to_string(),
+        },
+    };
+    diesel::insert_into(both_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = both_foo::table
+        .find(&value.id)
+        .select(BothFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    assert_select_roundtrip!(
+        &mut conn,
+        BothAttribute,
+        BothAttribute {
+            y: "jsonb".to_string()
+        }
+    );
+    assert_select_json_roundtrip!(
+        &mut conn,
+        BothAttribute,
+        BothAttribute {
+            y: "json".to_string()
+        }
+    );
+
+    assert_select_roundtrip!(&mut conn, AttributeMacro, AttributeMacro { x: 1 });
+    assert_select_roundtrip!(
+        &mut conn,
+        SizeHinted,
+        SizeHinted {
+            lines: vec!["line".to_string(); 100]
+        }
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        Streaming,
+        Streaming {
+            lines: vec!["line".to_string(); 100]
+        }
+    );
+    assert_select_roundtrip!(&mut conn, PgOnly, PgOnly { x: 1 });
+    assert_select_roundtrip!(&mut conn, CfgGated, CfgGated { x: 2 });
+    assert_select_json_roundtrip!(&mut conn, CfgGated, CfgGated { x: 3 });
+    let value = Catalog {
+        entries: ["small", "large", "large"]
+            .into_iter()
+            .map(|size| {
+                [("color", "blue"), ("size", size)]
+                    .into_iter()
+                    .map(|(key, value)| (Interned::from(key), Interned::from(value)))
+                    .collect()
+            })
+            .collect(),
+    };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+        .get_result::<Catalog>(&mut conn)?;
+    assert_eq!(loaded, value);
+    let (color, blue) = loaded.entries[0].get_key_value("color").unwrap();
+    let (last_color, last_blue) = loaded.entries[2].get_key_value("color").unwrap();
+    assert!(color.ptr_eq(last_color) && blue.ptr_eq(last_blue));
+    assert!(loaded.entries[1]["size"].ptr_eq(&loaded.entries[2]["size"]));
+    assert!(!loaded.entries[0]["size"].ptr_eq(&loaded.entries[1]["size"]));
+    // without the interner of `from_sql` every string is allocated
+    let json = serde_json::to_string(&value).unwrap();
+    let parsed = serde_json::from_str::<Catalog>(&json).unwrap();
+    assert!(!parsed.entries[0]["color"].ptr_eq(&parsed.entries[2]["color"]));
+    assert_select_roundtrip!(
+        &mut conn,
+        Dynamic,
+        Dynamic(serde_json::json!({"any": ["shape", 1, null]}))
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        DynamicNamed,
+        DynamicNamed {
+            value: serde_json::json!([{"nested": true}])
+        }
+    );
+
+    let value = Converted {
+        name: "converted".to_string(),
+        tags: vec!["a".to_string()],
+    };
+    let stored = diesel::select(sql::<Text>("(").bind::<Jsonb, _>(&value).sql(")::text"))
+        .get_result::<String>(&mut conn)?;
+    let json = serde_json::Value::from(value.clone());
+    assert_eq!(
+        json,
+        serde_json::from_str::<serde_json::Value>(&stored).unwrap()
+    );
+    assert_eq!(Converted::try_from(json).unwrap(), value);
+    let error = Converted::try_from(serde_json::json!({"name": 1})).unwrap_err();
+    assert!(error.is_data(), "{error}");
+    let json = serde_json::json!({"any": "shape"});
+    let dynamic = ConvertedDynamic::try_from(json.clone()).unwrap();
+    assert_eq!(dynamic, ConvertedDynamic(json.clone()));
+    assert_eq!(serde_json::Value::from(dynamic), json);
+
+    assert_select_roundtrip!(&mut conn, Remote, Remote { x: 1 });
+    assert_select_json_roundtrip!(&mut conn, RemoteJson, RemoteJson { x: 1 });
+    assert_select_roundtrip!(&mut conn, Pair<i32>, Pair { a: 1, b: 2 });
+    let labelled = || Labelled {
+        value: vec![1, 2],
+        label: "two".to_string(),
+    };
+    assert_select_json_roundtrip!(&mut conn, Labelled<Vec<i32>>, labelled());
+    // sort_keys was passed on after the where clause
+    let stored = diesel::select(sql::<Text>("(").bind::<Json, _>(&labelled()).sql(")::text"))
+        .get_result::<String>(&mut conn)?;
+    assert_eq!(stored, r#"{"label":"two","value":[1,2]}"#);
+    assert_select_roundtrip!(&mut conn, Manual, Manual { x: 1 });
+    assert_select_roundtrip!(
+        &mut conn,
+        HTTPConfig,
+        HTTPConfig {
+            url: "upper".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        http_config,
+        http_config {
+            url: "lower".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        JSONRPCOverHTTPSURL,
+        JSONRPCOverHTTPSURL {
+            url: "acronyms".to_string()
+        }
+    );
+    assert_select_roundtrip!(&mut conn, Write, Write { bytes: vec![1, 2] });
+    let value = Write { bytes: vec![3] };
+    let loaded = diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+        .get_result::<Write>(&mut conn)?;
+    assert_eq!(loaded, value);
+    assert_eq!(
+        Write::from_jsonb_bytes(&value.to_jsonb_bytes().unwrap()).unwrap(),
+        value
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        ApiKey,
+        ApiKey {
+            key: "camel".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        &mut conn,
+        APIKey,
+        APIKey {
+            key: "upper".to_string()
+        }
+    );
+    let value = Kinds {
+        r#type: "raw".to_string(),
+        r#struct: 1,
+    };
+    let text = diesel::select(sql::<Text>("(").bind::<Jsonb, _>(&value).sql(")::text"))
+        .get_result::<String>(&mut conn)?;
+    assert_eq!(text, r#"{"type": "raw", "struct": 1}"#);
+    assert_select_roundtrip!(&mut conn, Kinds, value);
+    let loaded = read_raw::<r#Type>(&mut conn, b"\x01{\"type\":\"raw\"}").unwrap();
+    assert_eq!(
+        loaded,
+        r#Type {
+            r#type: "raw".to_string()
+        }
+    );
+    let error = read_raw::<r#Type>(&mut conn, b"\x01{\"type\":1}").unwrap_err();
+    assert!(
+        error.to_string().starts_with("Invalid Json for Type: "),
+        "{error}"
+    );
+    assert_select_roundtrip!(&mut conn, Renamed, Renamed { x: 1 });
+    let error = read_raw::<Renamed>(&mut conn, b"\x01{}").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Original",
+                ..
+            }
+        ),
+        "{error}"
+    );
+    assert_select_roundtrip!(&mut conn, shadowing::Exported, shadowing::Exported { x: 2 });
+    let value = shadowing::Original {
+        y: "shadowing".to_string(),
+    };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+        .get_result::<shadowing::Original>(&mut conn)?;
+    assert_eq!(loaded, value);
+    assert_select_roundtrip!(&mut conn, First, First { a: 1 });
+    assert_select_roundtrip!(
+        &mut conn,
+        Second,
+        Second {
+            b: "two".to_string()
+        }
+    );
+    assert_select_roundtrip!(&mut conn, Third, Third(vec![true, false, true]));
+    assert_select_json_roundtrip!(
+        &mut conn,
+        AnyBackend,
+        AnyBackend {
+            y: "generic".to_string()
+        }
+    );
+    let loaded = diesel::select(sql::<Nullable<Json>>("").bind::<Json, _>(AnyBackend {
+        y: "generic".to_string(),
+    }))
+    .get_result::<AnyBackend>(&mut conn)?;
+    assert_eq!(loaded.y, "generic");
+
+    let value = Pretty {
+        y: "pretty".to_string(),
+    };
+    let text =
+        diesel::select(sql::<Text>("").bind::<Json, _>(&value)).get_result::<String>(&mut conn)?;
+    assert_eq!(text, "{\n  \"y\": \"pretty\"\n}");
+    assert_select_json_roundtrip!(&mut conn, Pretty, value);
+
+    let value = Escaped {
+        y: "\u{e9}\u{1f600}".to_string(),
+    };
+    let text =
+        diesel::select(sql::<Text>("").bind::<Json, _>(&value)).get_result::<String>(&mut conn)?;
+    assert_eq!(text, r#"{"y":"\u00e9\ud83d\ude00"}"#);
+    assert_select_json_roundtrip!(&mut conn, Escaped, value);
+
+    let value = Unescaped {
+        y: "fine".to_string(),
+    };
+    let loaded = diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+        .get_result::<Unescaped>(&mut conn)?;
+    assert_eq!(loaded, value);
+    let loaded = diesel::select(sql::<Text>("").bind::<Text, _>(&value))
+        .get_result::<Unescaped>(&mut conn)?;
+    assert_eq!(loaded, value);
+    let value = Unescaped {
+        y: "two\nlines".to_string(),
+    };
+    for error in [
+        diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+            .execute(&mut conn)
+            .unwrap_err(),
+        diesel::select(sql::<Text>("").bind::<Text, _>(&value))
+            .execute(&mut conn)
+            .unwrap_err(),
+    ] {
+        let diesel::result::Error::SerializationError(error) = error else {
+            panic!("{error:?}");
+        };
+        let error = error.downcast::<DieselJsonError>().unwrap();
+        assert!(
+            matches!(
+                *error,
+                DieselJsonError::InvalidOutput {
+                    type_name: "Unescaped",
+                    ..
+                }
+            ),
+            "{error}"
+        );
+        assert!(
+            error
+                .to_string()
+                .starts_with("Refusing to write Unescaped, it serialized to invalid JSON: "),
+            "{error}"
+        );
+    }
+
+    let sorted = |letters: [&str; 4]| Sorted {
+        version: 1,
+        counts: letters.iter().map(|l| (l.to_string(), 1)).collect(),
+        attributes: vec![serde_json::json!({ "z": 1, "y": { "b": 2, "a": 1 } })],
+    };
+    let value = sorted(["d", "a", "c", "b"]);
+    let reversed = sorted(["b", "c", "a", "d"]);
+    let text =
+        diesel::select(sql::<Text>("").bind::<Json, _>(&value)).get_result::<String>(&mut conn)?;
+    let reversed_text = diesel::select(sql::<Text>("").bind::<Json, _>(&reversed))
+        .get_result::<String>(&mut conn)?;
+    assert_eq!(text, reversed_text);
+    assert_eq!(
+        text,
+        r#"{"attributes":[{"y":{"a":1,"b":2},"z":1}],"counts":{"a":1,"b":1,"c":1,"d":1},"version":1}"#
+    );
+    assert_select_json_roundtrip!(&mut conn, Sorted, value);
+
+    let number = |text: &str| serde_json::from_str::<serde_json::Value>(text).unwrap();
+    let value = Canonical {
+        name: "x".to_string(),
+        ratio: 100.0,
+        attributes: [
+            ("\u{ff61}", serde_json::Value::Null),
+            ("b", serde_json::json!(1)),
+            ("a", serde_json::json!([0.5, 1e21])),
+            ("\u{1f600}", serde_json::Value::Bool(true)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect(),
+    };
+    let reordered = CanonicalReordered {
+        attributes: [
+            ("a", serde_json::json!([number("5e-1"), number("1E21")])),
+            ("\u{1f600}", serde_json::Value::Bool(true)),
+            ("b", number("1.0")),
+            ("\u{ff61}", serde_json::Value::Null),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect(),
+        ratio: 1e2,
+        name: "x".to_string(),
+    };
+    let text =
+        diesel::select(sql::<Text>("").bind::<Json, _>(&value)).get_result::<String>(&mut conn)?;
+    let reordered_text = diesel::select(sql::<Text>("").bind::<Json, _>(&reordered))
+        .get_result::<String>(&mut conn)?;
+    assert_eq!(crc32(text.as_bytes()), crc32(reordered_text.as_bytes()));
+    // the keys in the order of their UTF-16 code units, which puts the
+    // surrogates of U+1F600 before U+FF61
+    assert_eq!(
+        text,
+        "{\"attributes\":{\"a\":[0.5,1e+21],\"b\":1,\"\u{1f600}\":true,\"\u{ff61}\":null},\
+         \"name\":\"x\",\"ratio\":100}"
+    );
+    assert_select_json_roundtrip!(&mut conn, Canonical, value);
+
+    let value = TextFoo {
+        id: "pg".to_string(),
+        qux: Qux { z: vec![1, 2] },
+    };
+    diesel::insert_into(text_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = text_foo::table
+        .find(&value.id)
+        .select(TextFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+
+    let value = BinaryFoo {
+        id: "pg".to_string(),
+        quux: Quux { w: true },
+    };
+    diesel::insert_into(binary_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = binary_foo::table
+        .find(&value.id)
+        .select(BinaryFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    let bytes = binary_foo::table
+        .find(&value.id)
+        .select(diesel::dsl::sql::<diesel::sql_types::Binary>("quux"))
+        .first::<Vec<u8>>(&mut conn)?;
+    assert_eq!(bytes, b"{\"w\":true}");
+    // BYTEA doesn't validate the JSON, so what JSONB rejects is kept as is
+    let note = Note {
+        text: "a\0b".to_string(),
+    };
+    let loaded =
+        diesel::select(sql::<Binary>("").bind::<Binary, _>(&note)).get_result::<Note>(&mut conn)?;
+    assert_eq!(loaded, note);
+    let rejected = diesel::sql_query(r#"SELECT '{"text":"a\u0000b"}'::jsonb"#).execute(&mut conn);
+    assert!(rejected.is_err());
+
+    assert_nullable_roundtrip!(&mut conn);
+    json_null(&mut conn)?;
+    assert_formats_roundtrip!(&mut conn);
+    assert_tagged_roundtrip!(&mut conn);
+    assert_compressed_roundtrip!(&mut conn);
+
+    shapes(&mut conn)?;
+    generics(&mut conn)?;
+    function_local(&mut conn)?;
+    generated(&mut conn)?;
+    shared(&mut conn)?;
+    lazy(&mut conn)?;
+    foreign(&mut conn)?;
+    #[cfg(feature = "arbitrary-precision")]
+    precision(&mut conn)?;
+    wide(&mut conn)?;
+    one_way(&mut conn)?;
+    composite(&mut conn)?;
+    arrays(&mut conn)?;
+    sql_type(&mut conn)?;
+    diesel_json_compat(&mut conn)?;
+    sqlx_compat(&mut conn)?;
+    inherent_methods(&mut conn)?;
+    reuse_buffer(&mut conn)?;
+    failing_serialize(&mut conn)?;
+    partial_update(&mut conn)?;
+    malformed_payloads(&mut conn)
+}
+
+fn shapes(conn: &mut PgConnection) -> QueryResult<()> {
+    assert_select_roundtrip!(
+        conn,
+        Named,
+        Named {
+            a: 1,
+            b: "b".to_string()
+        }
+    );
+    assert_select_roundtrip!(conn, Tuple, Tuple(1, "b".to_string()));
+    assert_select_roundtrip!(conn, Newtype, Newtype("id".to_string()));
+    assert_select_roundtrip!(conn, Unit, Unit);
+    assert_select_roundtrip!(conn, EmptyNamed, EmptyNamed {});
+    assert_select_roundtrip!(conn, EmptyTuple, EmptyTuple());
+    assert_eq!(bound_bytes(conn, &Unit), b"\x01null");
+    assert_eq!(bound_bytes(conn, &EmptyNamed {}), b"\x01{}");
+    assert_eq!(bound_bytes(conn, &EmptyTuple()), b"\x01[]");
+    // each reads only its own shape, `strict` rejects the others before
+    // parsing
+    assert!(read_raw::<Unit>(conn, b"\x01{}").is_err());
+    assert!(read_raw::<EmptyTuple>(conn, b"\x01null").is_err());
+    let error = read_raw::<EmptyNamed>(conn, b"\x01null").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::NotAnObject {
+                type_name: "EmptyNamed"
+            }
+        ),
+        "{error}"
+    );
+    assert_select_roundtrip!(conn, Gated, Gated::sample());
+    assert_select_roundtrip!(conn, Transparent, Transparent(42));
+    assert_select_roundtrip!(conn, Transparent, Transparent(0));
+    assert_select_roundtrip!(
+        conn,
+        TransparentNamed,
+        TransparentNamed {
+            name: "str".to_string()
+        }
+    );
+    let kinds = diesel::select((
+        sql::<Text>("jsonb_typeof(")
+            .bind::<Jsonb, _>(Transparent(42))
+            .sql(")"),
+        sql::<Text>("jsonb_typeof(")
+            .bind::<Jsonb, _>(TransparentNamed {
+                name: "str".to_string(),
+            })
+            .sql(")"),
+    ))
+    .get_result::<(String, String)>(conn)?;
+    assert_eq!(kinds, ("number".to_string(), "string".to_string()));
+    // scalars Postgres wrote itself read the same
+    let loaded = diesel::select(sql::<Jsonb>("'42'::jsonb")).get_result::<Transparent>(conn)?;
+    assert_eq!(loaded, Transparent(42));
+    let loaded =
+        diesel::select(sql::<Jsonb>(r#"'"str"'::jsonb"#)).get_result::<TransparentNamed>(conn)?;
+    assert_eq!(loaded.name, "str");
+    assert_select_roundtrip!(conn, Event, Event::Created { id: 1 });
+    assert_select_roundtrip!(conn, Event, Event::Renamed("b".to_string()));
+    assert_select_roundtrip!(conn, Event, Event::Deleted);
+    assert_select_roundtrip!(conn, InternallyTagged, InternallyTagged::Created { id: 1 });
+    assert_select_roundtrip!(conn, InternallyTagged, InternallyTagged::Deleted);
+    assert_select_roundtrip!(conn, AdjacentlyTagged, AdjacentlyTagged::Created { id: 1 });
+    assert_select_roundtrip!(
+        conn,
+        AdjacentlyTagged,
+        AdjacentlyTagged::Renamed("b".to_string())
+    );
+    assert_select_roundtrip!(conn, AdjacentlyTagged, AdjacentlyTagged::Deleted);
+    let value = Flattened {
+        id: 1,
+        counters: Counters {
+            count: 2,
+            active: true,
+            ratio: 0.5,
+        },
+    };
+    assert_select_roundtrip!(conn, Flattened, value);
+    let loaded = read_raw::<Flattened>(
+        conn,
+        b"\x01{\"id\":1,\"count\":-2,\"active\":false,\"ratio\":1.5}",
+    )
+    .unwrap();
+    assert_eq!(loaded.counters.count, -2);
+    let error = read_raw::<Flattened>(
+        conn,
+        b"\x01{\"id\":1,\"count\":\"2\",\"active\":true,\"ratio\":0.5}",
+    )
+    .unwrap_err();
+    if !cfg!(any(feature = "simd-json", feature = "sonic-rs")) {
+        assert_eq!(
+            error.to_string(),
+            "Invalid Json for Flattened: invalid type: string \"2\", expected i32 at line 1 column 46"
+        );
+    }
+
+    let compact = [
+        Compact::Deleted,
+        Compact::Renamed("b".to_string()),
+        Compact::Moved(1, -2),
+        Compact::Created {
+            id: 1,
+            name: "a".to_string(),
+        },
+        Compact::Tagged {
+            tag: "t".to_string(),
+        },
+    ];
+    for value in compact {
+        let bytes = diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+            .get_result::<Vec<u8>>(conn)?;
+        let json = serde_json::to_vec(&value).unwrap();
+        assert!(bytes.len() < json.len(), "{value:?}: {bytes:?}");
+        assert_eq!(read_raw_as::<Binary, Compact>(conn, &bytes).unwrap(), value);
+    }
+    let value = Compact::Created {
+        id: 1,
+        name: "a".to_string(),
+    };
+    let bytes =
+        diesel::select(sql::<Binary>("").bind::<Binary, _>(&value)).get_result::<Vec<u8>>(conn)?;
+    assert_eq!(bytes, b"\x01\x03[1,\"a\"]");
+    let error = read_raw_as::<Binary, Compact>(conn, b"\x01\x05").unwrap_err();
+    assert_eq!(error.to_string(), "Unknown discriminant 5 for Compact");
+    let error = read_raw_as::<Binary, Compact>(conn, b"\x01\x00null").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::UnexpectedPayload {
+                type_name: "Compact",
+                discriminant: 0
+            }
+        ),
+        "{error}"
+    );
+    let error = read_raw_as::<Binary, Compact>(conn, b"\x01\x01[]").unwrap_err();
+    assert!(
+        error.to_string().starts_with("Invalid Json for Compact: "),
+        "{error}"
+    );
+    for value in [Slot::Empty, Slot::Full(vec![1, 2])] {
+        let bytes = diesel::select(sql::<Binary>("").bind::<Binary, _>(&value))
+            .get_result::<Vec<u8>>(conn)?;
+        assert_eq!(
+            read_raw_as::<Binary, Slot<Vec<u8>>>(conn, &bytes).unwrap(),
+            value
+        );
+    }
+    assert_select_roundtrip!(
+        conn,
+        Document,
+        Document {
+            title: "draft".to_string(),
+            author: Person {
+                name: "a".to_string()
+            },
+            reviewer: None,
+            status: Status::Draft,
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Document,
+        Document {
+            title: "published".to_string(),
+            author: Person {
+                name: "a".to_string()
+            },
+            reviewer: Some(Person {
+                name: "b".to_string()
+            }),
+            status: Status::Published {
+                at: "2024-01-01".to_string()
+            },
+        }
+    );
+
+    assert_select_roundtrip!(conn, Bars, Bars(vec![Bar { x: 1 }, Bar { x: 2 }]));
+    assert_select_roundtrip!(conn, Bars, Bars(Vec::new()));
+    assert_select_roundtrip!(
+        conn,
+        Attributes,
+        Attributes(
+            [
+                ("color".to_string(), serde_json::json!("red")),
+                ("size".to_string(), serde_json::json!(3)),
+                ("tags".to_string(), serde_json::json!(["a", "b"])),
+            ]
+            .into()
+        )
+    );
+    assert_select_roundtrip!(conn, Counts, Counts([(1, 10), (2, 20)].into()));
+
+    let value = ApiSettings {
+        display_name: "a".to_string(),
+        max_items: 1,
+    };
+    assert_select_roundtrip!(conn, ApiSettings, value);
+    let loaded =
+        read_raw::<ApiSettings>(conn, b"\x01{\"displayName\":\"a\",\"maxItems\":1}").unwrap();
+    assert_eq!(loaded.max_items, 1);
+    let error = read_raw::<ApiSettings>(
+        conn,
+        b"\x01{\"displayName\":\"a\",\"maxItems\":1,\"extra\":true}",
+    )
+    .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "ApiSettings",
+                ..
+            }
+        ),
+        "{error}"
+    );
+    if !cfg!(any(feature = "simd-json", feature = "sonic-rs")) {
+        assert_eq!(
+            error.to_string(),
+            "Invalid Json for ApiSettings: unknown field `extra`, expected `displayName` or \
+             `maxItems` at line 1 column 39"
+        );
+    }
+
+    let value = Sparse {
+        name: "a".to_string(),
+        nickname: None,
+    };
+    assert_select_roundtrip!(conn, Sparse, value);
+    let loaded = read_raw::<Sparse>(conn, b"\x01{\"name\":\"a\"}").unwrap();
+    assert_eq!(loaded.nickname, None);
+    assert_select_roundtrip!(
+        conn,
+        Sparse,
+        Sparse {
+            name: "a".to_string(),
+            nickname: Some("b".to_string()),
+        }
+    );
+
+    Ok(())
+}
+
+fn generics(conn: &mut PgConnection) -> QueryResult<()> {
+    assert_select_roundtrip!(
+        conn,
+        Cache,
+        Cache {
+            data: serde_json::json!({"hits": [1, 2]})
+        }
+    );
+    assert_select_roundtrip!(conn, Cache<u8>, Cache { data: 3 });
+    assert_select_roundtrip!(conn, FixedVec<4>, FixedVec { items: vec![1, 2] });
+    assert_select_roundtrip!(
+        conn,
+        Holder<V1>,
+        Holder {
+            val: Entry { x: 1 }
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Marked<NonSerializableTag>,
+        Marked {
+            value: 3,
+            _marker: std::marker::PhantomData
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Tagged<V1>,
+        Tagged {
+            id: 2,
+            kind: std::marker::PhantomData
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Mixed<'static, i32, 3>,
+        Mixed {
+            name: "mixed".into(),
+            values: vec![1, 2, 3]
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Measured<Meters, f64>,
+        Measured {
+            unit: Meters,
+            value: 1.5
+        }
+    );
+    assert_select_roundtrip!(conn, Shown<u16>, Shown { value: 443 });
+    let shown = Shown { value: 1.5 };
+    let text = diesel::select(sql::<Text>("(").bind::<Jsonb, _>(&shown).sql(")::text"))
+        .get_result::<String>(conn)?;
+    assert_eq!(text, r#"{"value": "1.5"}"#);
+    assert_select_roundtrip!(
+        conn,
+        Reading<u32>,
+        Reading {
+            value: 21,
+            unit: "°C".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Reading<String>,
+        Reading {
+            value: "warm".to_string(),
+            unit: "°C".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Body,
+        Body {
+            text: "alone".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Wrapper<Body>,
+        Wrapper {
+            meta: Meta { revision: 2 },
+            body: Body {
+                text: "wrapped".to_string()
+            },
+        }
+    );
+
+    let (first, second) = ("first".to_string(), "second".to_string());
+    let value = Ref {
+        first: &first,
+        second: &second,
+    };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+        .get_result::<Owned<'static, 'static>>(conn)?;
+    assert_eq!(
+        loaded,
+        Owned {
+            first: first.into(),
+            second: second.into(),
+        }
+    );
+
+    let text = "borrowed".to_string();
+    let value = Label(std::borrow::Cow::Borrowed(&text));
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+        .get_result::<Label<'static>>(conn)?;
+    assert_eq!(loaded, value);
+    assert!(matches!(loaded.0, std::borrow::Cow::Owned(_)));
+
+    Ok(())
+}
+
+/// `details` is stored as it is, Postgres normalizes it for `Jsonb` like
+/// the rest of the document and keeps the text for `Json`.
+fn lazy(conn: &mut PgConnection) -> QueryResult<()> {
+    let details = r#"{"pages":3,  "authors":["b","a"]}"#;
+    let report = Report {
+        title: "report".to_string(),
+        details: RawValue::from_string(details.to_string()).unwrap(),
+    };
+    let loaded =
+        diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&report)).get_result::<Report>(conn)?;
+    assert_eq!(loaded.title, "report");
+    assert_eq!(
+        loaded.details.get(),
+        r#"{"pages": 3, "authors": ["b", "a"]}"#
+    );
+    let loaded =
+        diesel::select(sql::<Json>("").bind::<Json, _>(&report)).get_result::<Report>(conn)?;
+    assert_eq!(loaded.details.get(), details);
+    let parsed: Details = serde_json::from_str(loaded.details.get()).unwrap();
+    assert_eq!(
+        parsed,
+        Details {
+            pages: 3,
+            authors: vec!["b".to_string(), "a".to_string()],
+        }
+    );
+    let archive = Archive {
+        reports: vec![report],
+    };
+    let loaded =
+        diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&archive)).get_result::<Archive>(conn)?;
+    assert_eq!(
+        loaded.reports[0].details.get(),
+        r#"{"pages": 3, "authors": ["b", "a"]}"#
+    );
+    Ok(())
+}
+
+/// The local wrappers of an enum (de)serialized with `#[serde(remote)]`.
+fn foreign(conn: &mut PgConnection) -> QueryResult<()> {
+    for priority in [Priority::Low, Priority::High, Priority::Custom(3)] {
+        assert_select_roundtrip!(conn, LocalPriority, LocalPriority(priority));
+        assert_select_json_roundtrip!(conn, LocalPriority, LocalPriority(priority));
+    }
+    let text = diesel::select(sql::<Text>("").bind::<Json, _>(LocalPriority(Priority::Custom(3))))
+        .get_result::<String>(conn)?;
+    assert_eq!(text, r#"{"Custom":3}"#);
+    assert_select_roundtrip!(
+        conn,
+        Ticket,
+        Ticket {
+            title: "remote".to_string(),
+            priority: Priority::High,
+        }
+    );
+
+    Ok(())
+}
+
+fn shared(conn: &mut PgConnection) -> QueryResult<()> {
+    let shared = SharedFoo {
+        id: "shared".to_string(),
+        bar: Arc::new(Bar { x: 7 }),
+    };
+    diesel::insert_into(foo::table)
+        .values(NewFoo {
+            id: &shared.id,
+            bar: &shared.bar,
+        })
+        .execute(conn)?;
+    let loaded = foo::table
+        .find(&shared.id)
+        .select(SharedFoo::as_select())
+        .first(conn)?;
+    assert_eq!(loaded, shared);
+    let loaded = foo::table
+        .find(&shared.id)
+        .select(BoxedFoo::as_select())
+        .first(conn)?;
+    assert_eq!(
+        loaded,
+        BoxedFoo {
+            id: "shared".to_string(),
+            bar: Box::new(Bar { x: 7 }),
+        }
+    );
+    Ok(())
+}
+
+#[cfg(feature = "arbitrary-precision")]
+fn precision(conn: &mut PgConnection) -> QueryResult<()> {
+    use crate::precision::{Amount, Priced};
+
+    let value = Amount::parse("123456789012345678901234567890.000000000000000000001");
+    let loaded =
+        diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value)).get_result::<Amount>(conn)?;
+    assert_eq!(
+        loaded.value.to_string(),
+        "123456789012345678901234567890.000000000000000000001"
+    );
+
+    let value = Priced {
+        currency: "EUR".to_string(),
+        amount: Amount::parse("0.10000000000000000000001"),
+    };
+    let loaded =
+        diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value)).get_result::<Priced>(conn)?;
+    assert_eq!(loaded, value);
+
+    Ok(())
+}
+
+/// Types defined in a function body have no module path the generated code
+/// could refer to.
+fn wide(conn: &mut PgConnection) -> QueryResult<()> {
+    let value = Wide {
+        unsigned: u128::MAX,
+        signed: i128::MIN,
+    };
+    let text = diesel::select(sql::<Text>("(").bind::<Jsonb, _>(&value).sql(")::text"))
+        .get_result::<String>(conn)?;
+    assert_eq!(
+        text,
+        format!(r#"{{"signed": {}, "unsigned": {}}}"#, i128::MIN, u128::MAX)
+    );
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value)).get_result::<Wide>(conn);
+    if cfg!(feature = "simd-json") {
+        // simd-json parses numbers up to 64 bits, larger ones are an error
+        let error = loaded.unwrap_err();
+        assert!(
+            matches!(&error, diesel::result::Error::DeserializationError(_))
+                && format!("{error:?}").contains("InvalidNumber"),
+            "{error:?}"
+        );
+    } else {
+        assert_eq!(loaded?, value);
+    }
+
+    let value = WideAsString {
+        unsigned: u128::MAX,
+        signed: i128::MIN,
+    };
+    let text = diesel::select(sql::<Text>("(").bind::<Jsonb, _>(&value).sql(")::text"))
+        .get_result::<String>(conn)?;
+    assert_eq!(
+        text,
+        format!(
+            r#"{{"signed": "{}", "unsigned": "{}"}}"#,
+            i128::MIN,
+            u128::MAX
+        )
+    );
+    assert_select_roundtrip!(conn, WideAsString, value);
+
+    let value = WideSorted {
+        unsigned: u128::MAX,
+    };
+    if cfg!(feature = "arbitrary-precision") {
+        assert_select_roundtrip!(conn, WideSorted, value);
+    } else {
+        let error = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+            .execute(conn)
+            .unwrap_err();
+        assert!(
+            matches!(&error, diesel::result::Error::SerializationError(e) if e.to_string() == "number out of range"),
+            "{error:?}"
+        );
+    }
+
+    Ok(())
+}
+
+fn function_local(conn: &mut PgConnection) -> QueryResult<()> {
+    #[derive(
+        Debug,
+        PartialEq,
+        serde::Serialize,
+        serde::Deserialize,
+        diesel::AsExpression,
+        diesel::FromSqlRow,
+        diesel_json_derive::DieselJsonb,
+    )]
+    #[diesel(sql_type = Jsonb)]
+    struct Local {
+        x: i32,
+    }
+
+    assert_select_roundtrip!(conn, Local, Local { x: 1 });
+
+    Ok(())
+}
+
+/// The types `build.rs` generates, wherever they are included.
+fn generated(conn: &mut PgConnection) -> QueryResult<()> {
+    use crate::generated;
+
+    // `include!` in a block is parsed as an expression, items need a module
+    mod in_function {
+        include!(concat!(env!("OUT_DIR"), "/generated_types.rs"));
+    }
+
+    assert_select_roundtrip!(
+        conn,
+        generated::GeneratedRecord,
+        generated::GeneratedRecord {
+            id: 1,
+            name: "module".to_string(),
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        generated::nested::GeneratedRecord,
+        generated::nested::GeneratedRecord {
+            id: 2,
+            name: "nested".to_string(),
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        in_function::GeneratedRecord,
+        in_function::GeneratedRecord {
+            id: 3,
+            name: "function".to_string(),
+        }
+    );
+    assert_select_json_roundtrip!(
+        conn,
+        generated::GeneratedKind,
+        generated::GeneratedKind::Tagged("json".to_string())
+    );
+    assert_select_roundtrip!(
+        conn,
+        generated::nested::GeneratedKind,
+        generated::nested::GeneratedKind::Plain
+    );
+    let bytes = in_function::GeneratedKind::Tagged("bytes".to_string())
+        .to_jsonb_bytes()
+        .unwrap();
+    assert_eq!(bytes, b"\x01{\"Tagged\":\"bytes\"}");
+
+    Ok(())
+}
+
+/// `None` in a `NOT NULL` column, as the JSON `null`.
+fn json_null(conn: &mut PgConnection) -> QueryResult<()> {
+    let values = vec![
+        JsonNullFoo {
+            id: "some".to_string(),
+            bar: MaybeBar(Some(Bar { x: 1 })),
+        },
+        JsonNullFoo {
+            id: "none".to_string(),
+            bar: MaybeBar(None),
+        },
+    ];
+    diesel::insert_into(json_null_foo::table)
+        .values(&values)
+        .execute(conn)?;
+    let loaded = json_null_foo::table
+        .order(json_null_foo::id.desc())
+        .select(JsonNullFoo::as_select())
+        .load(conn)?;
+    assert_eq!(loaded, values);
+    let nulls = json_null_foo::table
+        .filter(sql::<diesel::sql_types::Bool>("jsonb_typeof(bar) = 'null'"))
+        .select(json_null_foo::id)
+        .load::<String>(conn)?;
+    assert_eq!(nulls, ["none"]);
+    Ok(())
+}
+
+/// JSONB fields of records and composite types. Postgres sends them like
+/// JSONB columns, version byte included.
+fn composite(conn: &mut PgConnection) -> QueryResult<()> {
+    let loaded = diesel::select(sql::<Record<(Integer, Jsonb)>>(
+        r#"ROW(1, '{"x":2}'::jsonb)"#,
+    ))
+    .get_result::<(i32, Bar)>(conn)?;
+    assert_eq!(loaded, (1, Bar { x: 2 }));
+
+    diesel::sql_query("CREATE TYPE pg_temp.tagged_bar AS (tag TEXT, bar JSONB)").execute(conn)?;
+    let loaded = diesel::select(sql::<Record<(Text, Jsonb)>>(
+        r#"ROW('a', '{"x":3}')::pg_temp.tagged_bar"#,
+    ))
+    .get_result::<(String, Bar)>(conn)?;
+    assert_eq!(loaded, ("a".to_string(), Bar { x: 3 }));
+
+    // a `json` field has no version byte, its first byte is not taken for one
+    let error = diesel::select(sql::<Record<(Integer, Jsonb)>>(
+        r#"ROW(1, '{"x":2}'::json)"#,
+    ))
+    .get_result::<(i32, Bar)>(conn)
+    .unwrap_err();
+    let diesel::result::Error::DeserializationError(error) = error else {
+        panic!("expected a deserialization error, got {error:?}");
+    };
+    let error = error
+        .downcast::<diesel::result::DeserializeFieldError>()
+        .expect("a field error")
+        .error
+        .downcast::<DieselJsonError>()
+        .expect("a DieselJsonError");
+    assert!(
+        matches!(*error, DieselJsonError::UnsupportedVersion(b'{')),
+        "{error}"
+    );
+
+    Ok(())
+}
+
+fn arrays(conn: &mut PgConnection) -> QueryResult<()> {
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE bar_lists \
+         (id TEXT PRIMARY KEY, bars JSONB[] NOT NULL, maybe_bars JSONB[] NOT NULL)",
+    )
+    .execute(conn)?;
+    let value = BarList {
+        id: "bound".to_string(),
+        bars: vec![Bar { x: 1 }, Bar { x: 2 }],
+        maybe_bars: vec![None, Some(Bar { x: 3 })],
+    };
+    diesel::insert_into(bar_lists::table)
+        .values(&value)
+        .execute(conn)?;
+    let loaded = bar_lists::table
+        .find(&value.id)
+        .select(BarList::as_select())
+        .get_result(conn)?;
+    assert_eq!(loaded, value);
+
+    diesel::sql_query(
+        r#"INSERT INTO bar_lists VALUES ('literal', '{"{\"x\": 4}"}', ARRAY[NULL, '{"x": 5}'::jsonb])"#,
+    )
+    .execute(conn)?;
+    let loaded = bar_lists::table
+        .find("literal")
+        .select(bar_lists::bars)
+        .get_result::<Vec<Bar>>(conn)?;
+    assert_eq!(loaded, vec![Bar { x: 4 }]);
+    let loaded = bar_lists::table
+        .find("literal")
+        .select(bar_lists::maybe_bars)
+        .get_result::<Vec<Option<Bar>>>(conn)?;
+    assert_eq!(loaded, vec![None, Some(Bar { x: 5 })]);
+
+    // the failing element's error is that of `Bar`
+    let error = diesel::select(sql::<Array<Jsonb>>(
+        r#"ARRAY['{"x": 6}'::jsonb, '{"x": "seven"}']"#,
+    ))
+    .get_result::<Vec<Bar>>(conn)
+    .unwrap_err();
+    let diesel::result::Error::DeserializationError(error) = error else {
+        panic!("expected a deserialization error, got {error:?}");
+    };
+    let error = error
+        .downcast::<diesel::result::DeserializeFieldError>()
+        .expect("a field error")
+        .error
+        .downcast::<DieselJsonError>()
+        .expect("a DieselJsonError");
+    assert!(
+        matches!(
+            *error,
+            DieselJsonError::Deserialize {
+                type_name: "Bar",
+                ..
+            }
+        ),
+        "{error}"
+    );
+
+    Ok(())
+}
+
+fn sql_type(conn: &mut PgConnection) -> QueryResult<()> {
+    assert_select_roundtrip!(
+        conn,
+        Setting,
+        Setting {
+            key: "value".to_string()
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Widget,
+        Widget {
+            layout: "grid".to_string()
+        }
+    );
+    // the `SqlType` derives look up `jsonb` like diesel's `Jsonb` has it
+    let jsonb = <Pg as HasSqlType<Jsonb>>::metadata(conn).oid().unwrap();
+    assert_eq!(
+        <Pg as HasSqlType<Setting>>::metadata(conn).oid().unwrap(),
+        jsonb
+    );
+    assert_eq!(
+        <Pg as HasSqlType<Layout>>::metadata(conn).oid().unwrap(),
+        jsonb
+    );
+    Ok(())
+}
+
+/// Values written by the `diesel_json` crate's `Json<T>` are read by the
+/// derive and the other way around, the bytes are the same.
+fn diesel_json_compat(conn: &mut PgConnection) -> QueryResult<()> {
+    let bar = Bar { x: 7 };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(diesel_json::Json(Bar { x: 7 })))
+        .get_result::<Bar>(conn)?;
+    assert_eq!(loaded, bar);
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&bar))
+        .get_result::<diesel_json::Json<Bar>>(conn)?;
+    assert_eq!(loaded.0, bar);
+
+    let ours = bound_bytes(conn, &bar);
+    let theirs = bound_bytes(conn, &diesel_json::Json(Bar { x: 7 }));
+    assert_eq!(ours, theirs);
+    assert_eq!(ours, b"\x01{\"x\":7}");
+    Ok(())
+}
+
+/// Values written by sqlx are read by the derive and the other way around:
+/// both bind the same bytes, and what Postgres sends back for a `JSONB`
+/// column in binary format, which is what sqlx reads, is the version byte
+/// and Postgres' own JSON text.
+fn sqlx_compat(conn: &mut PgConnection) -> QueryResult<()> {
+    let value = Migrated {
+        name: "Zoë \"quoted\" \\ tab\t 日本 🦀".to_string(),
+        ratio: 0.1,
+        tags: vec![String::new(), "\u{1}".to_string()],
+        parent: Some(Box::new(Migrated {
+            name: "root".to_string(),
+            ratio: -2.5,
+            tags: Vec::new(),
+            parent: None,
+        })),
+    };
+    let ours = bound_bytes(conn, &value);
+    assert_eq!(ours, bound_bytes(conn, &SqlxJson(value.clone())));
+    assert_eq!(ours[0], 1);
+
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(SqlxJson(value.clone())))
+        .get_result::<Migrated>(conn)?;
+    assert_eq!(loaded, value);
+    let sent = diesel::select(
+        sql::<Binary>("jsonb_send(")
+            .bind::<Jsonb, _>(&value)
+            .sql(")"),
+    )
+    .get_result::<Vec<u8>>(conn)?;
+    assert_eq!(sqlx_decode::<Migrated>(&sent), Ok(value.clone()));
+    // the JSON is Postgres' and spelled differently, e.g. `"x": 7` rather than
+    // `"x":7`, both crates only ever parse it
+    assert_ne!(sent, ours);
+    assert_eq!(read_raw::<Migrated>(conn, &sent).unwrap(), value);
+    Ok(())
+}
+
+/// The bytes diesel sends for `value` as a `Jsonb` parameter.
+fn bound_bytes(conn: &mut PgConnection, value: &dyn ToSql<Jsonb, Pg>) -> Vec<u8> {
+    let mut collector = RawBytesBindCollector::<Pg>::new();
+    collector
+        .push_bound_value::<Jsonb, _>(value, conn)
+        .expect("binding succeeds");
+    collector.binds.pop().flatten().expect("a non-null bind")
+}
+
+fn inherent_methods(conn: &mut PgConnection) -> QueryResult<()> {
+    let fixture = Fixture {
+        name: "a".to_string(),
+        tags: vec!["x".to_string()],
+    };
+    let bytes = fixture.to_jsonb_bytes().unwrap();
+    assert_eq!(bytes, b"\x01{\"name\":\"a\",\"tags\":[\"x\"]}");
+    assert_eq!(bytes, bound_bytes(conn, &fixture));
+    assert_eq!(read_raw::<Fixture>(conn, &bytes).unwrap(), fixture);
+    // what Postgres sends for the value it stored
+    let sent = diesel::select(
+        sql::<Binary>("jsonb_send(")
+            .bind::<Jsonb, _>(&fixture)
+            .sql(")"),
+    )
+    .get_result::<Vec<u8>>(conn)?;
+    assert_eq!(Fixture::from_jsonb_bytes(&sent).unwrap(), fixture);
+    let error = Fixture::from_jsonb_bytes(b"\x02{}").unwrap_err();
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(*error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+
+    let sorted = SortedFixture { b: 2, a: 1 };
+    let bytes = sorted.to_jsonb_bytes().unwrap();
+    assert_eq!(bytes, b"\x01{\"a\":1,\"b\":2}");
+    assert_eq!(bytes, bound_bytes(conn, &sorted));
+    assert_eq!(read_raw::<SortedFixture>(conn, &bytes).unwrap(), sorted);
+    assert_eq!(SortedFixture::from_jsonb_bytes(&bytes).unwrap(), sorted);
+    assert_eq!(
+        SortedFixture::from_jsonb_bytes(b"\x01").unwrap(),
+        SortedFixture::default()
+    );
+    Ok(())
+}
+
+fn reuse_buffer(conn: &mut PgConnection) -> QueryResult<()> {
+    let batched = |id: u32| Batched {
+        id,
+        payload: "x".repeat(id as usize % 100),
+    };
+    // a long value first, the shorter ones after it must not see its bytes
+    for id in [99, 1, 0, 50] {
+        assert_select_roundtrip!(conn, Batched, batched(id));
+        let bytes = bound_bytes(conn, &batched(id));
+        let mut expected = vec![1];
+        expected.extend(serde_json::to_vec(&batched(id)).unwrap());
+        assert_eq!(bytes, expected);
+    }
+
+    std::thread::scope(|scope| {
+        for thread in 0..8 {
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    let value = batched(thread * 1000 + i);
+                    let bytes = value.to_jsonb_bytes().unwrap();
+                    assert_eq!(Batched::from_jsonb_bytes(&bytes).unwrap(), value);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// `jsonb_merge` sets the fields of the patch in the stored value, the
+/// others keep what they were.
+fn partial_update(conn: &mut PgConnection) -> QueryResult<()> {
+    let settings = ApiSettings {
+        display_name: "a".to_string(),
+        max_items: 1,
+    };
+    diesel::insert_into(foo::table)
+        .values((foo::id.eq("merged"), foo::bar.eq(&settings)))
+        .execute(conn)?;
+    let patch = ApiSettingsPatch {
+        display_name: None,
+        max_items: Some(5),
+    };
+    let update =
+        diesel::update(foo::table.find("merged")).set(foo::bar.eq(jsonb_merge(foo::bar, &patch)));
+    let query = diesel::debug_query::<Pg, _>(&update).to_string();
+    assert!(
+        query.starts_with(r#"UPDATE "foo" SET "bar" = (("foo"."bar" || $1))"#),
+        "{query}"
+    );
+    update.execute(conn)?;
+    let loaded = foo::table
+        .find("merged")
+        .select(foo::bar)
+        .first::<ApiSettings>(conn)?;
+    assert_eq!(
+        loaded,
+        ApiSettings {
+            display_name: "a".to_string(),
+            max_items: 5,
+        }
+    );
+    diesel::delete(foo::table.find("merged")).execute(conn)?;
+    Ok(())
+}
+
+/// A `serialize_only` value read back as a `deserialize_only` one.
+fn one_way(conn: &mut PgConnection) -> QueryResult<()> {
+    let entry = AuditEntry {
+        action: "login",
+        user_id: 7,
+    };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&entry))
+        .get_result::<AuditRecord>(conn)?;
+    assert_eq!(
+        loaded,
+        AuditRecord {
+            action: "login".to_string(),
+            user_id: 7,
+        }
+    );
+
+    // inserted into a table, borrowing from a buffer that doesn't outlive the
+    // insert
+    let buffer = String::from("logout user 7");
+    let entry = AuditEntry {
+        action: &buffer[..6],
+        user_id: 7,
+    };
+    diesel::insert_into(foo::table)
+        .values((foo::id.eq("audit"), foo::bar.eq(&entry)))
+        .execute(conn)?;
+    drop(buffer);
+    let loaded = foo::table
+        .find("audit")
+        .select(foo::bar)
+        .first::<AuditRecord>(conn)?;
+    assert_eq!(
+        loaded,
+        AuditRecord {
+            action: "logout".to_string(),
+            user_id: 7,
+        }
+    );
+    diesel::delete(foo::table.find("audit")).execute(conn)?;
+
+    let message = AuditMessage {
+        text: "hello".to_string(),
+    };
+    let text = diesel::select(
+        sql::<Text>("(")
+            .bind::<Jsonb, _>(&message)
+            .sql(") ->> 'text'"),
+    )
+    .get_result::<String>(conn)?;
+    assert_eq!(text, message.text);
+
+    Ok(())
+}
+
+/// A failing `Serialize` impl fails the query, whether or not the start of the
+/// value was written before.
+fn failing_serialize(conn: &mut PgConnection) -> QueryResult<()> {
+    let error = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(FailsMidway { x: 1 }))
+        .execute(conn)
+        .unwrap_err();
+    assert!(
+        matches!(&error, diesel::result::Error::SerializationError(e) if e.to_string() == "failed midway"),
+        "{error:?}"
+    );
+    let error = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(AtomicFailsMidway { x: 1 }))
+        .execute(conn)
+        .unwrap_err();
+    assert!(
+        matches!(&error, diesel::result::Error::SerializationError(e) if e.to_string() == "failed midway"),
+        "{error:?}"
+    );
+
+    assert_select_roundtrip!(conn, Quota, Quota { used: 1, limit: 2 });
+    let error = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(Quota { used: 3, limit: 2 }))
+        .execute(conn)
+        .unwrap_err();
+    let diesel::result::Error::SerializationError(error) = error else {
+        panic!("{error:?}");
+    };
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(*error, DieselJsonError::Rejected { .. }),
+        "{error}"
+    );
+    assert_eq!(error.to_string(), "Refusing to write Quota: 3 used of 2");
+
+    // `{"items":[0,...]}` of 10 zeros is 31 bytes, 32 with the version byte
+    assert_select_roundtrip!(conn, Capped, Capped { items: vec![0; 10] });
+    assert_select_json_roundtrip!(conn, Capped, Capped { items: vec![0; 10] });
+    let error = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(Capped { items: vec![0; 11] }))
+        .execute(conn)
+        .unwrap_err();
+    let diesel::result::Error::SerializationError(error) = error else {
+        panic!("{error:?}");
+    };
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(
+            *error,
+            DieselJsonError::TooLarge {
+                type_name: "Capped",
+                size: 34,
+                max_bytes: 32,
+            }
+        ),
+        "{error}"
+    );
+    assert_eq!(
+        error.to_string(),
+        "Refusing to write Capped of 34 bytes, more than max_bytes = 32"
+    );
+    let error = diesel::select(sql::<Json>("").bind::<Json, _>(Capped { items: vec![0; 11] }))
+        .execute(conn)
+        .unwrap_err();
+    let diesel::result::Error::SerializationError(error) = error else {
+        panic!("{error:?}");
+    };
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(*error, DieselJsonError::TooLarge { size: 33, .. }),
+        "{error}"
+    );
+
+    Ok(())
+}
+
+/// Raw bytes that are not valid JSONB are read as `Jsonb` by selecting them as
+/// `bytea`, Postgres would never hand those out for a real JSONB column.
+fn malformed_payloads(conn: &mut PgConnection) -> QueryResult<()> {
+    let error = read_raw_as::<Binary, Layered>(conn, b"\x09{}").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::UnknownFormat {
+                type_name: "Layered",
+                tag: Some(9),
+            }
+        ),
+        "{error}"
+    );
+    let error = read_raw_as::<Binary, Layered>(conn, b"").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Empty value for Layered, without a format tag"
+    );
+    // gzipped JSON is read with the `compression` feature, this is cut short
+    let error = read_raw_as::<Binary, Layered>(conn, b"\x02\x1f\x8b").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Layered",
+                format: "gzip",
+                ..
+            }
+        ),
+        "{error}"
+    );
+
+    let nested = |depth: usize| format!("\x01{}1{}", "[".repeat(depth), "]".repeat(depth));
+    let loaded = read_raw::<Shallow>(conn, nested(16).as_bytes()).unwrap();
+    assert_eq!(
+        loaded.0.to_string(),
+        format!("{}1{}", "[".repeat(16), "]".repeat(16))
+    );
+    let error = read_raw::<Shallow>(conn, nested(17).as_bytes()).unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::TooDeep { max_depth: 16, .. }),
+        "{error}"
+    );
+    assert_eq!(
+        error.to_string(),
+        "JSON for Shallow is nested deeper than 16 levels"
+    );
+    let error = read_raw::<Shallow>(conn, nested(100_000).as_bytes()).unwrap_err();
+    assert!(matches!(error, DieselJsonError::TooDeep { .. }), "{error}");
+    // brackets in strings don't count
+    let brackets = format!("\x01[\"{}\\\"{{\"]", "[".repeat(100));
+    let loaded = read_raw::<Shallow>(conn, brackets.as_bytes()).unwrap();
+    assert_eq!(loaded.0[0].as_str().unwrap().len(), 102);
+    // without a max_depth serde_json's and sonic-rs' own limits apply,
+    // simd-json has none and overflows the stack
+    if !cfg!(feature = "simd-json") {
+        let error = read_raw::<Dynamic>(conn, nested(100_000).as_bytes()).unwrap_err();
+        assert!(
+            matches!(error, DieselJsonError::Deserialize { .. }),
+            "{error}"
+        );
+    }
+
+    let error = read_raw::<Bar>(conn, b"").unwrap_err();
+    assert!(matches!(error, DieselJsonError::Empty), "{error}");
+    assert_eq!(error.to_string(), "Empty JSONB value");
+
+    let error = read_raw::<Bar>(conn, b"\x01").unwrap_err();
+    assert!(matches!(error, DieselJsonError::MissingPayload), "{error}");
+    assert_eq!(
+        error.to_string(),
+        "JSONB value contains version byte but no payload"
+    );
+    let error = read_raw::<VersionTwo>(conn, b"\x01").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(1)),
+        "{error}"
+    );
+
+    let error = read_raw::<Bar>(conn, b"\x01{").unwrap_err();
+    let DieselJsonError::Deserialize { type_name, .. } = &error else {
+        panic!("expected a deserialize error, got {error:?}");
+    };
+    assert_eq!(*type_name, "Bar");
+    let error = error.to_string();
+    if cfg!(any(feature = "simd-json", feature = "sonic-rs")) {
+        assert!(error.starts_with("Invalid Json for Bar: "), "{error}");
+    } else {
+        assert_eq!(
+            error,
+            "Invalid Json for Bar: EOF while parsing an object at line 1 column 1"
+        );
+    }
+
+    let error = read_raw::<Bar>(conn, b"\x02{\"x\":1}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+    assert_eq!(error.to_string(), "Unsupported JSONB encoding version 2");
+    let loaded = read_raw::<VersionTwo>(conn, b"\x02{\"x\":1}").unwrap();
+    assert_eq!(loaded, VersionTwo { x: 1 });
+    // the message names the byte that was found, not the expected one
+    let error = read_raw::<VersionTwo>(conn, b"\x01{\"x\":1}").unwrap_err();
+    assert_eq!(error.to_string(), "Unsupported JSONB encoding version 1");
+    let loaded = read_raw::<AnyVersion>(conn, b"\x02{\"x\":1}").unwrap();
+    assert_eq!(loaded, AnyVersion { x: 1 });
+    let error = read_raw::<AnyVersion>(conn, b"").unwrap_err();
+    assert!(matches!(error, DieselJsonError::Empty), "{error}");
+    let error = read_raw::<AnyVersion>(conn, b"\x02").unwrap_err();
+    assert!(matches!(error, DieselJsonError::MissingPayload), "{error}");
+    let loaded = read_raw::<SkipVersionCheck>(conn, b"\x02{\"x\":1}").unwrap();
+    assert_eq!(loaded, SkipVersionCheck { x: 1 });
+    for payload in [b"\x01{\"x\":1}", b"\x02{\"x\":1}"] {
+        let loaded = read_raw::<Migrating>(conn, payload).unwrap();
+        assert_eq!(loaded, Migrating { x: 1 });
+    }
+    let error = read_raw::<Migrating>(conn, b"\x03{\"x\":1}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(3)),
+        "{error}"
+    );
+    assert_select_roundtrip!(conn, Migrating, Migrating { x: 2 });
+
+    assert_eq!(
+        read_raw::<Degrading>(conn, b"\x02{\"x\":1}").unwrap(),
+        Degrading::default()
+    );
+    assert_eq!(
+        read_raw::<Degrading>(conn, b"\x01{\"x\":1}").unwrap(),
+        Degrading { x: 1 }
+    );
+    // only the version is recovered from, not what follows a known one
+    let error = read_raw::<Degrading>(conn, b"\x01{").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::Deserialize { .. }),
+        "{error}"
+    );
+    let error = read_raw::<Degrading>(conn, b"").unwrap_err();
+    assert!(matches!(error, DieselJsonError::Empty), "{error}");
+    assert_select_roundtrip!(conn, Degrading, Degrading { x: 3 });
+    assert_eq!(
+        read_raw::<DegradingMigrating>(conn, b"\x02{\"x\":1}").unwrap(),
+        DegradingMigrating { x: 1 }
+    );
+    assert_eq!(
+        read_raw::<DegradingMigrating>(conn, b"\x03{\"x\":1}").unwrap(),
+        DegradingMigrating::default()
+    );
+    let error = read_raw::<Failing>(conn, b"\x02{\"x\":1}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+    assert_eq!(
+        read_raw::<Failing>(conn, b"\x01{\"x\":1}").unwrap(),
+        Failing { x: 1 }
+    );
+
+    let error = read_raw::<Bar>(conn, b"\x01{\"x\":1} trailing").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::Deserialize { .. }),
+        "{error}"
+    );
+    if !cfg!(any(feature = "simd-json", feature = "sonic-rs")) {
+        assert_eq!(
+            error.to_string(),
+            "Invalid Json for Bar: trailing characters at line 1 column 9"
+        );
+    }
+    let loaded = read_raw::<Lenient>(conn, b"\x01{\"x\":1} trailing").unwrap();
+    assert_eq!(loaded, Lenient { x: 1 });
+    let loaded = read_raw::<Lenient>(conn, b"\x01{\"x\":1}{\"x\":2}").unwrap();
+    assert_eq!(loaded, Lenient { x: 1 });
+    let error = read_raw::<Lenient>(conn, b"\x01{\"x\":").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::Deserialize { .. }),
+        "{error}"
+    );
+
+    let loaded = read_raw::<Settings>(conn, b"").unwrap();
+    assert_eq!(loaded, Settings::default());
+    let loaded = read_raw::<Settings>(conn, b"\x01").unwrap();
+    assert_eq!(loaded, Settings::default());
+    // a single byte of another version is not empty, it is checked like one
+    let error = read_raw::<Settings>(conn, b"\x02").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+    let loaded = read_raw::<Settings>(conn, b"\x01{\"dark_mode\":true}").unwrap();
+    assert_eq!(loaded, Settings { dark_mode: true });
+
+    let loaded = read_raw::<Manual>(conn, &[&[JSONB_VERSION], &b"{\"x\":1}"[..]].concat()).unwrap();
+    assert_eq!(loaded, Manual { x: 1 });
+    let error = read_raw::<Manual>(conn, b"\x01{").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Manual",
+                ..
+            }
+        ),
+        "{error}"
+    );
+    let error = read_raw::<Manual>(conn, b"\x03{}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(3)),
+        "{error}"
+    );
+
+    // the hand-written impl on the helper reads like the derive
+    for payload in [
+        &b"\x01{\"x\":1}"[..],
+        b"\x01 {\"x\":1} ",
+        b"\x01{",
+        b"\x03{}",
+        b"\x01",
+        b"",
+    ] {
+        let manual = read_raw::<Manual>(conn, payload).map(|Manual { x }| x);
+        let derived = read_raw::<Bar>(conn, payload).map(|Bar { x }| x);
+        // the messages of the JSON parser differ with `simd-json` and `sonic-rs`
+        assert_eq!(
+            manual.map_err(|e| std::mem::discriminant(&e)),
+            derived.map_err(|e| std::mem::discriminant(&e)),
+        );
+    }
+
+    let loaded = read_raw::<ObjectOnly>(conn, b"\x01 {\"a\":1}").unwrap();
+    assert_eq!(loaded, ObjectOnly(serde_json::json!({"a": 1})));
+    for payload in [&b"\x01[1]"[..], b"\x011", b"\x01\"a\"", b"\x01null"] {
+        let error = read_raw::<ObjectOnly>(conn, payload).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                DieselJsonError::NotAnObject {
+                    type_name: "ObjectOnly"
+                }
+            ),
+            "{error}"
+        );
+    }
+    assert_eq!(
+        read_raw::<ObjectOnly>(conn, b"\x01[]")
+            .unwrap_err()
+            .to_string(),
+        "Expected a JSON object for ObjectOnly"
+    );
+
+    // `SizeHinted` is parsed from the slice, the other two with a reader.
+    let payload = b"\x01{\"lines\":[\"a\",\"\\u00e9\\n\",\"\"]}";
+    let lines = read_raw::<SizeHinted>(conn, payload).unwrap().lines;
+    assert_eq!(lines, ["a", "\u{e9}\n", ""]);
+    assert_eq!(read_raw::<Streaming>(conn, payload).unwrap().lines, lines);
+    assert_eq!(read_raw::<Reader>(conn, payload).unwrap().lines, lines);
+
+    let error = read_raw::<Streaming>(conn, b"\x01{").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid Json for Streaming: EOF while parsing an object at line 1 column 1"
+    );
+
+    for payload in [
+        &b"\x01{\"retries\":"[..],
+        b"\x01[]",
+        b"\x02{\"retries\":3}",
+        b"",
+    ] {
+        assert_eq!(
+            read_raw::<Tolerant>(conn, payload).unwrap(),
+            Tolerant::default()
+        );
+    }
+    let loaded = read_raw::<Tolerant>(conn, b"\x01{\"retries\":3}").unwrap();
+    assert_eq!(loaded, Tolerant { retries: 3 });
+
+    let error = read_raw_as::<Text, Qux>(conn, b"{\"z\":[1,\xff]}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "JSON text for Qux is not valid UTF-8 after 8 bytes"
+    );
+    let error = read_raw_as::<Json, Baz>(conn, b"{\"y\":\"caf\xe9\"}").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::NotUtf8 {
+                type_name: "Baz",
+                valid_up_to: 9
+            }
+        ),
+        "{error}"
+    );
+    let loaded = read_raw_as::<Json, Baz>(conn, "{\"y\":\"café\"}".as_bytes()).unwrap();
+    assert_eq!(loaded.y, "café");
+
+    let old = b"\x01{\"name\":\"a\",\"notifications\":{\"email\":true}}";
+    let loaded = read_raw::<Compatible>(conn, old).unwrap();
+    assert_eq!(
+        loaded,
+        Compatible {
+            name: "a".to_string(),
+            theme: String::new(),
+        }
+    );
+    let loaded = read_raw::<Evolved>(conn, old).unwrap();
+    assert_eq!(
+        loaded,
+        Evolved {
+            name: "a".to_string(),
+            theme: "light".to_string(),
+            notifications: Notifications {
+                email: true,
+                push: true,
+            },
+        }
+    );
+    let evolved = Evolved {
+        name: "b".to_string(),
+        theme: "dark".to_string(),
+        notifications: Notifications {
+            email: false,
+            push: false,
+        },
+    };
+    assert_select_roundtrip!(conn, Evolved, evolved);
+    let error = read_raw::<Evolved>(conn, b"\x01{\"name\":1}").unwrap_err();
+    // the type is named `number` with arbitrary-precision
+    let message = error.to_string();
+    assert!(
+        message.starts_with("Invalid Json for Evolved: invalid type: ")
+            && message.ends_with(", expected a string"),
+        "{message}"
+    );
+
+    for payload in [&b"\x01{}"[..], b"\x01 {\n} "] {
+        assert_eq!(
+            read_raw::<Legacy>(conn, payload).unwrap(),
+            Legacy::default()
+        );
+    }
+    let loaded = read_raw::<Legacy>(conn, b"\x01{\"name\": \"{ }\", \"retries\": 1}").unwrap();
+    assert_eq!(loaded.name, "{ }");
+    let error = read_raw::<Legacy>(conn, b"\x01{\"name\":\"\"}").unwrap_err();
+    assert!(
+        error.to_string().contains("missing field `retries`"),
+        "{error}"
+    );
+    let loaded: Legacy = diesel::select(sql::<Jsonb>("'{}'::jsonb")).get_result(conn)?;
+    assert_eq!(loaded, Legacy::default());
+
+    let newer = br#"{"name":"new","retryCount":2,"added":{"nested":[1]}}"#;
+    let error = serde_json::from_slice::<Forward>(newer).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown field `added`"),
+        "{error}"
+    );
+    let forward = Forward {
+        name: "new".to_string(),
+        retry_count: 2,
+    };
+    assert_eq!(
+        read_raw::<Forward>(conn, &[b"\x01", &newer[..]].concat()).unwrap(),
+        forward
+    );
+    let loaded =
+        read_raw::<Forward>(conn, b"\x01{\"retries\":2,\"name\":\"new\",\"x\":null}").unwrap();
+    assert_eq!(loaded, forward);
+    assert_select_roundtrip!(conn, Forward, forward);
+    let error = read_raw::<Forward>(conn, b"\x01{\"name\":\"new\",\"added\":1}").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Forward",
+                ..
+            }
+        ),
+        "{error}"
+    );
+    assert!(
+        error.to_string().contains("missing field `retryCount`"),
+        "{error}"
+    );
+
+    assert_eq!(
+        read_raw::<Upgraded>(conn, b"\x01{\"name\":\"old\"}").unwrap(),
+        Upgraded {
+            name: "old".to_string(),
+            retries: 3,
+            region: "eu".to_string(),
+        }
+    );
+    assert_select_roundtrip!(
+        conn,
+        Upgraded,
+        Upgraded {
+            name: "new".to_string(),
+            retries: 0,
+            region: "us".to_string(),
+        }
+    );
+
+    let located = Located {
+        theme: "dark".to_string(),
+    };
+    assert_select_roundtrip!(conn, Located, located);
+    let error = read_raw::<Located>(conn, b"\x01{\"theme\":1}").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DieselJsonError::Deserialize {
+                type_name: "Located in column users.settings",
+                ..
+            }
+        ),
+        "{error}"
+    );
+    assert!(
+        error
+            .to_string()
+            .starts_with("Invalid Json for Located in column users.settings: "),
+        "{error}"
+    );
+    // errors that name no type are left as they are
+    let error = read_raw::<Located>(conn, b"\x02{}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+
+    let imported = Imported {
+        name: "old".to_string(),
+    };
+    for payload in [
+        &b"\x01{\"name\":\"old\"}"[..],
+        b"{\"name\":\"old\"}",
+        b"\n {\"name\":\"old\"}",
+    ] {
+        assert_eq!(read_raw::<Imported>(conn, payload).unwrap(), imported);
+    }
+    assert_select_roundtrip!(conn, Imported, imported);
+    let error = read_raw::<Imported>(conn, b"\x02{\"name\":\"old\"}").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+    for (payload, count) in [
+        (&b"7"[..], 7),
+        (b"\x017", 7),
+        (b"42", 42),
+        (b"\x01", 0),
+        (b"", 0),
+    ] {
+        assert_eq!(
+            read_raw::<ImportedCount>(conn, payload).unwrap(),
+            ImportedCount(count)
+        );
+    }
+    let error = read_raw::<ImportedCount>(conn, b"\x02").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::UnsupportedVersion(2)),
+        "{error}"
+    );
+
+    let value = Guarded { w: true };
+    let bytes =
+        diesel::select(sql::<Binary>("").bind::<Binary, _>(&value)).get_result::<Vec<u8>>(conn)?;
+    let mut expected = vec![CHECKSUM_MARKER];
+    expected.extend_from_slice(&crc32(b"{\"w\":true}").to_be_bytes());
+    expected.extend_from_slice(b"{\"w\":true}");
+    assert_eq!(bytes, expected);
+    assert_eq!(read_raw_as::<Binary, Guarded>(conn, &bytes).unwrap(), value);
+    // written before the type had a checksum
+    let loaded = read_raw_as::<Binary, Guarded>(conn, b"{\"w\":true}").unwrap();
+    assert_eq!(loaded, value);
+    let mut corrupt = bytes.clone();
+    *corrupt.last_mut().unwrap() = b']';
+    let error = read_raw_as::<Binary, Guarded>(conn, &corrupt).unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::ChecksumMismatch { stored, .. } if stored == crc32(b"{\"w\":true}")),
+        "{error}"
+    );
+    let error = read_raw_as::<Binary, Guarded>(conn, &bytes[..3]).unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::TruncatedChecksum),
+        "{error}"
+    );
+
+    let error = read_raw_as::<Binary, Compressed>(conn, b"\x02\x1f\x8b\x08").unwrap_err();
+    assert!(
+        matches!(error, DieselJsonError::Deserialize { format: "gzip", .. }),
+        "{error}"
+    );
+
+    let ada = Profile {
+        first_name: "Ada".to_string(),
+        last_name: "Lovelace".to_string(),
+    };
+    for payload in [
+        &b"\x01{\"name\":\"Ada Lovelace\"}"[..],
+        b"\x01{\"schema_version\":1,\"data\":{\"name\":\"Ada Lovelace\"}}",
+        b"\x01{\"schema_version\":2,\"data\":{\"first_name\":\"Ada\",\"last_name\":\"Lovelace\"}}",
+    ] {
+        assert_eq!(read_raw::<Profile>(conn, payload).unwrap(), ada);
+    }
+    let error = read_raw::<Profile>(conn, b"\x01{\"schema_version\":3,\"data\":{}}").unwrap_err();
+    assert!(matches!(error, DieselJsonError::Migrate { .. }), "{error}");
+    assert_eq!(
+        error.to_string(),
+        "Cannot migrate Profile from schema version 3: newer than schema version 2"
+    );
+    let error = read_raw::<Profile>(conn, b"\x01{\"title\":\"Ada\"}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Cannot migrate Profile from schema version 0: no name"
+    );
+    let version = diesel::select(
+        sql::<Text>("(")
+            .bind::<Jsonb, _>(&ada)
+            .sql(") ->> 'schema_version'"),
+    )
+    .get_result::<String>(conn)?;
+    assert_eq!(version, "2");
+    assert_select_roundtrip!(conn, Profile, ada);
+
+    let loaded = read_raw::<Percent>(conn, b"\x01{\"value\":50}").unwrap();
+    assert_eq!(loaded, Percent { value: 50 });
+    let error = read_raw::<Percent>(conn, b"\x01{\"value\":101}").unwrap_err();
+    assert!(matches!(error, DieselJsonError::Invalid { .. }), "{error}");
+    assert_eq!(
+        error.to_string(),
+        "Invalid Percent: 101 is not a percentage"
+    );
+
+    Ok(())
+}
+
+/// Reads `bytes` as a `Jsonb` value, returning the deserialization error.
+fn read_raw<T>(conn: &mut PgConnection, bytes: &[u8]) -> Result<T, DieselJsonError>
+where
+    T: FromSqlRow<Jsonb, Pg> + 'static,
+{
+    read_raw_as::<Jsonb, T>(conn, bytes)
+}
+
+/// `read_raw` for any sql type.
+fn read_raw_as<ST, T>(conn: &mut PgConnection, bytes: &[u8]) -> Result<T, DieselJsonError>
+where
+    ST: diesel::sql_types::SingleValue + diesel::query_builder::QueryId + 'static,
+    Pg: diesel::sql_types::HasSqlType<ST>,
+    T: FromSqlRow<ST, Pg> + 'static,
+{
+    let query = diesel::select(sql::<ST>("").bind::<Binary, _>(bytes));
+    match query.get_result::<T>(conn) {
+        Ok(value) => Ok(value),
+        Err(diesel::result::Error::DeserializationError(e)) => {
+            let e = e
+                .downcast::<diesel::result::DeserializeFieldError>()
+                .expect("a field error");
+            match e.error.downcast::<DieselJsonError>() {
+                Ok(e) => Err(*e),
+                Err(e) => panic!("expected a DieselJsonError, got {e:?}"),
+            }
+        }
+        Err(e) => panic!("expected a deserialization error, got {e:?}"),
+    }
+}
@@ -0,0 +1,41 @@
+//! The same round-trip as `pg` but through diesel-async, which uses the
+//! generated `ToSql`/`FromSql` impls just like the sync connection does.
+
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::schema::foo;
+use crate::{Bar, Foo};
+
+pub async fn roundtrip(database_url: &str) -> QueryResult<()> {
+    let mut conn = AsyncPgConnection::establish(database_url)
+        .await
+        .expect("postgres connection");
+    diesel::sql_query("CREATE TEMPORARY TABLE foo (id TEXT PRIMARY KEY, bar JSONB NOT NULL)")
+        .execute(&mut conn)
+        .await?;
+
+    let value = Foo {
+        id: "pg_async".to_string(),
+        bar: Bar { x: 42 },
+    };
+    diesel::insert_into(foo::table)
+        .values(&value)
+        .execute(&mut conn)
+        .await?;
+    let loaded = foo::table
+        .find(&value.id)
+        .select(Foo::as_select())
+        .first(&mut conn)
+        .await?;
+    assert_eq!(loaded, value);
+
+    let loaded = foo::table
+        .filter(foo::bar.eq(&value.bar))
+        .select(foo::bar)
+        .load::<Bar>(&mut conn)
+        .await?;
+    assert_eq!(loaded, vec![value.bar]);
+
+    Ok(())
+}
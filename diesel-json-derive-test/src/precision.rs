@@ -0,0 +1,30 @@
+//! Numbers beyond `f64`/`u64` with the `arbitrary-precision` feature.
+
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Amount {
+    pub value: serde_json::Number,
+}
+
+impl Amount {
+    pub fn parse(value: &str) -> Self {
+        Self {
+            value: value.parse().expect("a JSON number"),
+        }
+    }
+}
+
+/// `{"currency":"EUR","value":0.1}`, a flattened number is read from a
+/// `serde_json::Value`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Priced {
+    pub currency: String,
+    #[serde(flatten)]
+    pub amount: Amount,
+}
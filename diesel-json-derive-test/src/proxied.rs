@@ -0,0 +1,98 @@
+//! A JSONB column next to columns that diesel converts through proxy types
+//! with `serialize_as` and `deserialize_as`, and a JSONB type that serde
+//! converts through a proxy with `into` and `try_from`.
+
+use diesel::prelude::*;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+diesel::table! {
+    proxied_foo (id) {
+        id -> Text,
+        tag -> Text,
+        payload -> Jsonb,
+    }
+}
+
+/// `serialize_as` needs the struct by value, not by reference, for the insert.
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = proxied_foo)]
+pub struct ProxiedFoo {
+    pub id: String,
+    #[diesel(serialize_as = String, deserialize_as = String)]
+    pub tag: Tag,
+    pub payload: Payload,
+}
+
+/// Stored as its lowercase name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tag {
+    Red,
+    Blue,
+}
+
+impl From<Tag> for String {
+    fn from(tag: Tag) -> Self {
+        match tag {
+            Tag::Red => "red".to_string(),
+            Tag::Blue => "blue".to_string(),
+        }
+    }
+}
+
+impl From<String> for Tag {
+    fn from(tag: String) -> Self {
+        match tag.as_str() {
+            "red" => Tag::Red,
+            _ => Tag::Blue,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+pub struct Payload {
+    pub tag: String,
+    pub weight: u32,
+}
+
+/// Stored as its [`TemperatureDto`]. `try_from` still gives serde a
+/// `Deserialize` impl for any lifetime, so the derive's `DeserializeOwned`
+/// bound holds, and `into` needs the `Clone`.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[serde(into = "TemperatureDto", try_from = "TemperatureDto")]
+pub struct Temperature {
+    pub kelvin: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TemperatureDto {
+    pub celsius: f64,
+}
+
+impl From<Temperature> for TemperatureDto {
+    fn from(temperature: Temperature) -> Self {
+        Self {
+            celsius: temperature.kelvin - 273.15,
+        }
+    }
+}
+
+impl TryFrom<TemperatureDto> for Temperature {
+    type Error = String;
+
+    fn try_from(dto: TemperatureDto) -> Result<Self, Self::Error> {
+        let kelvin = dto.celsius + 273.15;
+        if kelvin < 0.0 {
+            return Err(format!("{} °C is below absolute zero", dto.celsius));
+        }
+        Ok(Self { kelvin })
+    }
+}
@@ -0,0 +1,93 @@
+//! Types getting their impls from `impl_diesel_jsonb!` instead of the derive.
+
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, ToSql};
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::{diesel_jsonb_types, impl_diesel_jsonb};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub mod elsewhere {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Remote {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Json)]
+    pub struct RemoteJson {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Manual {
+        pub x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct First {
+        pub a: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Second {
+        pub b: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Third(pub Vec<bool>);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Jsonb)]
+    pub struct Pair<T> {
+        pub a: T,
+        pub b: T,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+    #[diesel(sql_type = Json)]
+    pub struct Labelled<T> {
+        pub value: T,
+        pub label: String,
+    }
+}
+
+impl_diesel_jsonb!(elsewhere::Remote);
+impl_diesel_jsonb!(elsewhere::RemoteJson, sql_type = Json, size_hint = 64);
+impl_diesel_jsonb!(impl<T: Serialize + DeserializeOwned> for elsewhere::Pair<T>);
+impl_diesel_jsonb!(
+    impl<T> for elsewhere::Labelled<T>
+    where
+        T: Serialize + DeserializeOwned,
+        T: Clone,
+    sql_type = Json,
+    sort_keys,
+);
+
+diesel_jsonb_types! {
+    elsewhere::First,
+    elsewhere::Second,
+    elsewhere::Third,
+}
+
+/// Hand-written impls using the helpers the derive is built on.
+impl ToSql<Jsonb, Pg> for elsewhere::Manual {
+    fn to_sql<'b>(&'b self, out: &mut serialize::Output<'b, '_, Pg>) -> serialize::Result {
+        diesel_json_derive::to_jsonb(self, out)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for elsewhere::Manual {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        diesel_json_derive::jsonb_from_pg_value(bytes)
+    }
+}
@@ -0,0 +1,236 @@
+//! The different shapes of types `DieselJsonb` can be derived for. The derive
+//! only names the type so all of them work the same, what ends up in the
+//! column is whatever serde makes of them.
+
+use diesel::sql_types::{Binary, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// `{"a":1,"b":"b"}`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Named {
+    pub a: i32,
+    pub b: String,
+}
+
+/// `[1,"b"]`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Tuple(pub i32, pub String);
+
+/// Newtypes are serialized transparently, `"id"`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Newtype(pub String);
+
+/// `#[serde(transparent)]` newtypes are the bare scalar as well, `42`, so
+/// the JSONB payload is nothing but the number after the version byte.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(transparent)]
+pub struct Transparent(pub u32);
+
+/// A transparent struct with a named field, `"name"`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(transparent)]
+pub struct TransparentNamed {
+    pub name: String,
+}
+
+/// Unit structs are serialized as `null`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Unit;
+
+/// Structs without fields differ by their brackets: this is `{}`, an object,
+/// so `strict` reads it like any other struct.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(strict)]
+pub struct EmptyNamed {}
+
+/// `[]`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct EmptyTuple();
+
+/// `"Deleted"` and `{"Created":{"id":1}}`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub enum Event {
+    Created { id: u32 },
+    Renamed(String),
+    Deleted,
+}
+
+/// `{"type":"Deleted"}` and `{"type":"Created","id":1}`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(tag = "type")]
+pub enum InternallyTagged {
+    Created { id: u32 },
+    Deleted,
+}
+
+/// `{"t":"Deleted"}` and `{"t":"Renamed","c":"b"}`
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(tag = "t", content = "c")]
+pub enum AdjacentlyTagged {
+    Created { id: u32 },
+    Renamed(String),
+    Deleted,
+}
+
+/// `enum_discriminant` stores the variant as a byte after the version byte,
+/// followed by the fields: `\x01\x00` for `Deleted`, `\x01\x02[1,-2]` for
+/// `Moved(1, -2)` and `\x01\x04"t"` for `Tagged { tag: "t" }`. serde's impls
+/// are only for comparing with the plain JSON.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(enum_discriminant)]
+pub enum Compact {
+    Deleted,
+    Renamed(String),
+    Moved(i32, i32),
+    Created { id: u32, name: String },
+    Tagged { tag: String },
+}
+
+/// Without serde impls of its own, the impls need them for `T`.
+#[derive(Debug, PartialEq, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(enum_discriminant)]
+pub enum Slot<T> {
+    Empty,
+    Full(T),
+}
+
+/// A type with one definition per build, here for debug and release builds.
+/// The impls go into anonymous `const _` blocks, so nothing generated clashes
+/// and `#[cfg]` only has to strip the inactive definition before expanding.
+#[cfg(debug_assertions)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Gated {
+    pub build: String,
+}
+
+#[cfg(debug_assertions)]
+impl Gated {
+    pub fn sample() -> Self {
+        Self {
+            build: "debug".to_string(),
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Gated {
+    pub build: String,
+    pub optimized: bool,
+}
+
+#[cfg(not(debug_assertions))]
+impl Gated {
+    pub fn sample() -> Self {
+        Self {
+            build: "release".to_string(),
+            optimized: true,
+        }
+    }
+}
+
+/// Nested values of types that are no `DieselJsonb` themselves.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Document {
+    pub title: String,
+    pub author: Person,
+    pub reviewer: Option<Person>,
+    pub status: Status,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Person {
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+    Draft,
+    Published { at: String },
+}
+
+/// `{"name":"a"}` when `nickname` is `None`. Skipping a field when writing
+/// needs `#[serde(default)]` for reading it back without the field.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Sparse {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+}
+
+/// `{"id":1,"count":2,"active":true,"ratio":0.5}`, the fields of `Counters`
+/// are written next to `id`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Flattened {
+    pub id: i64,
+    #[serde(flatten)]
+    pub counters: Counters,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Counters {
+    pub count: i32,
+    pub active: bool,
+    pub ratio: f64,
+}
+
+/// `{"displayName":"a","maxItems":1}`, container attributes are serde's
+/// business like everything else.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ApiSettings {
+    pub display_name: String,
+    pub max_items: u32,
+}
+
+/// The fields of `ApiSettings` to change with `jsonb_merge`, the ones that
+/// are `None` are left out of the JSON and so keep their stored value.
+#[derive(Debug, Serialize, AsExpression, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(serialize_only)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiSettingsPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+}
+
+/// `[{"x":1},{"x":2}]`. `Vec<Bar>` itself can't get the impls, neither `Vec`
+/// nor diesel's traits are local.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub(crate) struct Bars(pub(crate) Vec<crate::Bar>);
+
+/// `{"a":1}`. serde_json writes map keys as JSON strings, integer keys are
+/// quoted, keys that are neither strings nor integers fail to serialize.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Attributes(pub std::collections::BTreeMap<String, serde_json::Value>);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Counts(pub std::collections::HashMap<u32, u32>);
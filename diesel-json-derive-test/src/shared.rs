@@ -0,0 +1,35 @@
+//! Rows holding the derived `Bar` behind an `Arc` or a `Box`. The orphan rule
+//! forbids implementing diesel's traits for `Arc<Bar>`, so the fields are
+//! loaded as `Bar` and converted with `deserialize_as`, and written by
+//! reference to the `Bar` they point to.
+
+use std::sync::Arc;
+
+use diesel::prelude::*;
+
+use crate::schema::foo;
+use crate::Bar;
+
+#[derive(Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = foo)]
+pub(crate) struct SharedFoo {
+    pub(crate) id: String,
+    #[diesel(deserialize_as = Bar)]
+    pub(crate) bar: Arc<Bar>,
+}
+
+#[derive(Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = foo)]
+pub(crate) struct BoxedFoo {
+    pub(crate) id: String,
+    #[diesel(deserialize_as = Bar)]
+    pub(crate) bar: Box<Bar>,
+}
+
+/// `&*shared.bar` binds the `Bar` of an `Arc` like any other reference.
+#[derive(Insertable)]
+#[diesel(table_name = foo)]
+pub(crate) struct NewFoo<'a> {
+    pub(crate) id: &'a str,
+    pub(crate) bar: &'a Bar,
+}
@@ -0,0 +1,29 @@
+//! Types deriving diesel's `SqlType` next to `DieselJsonb`. The derives
+//! implement different traits, so they coexist on one type and in one module.
+
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow, SqlType};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// A sql type of its own, Postgres' `jsonb`, and a value of diesel's `Jsonb`.
+#[derive(
+    Debug, PartialEq, Serialize, Deserialize, SqlType, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(postgres_type(name = "jsonb"))]
+#[diesel(sql_type = Jsonb)]
+pub struct Setting {
+    pub key: String,
+}
+
+/// A marker for `jsonb` columns next to a value stored in them, which is
+/// stored as diesel's `Jsonb`: the derive only implements diesel's sql types.
+#[derive(SqlType)]
+#[diesel(postgres_type(name = "jsonb"))]
+pub struct Layout;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Widget {
+    pub layout: String,
+}
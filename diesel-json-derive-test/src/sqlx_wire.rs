@@ -0,0 +1,52 @@
+//! The JSONB wire format of `sqlx`, for tables written by both crates while
+//! moving from one to the other. `sqlx::types::Json<T>` (sqlx-postgres 0.7 and
+//! 0.8) encodes a JSONB parameter as the version byte 1 followed by
+//! `serde_json::to_writer`, and decodes a binary JSONB value by asserting the
+//! version byte is 1 and parsing the rest with `serde_json::from_slice`. Both
+//! are repeated here, sqlx itself is not a dependency.
+
+use std::io::Write;
+
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A value as `sqlx::types::Json` binds it.
+#[derive(Debug, AsExpression)]
+#[diesel(sql_type = Jsonb)]
+pub struct SqlxJson<T>(pub T);
+
+impl<T: Serialize + std::fmt::Debug> ToSql<Jsonb, Pg> for SqlxJson<T> {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(&[1])?;
+        serde_json::to_writer(&mut *out, &self.0)?;
+        Ok(IsNull::No)
+    }
+}
+
+/// Reads the bytes of a binary JSONB value like `sqlx::types::Json` does,
+/// failing where sqlx panics.
+pub fn sqlx_decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    match bytes.split_first() {
+        Some((1, json)) => serde_json::from_slice(json).map_err(|e| e.to_string()),
+        Some((version, _)) => Err(format!("unsupported JSONB format version {version}")),
+        None => Err("empty JSONB value".to_string()),
+    }
+}
+
+/// Escapes, non-ASCII text, floats and nesting, the parts where encoders
+/// could disagree.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+pub struct Migrated {
+    pub name: String,
+    pub ratio: f64,
+    pub tags: Vec<String>,
+    pub parent: Option<Box<Migrated>>,
+}
@@ -0,0 +1,29 @@
+//! The generated code must not trip lints in crates that deny warnings.
+
+#![deny(warnings, unused_qualifications, clippy::use_self)]
+#![allow(dead_code)]
+
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Strict {
+    pub x: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(generic_backend)]
+pub struct StrictGeneric<T> {
+    pub value: T,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(default_on_error)]
+pub struct StrictTolerant {
+    pub x: i32,
+}
@@ -0,0 +1,27 @@
+//! A type stored as serialized JSON in a plain `TEXT` column.
+
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+diesel::table! {
+    text_foo (id) {
+        id -> Text,
+        qux -> Text,
+    }
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable, Selectable)]
+#[diesel(table_name = text_foo)]
+pub struct TextFoo {
+    pub id: String,
+    pub qux: Qux,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Text)]
+pub struct Qux {
+    pub z: Vec<i32>,
+}
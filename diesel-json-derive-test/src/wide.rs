@@ -0,0 +1,60 @@
+//! 128-bit integers. serde_json writes them as plain numbers and Postgres
+//! keeps them exactly, JSONB numbers are `numeric`, but not every reader of the
+//! JSON does.
+
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Wide {
+    pub unsigned: u128,
+    pub signed: i128,
+}
+
+/// Stored as strings, for JSON that is also read by something limited to
+/// `f64` or `u64`, e.g. JavaScript.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct WideAsString {
+    #[serde(with = "as_string")]
+    pub unsigned: u128,
+    #[serde(with = "as_string")]
+    pub signed: i128,
+}
+
+/// `serde_json::Value` has no 128-bit numbers, so `sort_keys` fails for values
+/// beyond `u64` and `i64`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(sort_keys)]
+pub struct WideSorted {
+    pub unsigned: u128,
+}
+
+/// (De)serializes a number through its decimal string.
+mod as_string {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let text = <&str>::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
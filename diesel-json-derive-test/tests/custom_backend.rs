@@ -0,0 +1,185 @@
+//! Impls for a backend the crate has no feature for, with
+//! `#[diesel_json(backend = ..., value = ...)]`. `Mock` implements just what
+//! diesel needs of a backend to bind values and read them back.
+
+use diesel::backend::sql_dialect;
+use diesel::backend::{Backend, DieselReserveSpecialization, SqlDialect, TrustedBackend};
+use diesel::deserialize::FromSql;
+use diesel::query_builder::bind_collector::RawBytesBindCollector;
+use diesel::query_builder::{BindCollector, QueryBuilder};
+use diesel::sql_types::{self, HasSqlType, Json, Jsonb, Nullable, TypeMetadata};
+use diesel::{AsExpression, FromSqlRow, QueryResult};
+use diesel_json_derive::test_util::jsonb_value;
+use diesel_json_derive::{DieselJsonError, DieselJsonb, JsonbRawValue};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mock;
+
+/// The bytes of a value, like `PgValue`.
+pub struct MockValue<'a>(&'a [u8]);
+
+/// All the derive needs of the raw value.
+impl JsonbRawValue for MockValue<'_> {
+    fn jsonb_bytes(&self) -> &[u8] {
+        self.0
+    }
+}
+
+#[derive(Default)]
+pub struct MockQueryBuilder(String);
+
+impl QueryBuilder<Mock> for MockQueryBuilder {
+    fn push_sql(&mut self, sql: &str) {
+        self.0.push_str(sql);
+    }
+
+    fn push_identifier(&mut self, identifier: &str) -> QueryResult<()> {
+        self.0.push_str(identifier);
+        Ok(())
+    }
+
+    fn push_bind_param(&mut self) {
+        self.0.push('?');
+    }
+
+    fn finish(self) -> String {
+        self.0
+    }
+}
+
+impl Backend for Mock {
+    type QueryBuilder = MockQueryBuilder;
+    type RawValue<'a> = MockValue<'a>;
+    type BindCollector<'a> = RawBytesBindCollector<Mock>;
+}
+
+impl TypeMetadata for Mock {
+    type TypeMetadata = ();
+    type MetadataLookup = ();
+}
+
+macro_rules! has_sql_types {
+    ($($ty:ty),*) => {
+        $(impl HasSqlType<$ty> for Mock {
+            fn metadata(_: &mut ()) {}
+        })*
+    };
+}
+
+has_sql_types!(
+    sql_types::SmallInt,
+    sql_types::Integer,
+    sql_types::BigInt,
+    sql_types::Float,
+    sql_types::Double,
+    sql_types::Text,
+    sql_types::Binary,
+    sql_types::Date,
+    sql_types::Time,
+    sql_types::Timestamp,
+    Json,
+    Jsonb
+);
+
+impl SqlDialect for Mock {
+    type ReturningClause = sql_dialect::returning_clause::DoesNotSupportReturningClause;
+    type OnConflictClause = sql_dialect::on_conflict_clause::DoesNotSupportOnConflictClause;
+    type InsertWithDefaultKeyword = sql_dialect::default_keyword_for_insert::IsoSqlDefaultKeyword;
+    type BatchInsertSupport = sql_dialect::batch_insert_support::DoesNotSupportBatchInsert;
+    type ConcatClause = sql_dialect::concat_clause::ConcatWithPipesClause;
+    type DefaultValueClauseForInsert = sql_dialect::default_value_clause::AnsiDefaultValueClause;
+    type EmptyFromClauseSyntax = sql_dialect::from_clause_syntax::AnsiSqlFromClauseSyntax;
+    type ExistsSyntax = sql_dialect::exists_syntax::AnsiSqlExistsSyntax;
+    type ArrayComparison = sql_dialect::array_comparison::AnsiSqlArrayComparison;
+    type SelectStatementSyntax = sql_dialect::select_statement_syntax::AnsiSqlSelectStatement;
+    type AliasSyntax = sql_dialect::alias_syntax::AsAliasSyntax;
+    type WindowFrameClauseGroupSupport =
+        sql_dialect::window_frame_clause_group_support::NoGroupWindowFrameUnit;
+    type WindowFrameExclusionSupport =
+        sql_dialect::window_frame_exclusion_support::NoFrameFrameExclusionSupport;
+    type AggregateFunctionExpressions =
+        sql_dialect::aggregate_function_expressions::NoAggregateFunctionExpressions;
+    type BuiltInWindowFunctionRequireOrder =
+        sql_dialect::built_in_window_function_require_order::NoOrderRequired;
+}
+
+impl TrustedBackend for Mock {}
+impl DieselReserveSpecialization for Mock {}
+
+/// Written with a version byte of its own.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(backend = Mock, value = MockValue, version = 2)]
+pub struct Settings {
+    pub theme: String,
+}
+
+/// Also read from nullable columns.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(backend = Mock, value = MockValue, nullable)]
+pub struct Plain {
+    pub theme: String,
+}
+
+/// The bytes `value` is bound as.
+fn bind<ST, T>(value: &T) -> Vec<u8>
+where
+    Mock: HasSqlType<ST>,
+    T: diesel::serialize::ToSql<ST, Mock>,
+{
+    let mut collector = RawBytesBindCollector::<Mock>::new();
+    collector
+        .push_bound_value::<ST, T>(value, &mut ())
+        .expect("binding succeeds");
+    collector.binds.pop().flatten().expect("a non-null bind")
+}
+
+#[test]
+fn roundtrip() {
+    let settings = Settings {
+        theme: "dark".to_string(),
+    };
+    let bytes = bind::<Jsonb, _>(&settings);
+    assert_eq!(bytes, b"\x02{\"theme\":\"dark\"}");
+    let loaded = <Settings as FromSql<Jsonb, Mock>>::from_sql(MockValue(&bytes)).unwrap();
+    assert_eq!(loaded, settings);
+    let error =
+        <Settings as FromSql<Jsonb, Mock>>::from_sql(MockValue(b"\x01{\"theme\":\"dark\"}"))
+            .unwrap_err();
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(*error, DieselJsonError::UnsupportedVersion(1)),
+        "{error}"
+    );
+
+    let plain = Plain {
+        theme: "light".to_string(),
+    };
+    let bytes = bind::<Json, _>(&plain);
+    assert_eq!(bytes, b"{\"theme\":\"light\"}");
+    let loaded = <Plain as FromSql<Json, Mock>>::from_sql(MockValue(&bytes)).unwrap();
+    assert_eq!(loaded, plain);
+    let loaded =
+        <Plain as FromSql<Nullable<Json>, Mock>>::from_nullable_sql(Some(MockValue(&bytes)))
+            .unwrap();
+    assert_eq!(loaded, plain);
+    let error = <Plain as FromSql<Nullable<Json>, Mock>>::from_nullable_sql(None).unwrap_err();
+    let error = error.downcast::<DieselJsonError>().unwrap();
+    assert!(
+        matches!(
+            *error,
+            DieselJsonError::UnexpectedNull { type_name: "Plain" }
+        ),
+        "{error}"
+    );
+
+    // the Postgres impls read through the same trait
+    let value = jsonb_value(b"\x01{\"theme\":\"dark\"}");
+    assert_eq!(value.jsonb_bytes(), b"\x01{\"theme\":\"dark\"}");
+    assert_eq!(
+        value.jsonb_bytes(),
+        MockValue(b"\x01{\"theme\":\"dark\"}").jsonb_bytes()
+    );
+}
@@ -0,0 +1,11 @@
+//! `DIESEL_JSONB_DEFAULT_FORMAT` changes the format of `Binary` types without
+//! a `format`. The cases are compiled by trybuild, which inherits the variable
+//! set here, and run to check the bytes.
+
+#[test]
+fn default_format() {
+    // read by the derive while trybuild compiles the cases
+    std::env::set_var("DIESEL_JSONB_DEFAULT_FORMAT", "msgpack");
+    let t = trybuild::TestCases::new();
+    t.pass("tests/default_format/*.rs");
+}
@@ -0,0 +1,54 @@
+use std::num::NonZeroU32;
+
+use diesel::deserialize::FromSql;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::ToSql;
+use diesel::sql_types::{Binary, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::test_util::to_sql_bytes;
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// Stored as MessagePack without saying so.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+struct Packed {
+    id: u32,
+    name: String,
+}
+
+/// Keeps JSON.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Binary)]
+#[diesel_json(format = "json")]
+struct Plain {
+    id: u32,
+}
+
+/// JSON columns are never affected.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+struct Document {
+    id: u32,
+}
+
+static BYTEA_OID: NonZeroU32 = NonZeroU32::new(17).unwrap();
+
+fn to_sql<T: ToSql<Binary, Pg>>(value: &T) -> Vec<u8> {
+    to_sql_bytes::<Binary, T>(value).unwrap()
+}
+
+fn main() {
+    let packed = Packed {
+        id: 1,
+        name: "a".to_string(),
+    };
+    let bytes = to_sql(&packed);
+    assert_eq!(bytes, rmp_serde::to_vec(&packed).unwrap());
+    let loaded: Packed = FromSql::<Binary, Pg>::from_sql(PgValue::new(&bytes, &BYTEA_OID)).unwrap();
+    assert_eq!(loaded, packed);
+
+    assert_eq!(to_sql(&Plain { id: 2 }), br#"{"id":2}"#);
+
+    let _ = Document { id: 3 };
+}
@@ -0,0 +1,134 @@
+//! With the `tracing` feature values `from_sql` can't read are logged before
+//! the error is returned. Reads `PgValue`s of bytes, so no database is
+//! needed, and collects the events with a subscriber of its own. Needs the
+//! `tracing` feature of this crate.
+
+#![cfg(feature = "tracing")]
+
+use std::fmt::{Debug, Write};
+use std::sync::{Arc, Mutex};
+
+use diesel::deserialize::FromSql;
+use diesel::pg::Pg;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::test_util::jsonb_value;
+use diesel_json_derive::{DieselJsonError, DieselJsonb};
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+struct Logged {
+    x: i32,
+}
+
+#[derive(
+    Debug, Default, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(default_on_error)]
+struct LoggedDefault {
+    x: i32,
+}
+
+/// The fields of every event, `name=value` separated by spaces.
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<String>>>);
+
+impl Events {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = write!(self.0, "{}={:?} ", field.name(), value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let _ = write!(self.0, "{}={} ", field.name(), value);
+    }
+}
+
+impl Subscriber for Events {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0.trim_end().to_string());
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn read<T: FromSql<Jsonb, Pg>>(bytes: &[u8]) -> diesel::deserialize::Result<T> {
+    T::from_sql(jsonb_value(bytes))
+}
+
+#[test]
+fn read_errors() {
+    let events = Events::default();
+    tracing::subscriber::with_default(events.clone(), || {
+        assert_eq!(read::<Logged>(b"\x01{\"x\":1}").unwrap(), Logged { x: 1 });
+        assert_eq!(events.take(), Vec::<String>::new());
+
+        let error = read::<Logged>(b"\x01{\"x\":\"one\"}").unwrap_err();
+        let error = error.downcast::<DieselJsonError>().unwrap();
+        assert!(
+            matches!(*error, DieselJsonError::Deserialize { .. }),
+            "{error}"
+        );
+        let logged = events.take();
+        assert_eq!(logged.len(), 1, "{logged:?}");
+        assert!(
+            logged[0].starts_with("message=cannot read Logged: "),
+            "{logged:?}"
+        );
+        assert!(
+            logged[0].ends_with("type_name=Logged len=12 bytes=017b2278223a226f6e65227d"),
+            "{logged:?}"
+        );
+
+        // only the first 32 bytes are shown
+        let long = [b"\x02".as_slice(), &[b' '; 40]].concat();
+        let error = read::<Logged>(&long).unwrap_err();
+        let error = error.downcast::<DieselJsonError>().unwrap();
+        assert!(
+            matches!(*error, DieselJsonError::UnsupportedVersion(2)),
+            "{error}"
+        );
+        let logged = events.take();
+        assert!(
+            logged[0].ends_with(&format!("len=41 bytes=02{}...", "20".repeat(31))),
+            "{logged:?}"
+        );
+
+        // default_on_error still reads the default, but the row is logged
+        assert_eq!(
+            read::<LoggedDefault>(b"\x01null").unwrap(),
+            LoggedDefault::default()
+        );
+        let logged = events.take();
+        assert_eq!(logged.len(), 1, "{logged:?}");
+        assert!(logged[0].contains("type_name=LoggedDefault"), "{logged:?}");
+    });
+}
@@ -0,0 +1,43 @@
+//! MySQL binds of a type shared with Postgres, collected like diesel does
+//! without a server. Needs the `mysql` feature of this crate.
+
+#![cfg(feature = "mysql")]
+
+use diesel::deserialize::FromSql;
+use diesel::mysql::{Mysql, MysqlType, MysqlValue};
+use diesel::query_builder::bind_collector::RawBytesBindCollector;
+use diesel::query_builder::BindCollector;
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// `Jsonb` on Postgres, `Json` on MySQL.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sql_types(Jsonb, Json))]
+struct Settings {
+    theme: String,
+    width: u32,
+}
+
+#[test]
+fn json_bind() {
+    let value = Settings {
+        theme: "dark".to_string(),
+        width: 80,
+    };
+    let mut collector = RawBytesBindCollector::<Mysql>::new();
+    collector
+        .push_bound_value::<Json, Settings>(&value, &mut ())
+        .unwrap();
+    assert_eq!(collector.metadata, [MysqlType::String]);
+    let bytes = collector.binds.pop().flatten().expect("a non-null bind");
+    assert_eq!(bytes, br#"{"theme":"dark","width":80}"#);
+
+    let loaded =
+        <Settings as FromSql<Json, Mysql>>::from_sql(MysqlValue::new(&bytes, MysqlType::String))
+            .unwrap();
+    assert_eq!(loaded, value);
+}
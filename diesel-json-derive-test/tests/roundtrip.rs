@@ -0,0 +1,165 @@
+//! Property tests of the Postgres round-trip: random values are bound with
+//! diesel's bind collector and read back from a `PgValue` of the bytes
+//! `to_sql` wrote, both with the `test_util` helpers, for `Jsonb` and `Json`.
+//! No database is involved, so strings with NUL bytes, which Postgres itself
+//! rejects, are covered too.
+//!
+//! The floats are compared exactly, which needs the `float-roundtrip` feature
+//! the dev-dependency enables.
+
+use std::collections::BTreeMap;
+
+use diesel::deserialize::FromSql;
+use diesel::pg::{Pg, PgValue};
+use diesel::sql_types::{Json, Jsonb};
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::test_util::{json_value, jsonb_bytes, jsonb_value, to_sql_bytes};
+use diesel_json_derive::{read_length_prefixed, DieselJsonError, DieselJsonb};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb,
+)]
+#[diesel(sql_type = Jsonb)]
+#[diesel(sql_type = Json)]
+#[diesel_json(sql_types(Jsonb, Json))]
+struct Document {
+    name: String,
+    tags: Vec<String>,
+    attributes: BTreeMap<String, Nested>,
+    score: f64,
+    count: i64,
+    flag: Option<bool>,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Nested {
+    values: BTreeMap<String, Vec<u32>>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Unit,
+    Newtype(String),
+    Struct { x: i32, y: f32 },
+}
+
+/// Reads `value` back like diesel does.
+fn from_sql<ST>(value: PgValue<'_>) -> diesel::deserialize::Result<Document>
+where
+    Document: FromSql<ST, Pg>,
+{
+    Document::from_sql(value)
+}
+
+fn nested() -> impl Strategy<Value = Nested> {
+    (
+        prop::collection::btree_map(
+            any::<String>(),
+            prop::collection::vec(any::<u32>(), 0..4),
+            0..4,
+        ),
+        any::<Option<String>>(),
+    )
+        .prop_map(|(values, note)| Nested { values, note })
+}
+
+fn kind() -> impl Strategy<Value = Kind> {
+    prop_oneof![
+        Just(Kind::Unit),
+        any::<String>().prop_map(Kind::Newtype),
+        (any::<i32>(), prop::num::f32::NORMAL | prop::num::f32::ZERO)
+            .prop_map(|(x, y)| Kind::Struct { x, y }),
+    ]
+}
+
+/// Documents with finite floats only, JSON has no other numbers. The strings
+/// are any `char`s, control characters and NUL included.
+fn record() -> impl Strategy<Value = Document> {
+    let finite = prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO;
+    (
+        any::<String>(),
+        prop::collection::vec(any::<String>(), 0..4),
+        prop::collection::btree_map(any::<String>(), nested(), 0..3),
+        finite,
+        any::<i64>(),
+        any::<Option<bool>>(),
+        kind(),
+    )
+        .prop_map(
+            |(name, tags, attributes, score, count, flag, kind)| Document {
+                name,
+                tags,
+                attributes,
+                score,
+                count,
+                flag,
+                kind,
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn jsonb_roundtrip(value in record()) {
+        let bytes = to_sql_bytes::<Jsonb, _>(&value).unwrap();
+        prop_assert_eq!(&bytes, &jsonb_bytes(&value));
+        prop_assert_eq!(from_sql::<Jsonb>(jsonb_value(&bytes)).unwrap(), value);
+    }
+
+    #[test]
+    fn json_roundtrip(value in record()) {
+        let bytes = to_sql_bytes::<Json, _>(&value).unwrap();
+        prop_assert_eq!(from_sql::<Json>(json_value(&bytes)).unwrap(), value);
+    }
+
+    /// Two records of a length-prefixed log, each the bytes `to_sql` binds
+    /// behind their big-endian `u32` length, are read one after the other.
+    /// A log cut short anywhere fails instead of reading a partial record.
+    #[test]
+    fn length_prefixed_log(first in record(), second in record(), cut in any::<prop::sample::Index>()) {
+        let mut log = Vec::new();
+        for value in [&first, &second] {
+            let bytes = to_sql_bytes::<Jsonb, _>(value).unwrap();
+            log.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            log.extend_from_slice(&bytes);
+        }
+        let (read, consumed) = read_length_prefixed::<Document>(&log).unwrap();
+        prop_assert_eq!(read, first);
+        let (read, rest) = read_length_prefixed::<Document>(&log[consumed..]).unwrap();
+        prop_assert_eq!(read, second);
+        prop_assert_eq!(consumed + rest, log.len());
+
+        let cut = cut.index(consumed);
+        let error = read_length_prefixed::<Document>(&log[..cut]).unwrap_err();
+        let error = error.downcast::<DieselJsonError>().unwrap();
+        prop_assert!(
+            matches!(*error, DieselJsonError::TruncatedRecord { .. }),
+            "{}",
+            error
+        );
+    }
+
+    /// serde_json writes NaN and the infinities as `null`, so `to_sql`
+    /// succeeds and reading the value back fails with an error naming the
+    /// type.
+    #[test]
+    fn non_finite_floats_fail_to_read(
+        value in record(),
+        score in prop_oneof![Just(f64::NAN), Just(f64::INFINITY), Just(f64::NEG_INFINITY)],
+    ) {
+        let value = Document { score, ..value };
+        let bytes = to_sql_bytes::<Jsonb, _>(&value).unwrap();
+        let error = from_sql::<Jsonb>(jsonb_value(&bytes)).unwrap_err();
+        let error = error.downcast::<DieselJsonError>().unwrap();
+        prop_assert!(
+            matches!(*error, DieselJsonError::Deserialize { type_name: "Document", .. }),
+            "{}",
+            error
+        );
+        prop_assert!(error.to_string().contains("invalid type: null"), "{}", error);
+    }
+}
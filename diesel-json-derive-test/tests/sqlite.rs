@@ -0,0 +1,257 @@
+//! Round-trips `DieselJsonb` types through an in-memory SQLite database, with
+//! the models the Postgres run of the binary uses as well.
+
+#[path = "../src/binary.rs"]
+mod binary;
+#[path = "../src/json.rs"]
+mod json;
+#[path = "../src/models.rs"]
+mod models;
+#[path = "../src/nullable.rs"]
+mod nullable;
+#[path = "../src/schema.rs"]
+mod schema;
+#[path = "../src/text.rs"]
+mod text;
+
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Binary, Integer, Jsonb, Text};
+use diesel::sqlite::SqliteConnection;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::{DieselJsonError, DieselJsonb};
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{
+    assert_compressed_roundtrip, assert_formats_roundtrip, assert_tagged_roundtrip, binary_foo,
+    BinaryFoo, Quux,
+};
+use crate::json::{json_foo, Baz, JsonFoo};
+use crate::models::{Bar, Foo, FooView};
+use crate::nullable::assert_nullable_roundtrip;
+use crate::schema::foo;
+use crate::text::{text_foo, Qux, TextFoo};
+
+/// What `jsonb()` keeps of JSON5 input: a hexadecimal integer, a float
+/// without its leading digit and a single-quoted string.
+#[derive(Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+struct Json5 {
+    hex: i64,
+    half: f64,
+    quoted: String,
+}
+
+#[test]
+fn roundtrip() -> QueryResult<()> {
+    let mut conn = SqliteConnection::establish(":memory:").expect("in-memory sqlite");
+    diesel::sql_query("CREATE TABLE foo (id TEXT PRIMARY KEY, bar JSONB NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query("CREATE TABLE json_foo (id TEXT PRIMARY KEY, baz JSON NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query("CREATE TABLE text_foo (id TEXT PRIMARY KEY, qux TEXT NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query("CREATE TABLE binary_foo (id TEXT PRIMARY KEY, quux BLOB NOT NULL)")
+        .execute(&mut conn)?;
+    diesel::sql_query("CREATE TABLE maybe_foo (id TEXT PRIMARY KEY, bar JSONB)")
+        .execute(&mut conn)?;
+
+    let value = Foo {
+        id: "sqlite".to_string(),
+        bar: Bar { x: 42 },
+    };
+    diesel::insert_into(foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = foo::table
+        .find(&value.id)
+        .select(Foo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    let loaded = foo::table
+        .find(&value.id)
+        .select(FooView::as_select())
+        .first(&mut conn)?;
+    assert_eq!(
+        loaded,
+        FooView {
+            payload: Bar { x: 42 },
+            maybe_payload: Some(Bar { x: 42 }),
+        }
+    );
+
+    jsonb(&mut conn)?;
+
+    let value = JsonFoo {
+        id: "sqlite".to_string(),
+        baz: Baz {
+            y: "json".to_string(),
+        },
+    };
+    diesel::insert_into(json_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = json_foo::table
+        .find(&value.id)
+        .select(JsonFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+
+    let value = TextFoo {
+        id: "sqlite".to_string(),
+        qux: Qux { z: vec![1, 2] },
+    };
+    diesel::insert_into(text_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = text_foo::table
+        .find(&value.id)
+        .select(TextFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    // stored as text, which SQLite's JSON functions read
+    let row = text_foo::table.find(&value.id);
+    let stored = row
+        .select(sql::<Text>("typeof(qux)"))
+        .first::<String>(&mut conn)?;
+    assert_eq!(stored, "text");
+    let z = row
+        .select(sql::<Integer>("json_extract(qux, '$.z[1]')"))
+        .first::<i32>(&mut conn)?;
+    assert_eq!(z, 2);
+
+    let value = BinaryFoo {
+        id: "sqlite".to_string(),
+        quux: Quux { w: true },
+    };
+    diesel::insert_into(binary_foo::table)
+        .values(&value)
+        .execute(&mut conn)?;
+    let loaded = binary_foo::table
+        .find(&value.id)
+        .select(BinaryFoo::as_select())
+        .first(&mut conn)?;
+    assert_eq!(loaded, value);
+    let bytes = binary_foo::table
+        .find(&value.id)
+        .select(diesel::dsl::sql::<diesel::sql_types::Binary>("quux"))
+        .first::<Vec<u8>>(&mut conn)?;
+    assert_eq!(bytes, b"{\"w\":true}");
+
+    // SQLite would read the text with a replacement character, the bytes are
+    // rejected instead
+    diesel::sql_query("INSERT INTO text_foo (id, qux) VALUES ('latin1', ?)")
+        .bind::<Binary, _>(b"{\"z\":[1],\"name\":\"caf\xe9\"}".as_slice())
+        .execute(&mut conn)?;
+    let error = text_foo::table
+        .find("latin1")
+        .select(TextFoo::as_select())
+        .first(&mut conn)
+        .unwrap_err();
+    let diesel::result::Error::DeserializationError(error) = error else {
+        panic!("expected a deserialization error, got {error:?}");
+    };
+    let error = error
+        .downcast::<diesel::result::DeserializeFieldError>()
+        .expect("a field error");
+    assert_eq!(
+        error.error.to_string(),
+        "JSON text for Qux is not valid UTF-8 after 20 bytes"
+    );
+
+    assert_nullable_roundtrip!(&mut conn);
+    assert_formats_roundtrip!(&mut conn);
+    assert_tagged_roundtrip!(&mut conn);
+    assert_compressed_roundtrip!(&mut conn);
+
+    Ok(())
+}
+
+/// `Jsonb` values are SQLite's binary JSONB, as written by its `jsonb()` and
+/// by diesel's `serde_json::Value`.
+fn jsonb(conn: &mut SqliteConnection) -> QueryResult<()> {
+    diesel::sql_query("INSERT INTO foo (id, bar) VALUES ('jsonb', jsonb('{\"x\":42}'))")
+        .execute(conn)?;
+    let loaded = foo::table
+        .find("jsonb")
+        .select(Foo::as_select())
+        .first(conn)?;
+    assert_eq!(loaded.bar, Bar { x: 42 });
+
+    let value = serde_json::json!({ "x": 7 });
+    let loaded =
+        diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value)).get_result::<Bar>(conn)?;
+    assert_eq!(loaded, Bar { x: 7 });
+
+    let value = Bar { x: 43 };
+    let loaded = diesel::select(sql::<Jsonb>("").bind::<Jsonb, _>(&value))
+        .get_result::<serde_json::Value>(conn)?;
+    assert_eq!(loaded, serde_json::json!({ "x": 43 }));
+    let json = diesel::select(sql::<Text>("json(").bind::<Jsonb, _>(&value).sql(")"))
+        .get_result::<String>(conn)?;
+    assert_eq!(json, "{\"x\":43}");
+    let x = diesel::select(
+        sql::<Integer>("json_extract(")
+            .bind::<Jsonb, _>(&value)
+            .sql(", '$.x')"),
+    )
+    .get_result::<i32>(conn)?;
+    assert_eq!(x, 43);
+    let stored = foo::table
+        .find("sqlite")
+        .select(sql::<Text>("json(bar)"))
+        .first::<String>(conn)?;
+    assert_eq!(stored, "{\"x\":42}");
+
+    let loaded = diesel::select(sql::<Jsonb>(
+        "jsonb('{hex: 0x2A, half: .5, quoted: ''say \"hi\"''}')",
+    ))
+    .get_result::<Json5>(conn)?;
+    assert_eq!(
+        loaded,
+        Json5 {
+            hex: 42,
+            half: 0.5,
+            quoted: "say \"hi\"".to_string(),
+        }
+    );
+
+    // escapes and headers with sizes of one and two bytes, as SQLite reads them
+    let value = Json5 {
+        hex: -1,
+        half: 1.5e-7,
+        quoted: format!("\"\\\n\u{1} caf\u{e9} {}", "x".repeat(300)),
+    };
+    let json = diesel::select(sql::<Text>("json(").bind::<Jsonb, _>(&value).sql(")"))
+        .get_result::<String>(conn)?;
+    assert_eq!(json, serde_json::to_string(&value).unwrap());
+    let loaded = diesel::select(sql::<Jsonb>("jsonb(").bind::<Text, _>(&json).sql(")"))
+        .get_result::<Json5>(conn)?;
+    assert_eq!(loaded, value);
+
+    // JSON text in a blob is not JSONB
+    let error = diesel::select(sql::<Jsonb>("").bind::<Binary, _>(b"{\"x\":1}".as_slice()))
+        .get_result::<Bar>(conn)
+        .unwrap_err();
+    let diesel::result::Error::DeserializationError(error) = error else {
+        panic!("expected a deserialization error, got {error:?}");
+    };
+    let error = error
+        .downcast::<diesel::result::DeserializeFieldError>()
+        .expect("a field error")
+        .error
+        .downcast::<DieselJsonError>()
+        .expect("a DieselJsonError");
+    assert!(
+        matches!(
+            *error,
+            DieselJsonError::Deserialize {
+                format: "SQLite JSONB",
+                ..
+            }
+        ),
+        "{error}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,8 @@
+//! The compile errors for invalid uses of the derive. Regenerate the
+//! `.stderr` files with `TRYBUILD=overwrite cargo test -p diesel-json-derive-test`.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,23 @@
+use diesel::deserialize::FromSql;
+use diesel::pg::Pg;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(backend_cfg(postgres = "any()"))]
+pub struct Settings {
+    pub theme: String,
+}
+
+fn reads<DB: diesel::backend::Backend>()
+where
+    Settings: FromSql<Jsonb, DB>,
+{
+}
+
+fn main() {
+    reads::<Pg>();
+}
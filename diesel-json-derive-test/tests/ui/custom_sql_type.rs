@@ -0,0 +1,19 @@
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+mod types {
+    #[derive(diesel::SqlType)]
+    #[diesel(postgres_type(name = "jsonb"))]
+    pub struct Jsonb;
+}
+
+use types::Jsonb;
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Bar {
+    pub x: i32,
+}
+
+fn main() {}
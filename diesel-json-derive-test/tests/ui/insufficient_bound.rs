@@ -0,0 +1,14 @@
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+/// The bounds replace the inferred ones, and serde's impls need more.
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(serialize_bound = "", deserialize_bound = "T: serde::de::DeserializeOwned")]
+pub struct Bare<T> {
+    pub value: T,
+}
+
+fn main() {}
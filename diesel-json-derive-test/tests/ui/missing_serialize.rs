@@ -0,0 +1,12 @@
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+pub struct Bar {
+    pub x: i32,
+}
+
+fn main() {}
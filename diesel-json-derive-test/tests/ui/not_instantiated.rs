@@ -0,0 +1,20 @@
+use diesel::pg::Pg;
+use diesel::serialize::ToSql;
+use diesel::sql_types::Jsonb;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Jsonb)]
+#[diesel_json(instantiate(Reading<u32>))]
+pub struct Reading<T> {
+    pub value: T,
+}
+
+fn assert_to_sql<T: ToSql<Jsonb, Pg>>() {}
+
+fn main() {
+    assert_to_sql::<Reading<u32>>();
+    assert_to_sql::<Reading<bool>>();
+}
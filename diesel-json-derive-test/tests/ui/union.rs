@@ -0,0 +1,9 @@
+use diesel_json_derive::DieselJsonb;
+
+#[derive(DieselJsonb)]
+pub union Bits {
+    pub int: u32,
+    pub float: f32,
+}
+
+fn main() {}
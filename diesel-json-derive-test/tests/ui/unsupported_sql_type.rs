@@ -0,0 +1,12 @@
+use diesel::sql_types::Integer;
+use diesel::{AsExpression, FromSqlRow};
+use diesel_json_derive::DieselJsonb;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, AsExpression, FromSqlRow, DieselJsonb)]
+#[diesel(sql_type = Integer)]
+pub struct Bar {
+    pub x: i32,
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use std::cell::Cell;
+
+/// Buffers that grew larger than this are dropped instead of kept for the
+/// next value, so that one large value doesn't hold on to its memory for the
+/// life of the thread.
+const MAX_KEPT_CAPACITY: usize = 1 << 20;
+
+thread_local! {
+    static SCRATCH: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// Calls `f` with an empty buffer of the current thread, the one the last
+/// call left behind, for `#[diesel_json(reuse_buffer)]`. The buffer is taken
+/// out while `f` runs, so a nested call, e.g. from a `Serialize` impl writing
+/// another such value, gets a new one instead of a buffer in use.
+pub fn with_scratch_buffer<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    let mut buf = SCRATCH.take();
+    buf.clear();
+    let result = f(&mut buf);
+    if buf.capacity() <= MAX_KEPT_CAPACITY {
+        SCRATCH.set(buf);
+    }
+    result
+}
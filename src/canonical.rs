@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// Writes the JSON of `value` in the canonical form of RFC 8785 (JCS), what
+/// `#[diesel_json(canonical)]` writes: no whitespace, the keys of every object
+/// in the order of their UTF-16 code units and numbers as ECMAScript prints
+/// them, e.g. `1` for `1.0` and `1e+21` for `1e21`. Equal values give the same
+/// bytes however their maps were built and whatever number types they hold,
+/// e.g. for hashing them.
+///
+/// JCS reads every number as an `f64`, integers that a `u64` or `i64` holds
+/// are written as they are instead, so that large ids keep their digits. NaN
+/// and the infinities are written as `null`, like serde_json does.
+pub fn to_canonical_writer<W, T>(mut writer: W, value: &T) -> serde_json::Result<()>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    write_value(&mut writer, &serde_json::to_value(value)?)
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> serde_json::Result<()> {
+    match value {
+        Value::Number(number) => write_number(writer, number),
+        Value::Array(values) => {
+            writer.write_all(b"[").map_err(serde_json::Error::io)?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                write_value(writer, value)?;
+            }
+            writer.write_all(b"]").map_err(serde_json::Error::io)
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            writer.write_all(b"{").map_err(serde_json::Error::io)?;
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                serde_json::to_writer(&mut *writer, key)?;
+                writer.write_all(b":").map_err(serde_json::Error::io)?;
+                write_value(writer, value)?;
+            }
+            writer.write_all(b"}").map_err(serde_json::Error::io)
+        }
+        // serde_json escapes strings as JCS does: `"`, `\` and the control
+        // characters, the short escapes where JSON has them
+        value => serde_json::to_writer(writer, value),
+    }
+}
+
+/// Integers as they are, other numbers with [`es_number`]. With the
+/// `arbitrary-precision` feature a number is the text it was parsed from or
+/// written as, which is an integer if it has no fraction or exponent.
+fn write_number<W: Write>(writer: &mut W, number: &Number) -> serde_json::Result<()> {
+    let text = number.to_string();
+    let text = if text.bytes().all(|b| b == b'-' || b.is_ascii_digit()) {
+        text
+    } else {
+        match number.as_f64() {
+            Some(float) if float.is_finite() => es_number(float),
+            _ => "null".to_string(),
+        }
+    };
+    writer
+        .write_all(text.as_bytes())
+        .map_err(serde_json::Error::io)
+}
+
+/// `value` formatted like ECMAScript's `Number.prototype.toString`, the
+/// shortest digits that read back as `value`, in plain notation from `1e-6`
+/// up to below `1e21` and in exponent notation outside of it.
+fn es_number(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    // `{:e}` gives the shortest digits, e.g. `-1.25e-7`
+    let scientific = format!("{value:e}");
+    let (sign, scientific) = match scientific.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", scientific.as_str()),
+    };
+    let (mantissa, exponent) = scientific.split_once('e').expect("`{:e}` has an exponent");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("an integer exponent") + 1;
+    let text = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let exponent = n - 1;
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{rest}")
+        };
+        format!("{first}{fraction}e{exponent_sign}{}", exponent.abs())
+    };
+    format!("{sign}{text}")
+}
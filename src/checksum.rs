@@ -0,0 +1,49 @@
+use crate::DieselJsonError;
+
+/// The first byte of a value written with `#[diesel_json(checksum = "crc32")]`,
+/// followed by the big-endian CRC32 of the JSON and the JSON itself. JSON text
+/// never starts with a NUL byte, so rows written without the checksum are
+/// told apart and read as they are.
+pub const CHECKSUM_MARKER: u8 = 0;
+
+/// The CRC32 (IEEE, as used by zlib and gzip) of `bytes`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// `json` behind the [`CHECKSUM_MARKER`] and its CRC32.
+pub fn with_checksum(json: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(json.len() + 5);
+    buf.push(CHECKSUM_MARKER);
+    buf.extend_from_slice(&crc32(json).to_be_bytes());
+    buf.extend_from_slice(json);
+    buf
+}
+
+/// The JSON of a value written by [`with_checksum`], after checking the
+/// CRC32. Values without the marker are returned unchanged.
+pub fn checksum_payload(bytes: &[u8]) -> Result<&[u8], DieselJsonError> {
+    match bytes.split_first() {
+        Some((&CHECKSUM_MARKER, rest)) => {
+            if rest.len() < 4 {
+                return Err(DieselJsonError::TruncatedChecksum);
+            }
+            let (stored, json) = rest.split_at(4);
+            let stored = u32::from_be_bytes(stored.try_into().expect("four bytes"));
+            let computed = crc32(json);
+            if stored != computed {
+                return Err(DieselJsonError::ChecksumMismatch { stored, computed });
+            }
+            Ok(json)
+        }
+        _ => Ok(bytes),
+    }
+}
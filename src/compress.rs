@@ -0,0 +1,47 @@
+//! `#[diesel_json(compress = "gzip")]`: the JSON gzipped behind the tag of
+//! `TaggedFormat::GzipJson`, which no JSON starts with.
+
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::{DieselJsonError, TaggedFormat};
+
+/// `json` gzipped, behind the tag of [`TaggedFormat::GzipJson`].
+pub fn gzip_json(json: &[u8]) -> std::io::Result<Vec<u8>> {
+    let buf = vec![TaggedFormat::GzipJson.tag()];
+    let mut encoder = GzEncoder::new(buf, Compression::default());
+    encoder.write_all(json)?;
+    encoder.finish()
+}
+
+/// The JSON of a value written with `compress`, decompressed if it starts
+/// with the tag of [`TaggedFormat::GzipJson`] and as it is otherwise, e.g.
+/// for rows written before the type was compressed.
+pub fn gunzip_payload<'a>(
+    bytes: &'a [u8],
+    type_name: &'static str,
+) -> Result<Cow<'a, [u8]>, DieselJsonError> {
+    match bytes.split_first() {
+        Some((&tag, gzipped)) if tag == TaggedFormat::GzipJson.tag() => {
+            gunzip(gzipped, type_name).map(Cow::Owned)
+        }
+        _ => Ok(Cow::Borrowed(bytes)),
+    }
+}
+
+/// The decompressed bytes of gzipped JSON without its tag.
+pub fn gunzip(gzipped: &[u8], type_name: &'static str) -> Result<Vec<u8>, DieselJsonError> {
+    let mut json = Vec::new();
+    GzDecoder::new(gzipped)
+        .read_to_end(&mut json)
+        .map_err(|error| DieselJsonError::Deserialize {
+            type_name,
+            format: "gzip",
+            source: Box::new(error),
+        })?;
+    Ok(json)
+}
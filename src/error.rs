@@ -0,0 +1,303 @@
+use std::error::Error;
+use std::fmt;
+
+/// The error the generated `from_sql` impls return, boxed as diesel requires,
+/// and `to_sql` for values rejected by `pre_serialize`. Downcast diesel's
+/// deserialization or serialization error to get at it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DieselJsonError {
+    /// A JSONB value without even the version byte.
+    Empty,
+    /// The JSONB version byte is not the one the type accepts.
+    UnsupportedVersion(u8),
+    /// A JSONB value that is just the version byte, without any JSON.
+    MissingPayload,
+    /// The value could not be decoded into the type.
+    Deserialize {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The format of the value, e.g. `Json` or `MessagePack`.
+        format: &'static str,
+        /// The error of the decoder, e.g. a `serde_json::Error`.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The JSON nests arrays and objects deeper than the type's `max_depth`.
+    TooDeep {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The deepest nesting the type accepts.
+        max_depth: usize,
+    },
+    /// A value of a `Json` or `Text` column is not valid UTF-8.
+    NotUtf8 {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The length of the valid UTF-8 the value starts with.
+        valid_up_to: usize,
+    },
+    /// A `strict` type was read from a value that is not a JSON object.
+    NotAnObject {
+        /// The name of the type that was read.
+        type_name: &'static str,
+    },
+    /// The value was decoded but rejected by the `validate` function.
+    Invalid {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// What the `validate` function returned.
+        message: String,
+    },
+    /// The value was rejected by the `pre_serialize` function and not written.
+    Rejected {
+        /// The name of the type that was written.
+        type_name: &'static str,
+        /// What the `pre_serialize` function returned.
+        message: String,
+    },
+    /// A value serialized to more bytes than its `max_bytes`, nothing was
+    /// written.
+    TooLarge {
+        /// The name of the type that was written.
+        type_name: &'static str,
+        /// The number of bytes the value serialized to.
+        size: usize,
+        /// The `max_bytes` of the type.
+        max_bytes: usize,
+    },
+    /// A `validate_on_write` type's `Serialize` impl wrote bytes that are not
+    /// JSON, nothing was written.
+    InvalidOutput {
+        /// The name of the type that was written.
+        type_name: &'static str,
+        /// The error of parsing the bytes, a `serde_json::Error`.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// A value of an older `schema_version` could not be migrated, or the
+    /// value is of a newer one.
+    Migrate {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The schema version of the value, 0 for values without one.
+        schema_version: u8,
+        /// What the `migrate` function returned, or why it wasn't called.
+        message: String,
+    },
+    /// An `enum_discriminant` value names a variant the enum doesn't have.
+    UnknownDiscriminant {
+        /// The name of the enum that was read.
+        type_name: &'static str,
+        /// The discriminant byte of the value.
+        discriminant: u8,
+    },
+    /// An `enum_discriminant` value of a unit variant has bytes after the
+    /// discriminant.
+    UnexpectedPayload {
+        /// The name of the enum that was read.
+        type_name: &'static str,
+        /// The discriminant byte of the value.
+        discriminant: u8,
+    },
+    /// A `nullable` type was read from a SQL `NULL`, which only an `Option`
+    /// of it can hold.
+    UnexpectedNull {
+        /// The name of the type that was read.
+        type_name: &'static str,
+    },
+    /// A `tagged_format` value is empty or starts with a byte that is not the
+    /// tag of any format.
+    UnknownFormat {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The first byte of the value, `None` for an empty value.
+        tag: Option<u8>,
+    },
+    /// A `tagged_format` value is in a format the derive can't read, e.g.
+    /// MessagePack without the `messagepack` feature.
+    UnsupportedFormat {
+        /// The name of the type that was read.
+        type_name: &'static str,
+        /// The name of the format.
+        format: &'static str,
+    },
+    /// A value with a checksum ends before the checksum does.
+    TruncatedChecksum,
+    /// The checksum of the value is not the one stored with it.
+    ChecksumMismatch {
+        /// The CRC32 stored in front of the JSON.
+        stored: u32,
+        /// The CRC32 of the JSON as read.
+        computed: u32,
+    },
+    /// A log of length-prefixed records ends within a record or its length,
+    /// see [`read_length_prefixed`](crate::read_length_prefixed).
+    TruncatedRecord {
+        /// The bytes the length prefix or the record needs.
+        needed: usize,
+        /// The bytes that are left.
+        available: usize,
+    },
+}
+
+impl fmt::Display for DieselJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DieselJsonError::Empty => write!(f, "Empty JSONB value"),
+            DieselJsonError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported JSONB encoding version {version}")
+            }
+            DieselJsonError::MissingPayload => {
+                write!(f, "JSONB value contains version byte but no payload")
+            }
+            DieselJsonError::Deserialize {
+                type_name,
+                format,
+                source,
+            } => write!(f, "Invalid {format} for {type_name}: {source}"),
+            DieselJsonError::TooDeep {
+                type_name,
+                max_depth,
+            } => write!(
+                f,
+                "JSON for {type_name} is nested deeper than {max_depth} levels"
+            ),
+            DieselJsonError::NotUtf8 {
+                type_name,
+                valid_up_to,
+            } => write!(
+                f,
+                "JSON text for {type_name} is not valid UTF-8 after {valid_up_to} bytes"
+            ),
+            DieselJsonError::NotAnObject { type_name } => {
+                write!(f, "Expected a JSON object for {type_name}")
+            }
+            DieselJsonError::Invalid { type_name, message } => {
+                write!(f, "Invalid {type_name}: {message}")
+            }
+            DieselJsonError::Rejected { type_name, message } => {
+                write!(f, "Refusing to write {type_name}: {message}")
+            }
+            DieselJsonError::TooLarge {
+                type_name,
+                size,
+                max_bytes,
+            } => write!(
+                f,
+                "Refusing to write {type_name} of {size} bytes, more than max_bytes = {max_bytes}"
+            ),
+            DieselJsonError::InvalidOutput { type_name, source } => write!(
+                f,
+                "Refusing to write {type_name}, it serialized to invalid JSON: {source}"
+            ),
+            DieselJsonError::Migrate {
+                type_name,
+                schema_version,
+                message,
+            } => write!(
+                f,
+                "Cannot migrate {type_name} from schema version {schema_version}: {message}"
+            ),
+            DieselJsonError::UnknownDiscriminant {
+                type_name,
+                discriminant,
+            } => write!(f, "Unknown discriminant {discriminant} for {type_name}"),
+            DieselJsonError::UnexpectedPayload {
+                type_name,
+                discriminant,
+            } => write!(
+                f,
+                "Unexpected payload after the discriminant {discriminant} of a unit variant of {type_name}"
+            ),
+            DieselJsonError::UnexpectedNull { type_name } => write!(
+                f,
+                "Unexpected NULL for {type_name}, load it as an Option to read NULLs"
+            ),
+            DieselJsonError::UnknownFormat {
+                type_name,
+                tag: Some(tag),
+            } => write!(f, "Unknown format tag {tag} for {type_name}"),
+            DieselJsonError::UnknownFormat {
+                type_name,
+                tag: None,
+            } => write!(f, "Empty value for {type_name}, without a format tag"),
+            DieselJsonError::UnsupportedFormat { type_name, format } => {
+                write!(f, "Cannot read {format} for {type_name}")
+            }
+            DieselJsonError::TruncatedChecksum => write!(f, "Value ends within its checksum"),
+            DieselJsonError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "Checksum mismatch: stored {stored:08x}, computed {computed:08x}"
+            ),
+            DieselJsonError::TruncatedRecord { needed, available } => write!(
+                f,
+                "Length-prefixed record needs {needed} bytes, only {available} are left"
+            ),
+        }
+    }
+}
+
+impl Error for DieselJsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DieselJsonError::Deserialize { source, .. }
+            | DieselJsonError::InvalidOutput { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces the type name of a [`DieselJsonError`] with `type_name`, for
+/// `#[diesel_json(context = "...")]`, whose impls pass the type name followed
+/// by the context, e.g. `Settings in column users.settings`. Errors without a
+/// type name and other errors are returned as they are.
+pub fn with_context(
+    error: Box<dyn Error + Send + Sync>,
+    type_name: &'static str,
+) -> Box<dyn Error + Send + Sync> {
+    let mut error = match error.downcast::<DieselJsonError>() {
+        Ok(error) => error,
+        Err(error) => return error,
+    };
+    match &mut *error {
+        DieselJsonError::Deserialize {
+            type_name: name, ..
+        }
+        | DieselJsonError::TooDeep {
+            type_name: name, ..
+        }
+        | DieselJsonError::NotUtf8 {
+            type_name: name, ..
+        }
+        | DieselJsonError::NotAnObject { type_name: name }
+        | DieselJsonError::Invalid {
+            type_name: name, ..
+        }
+        | DieselJsonError::Rejected {
+            type_name: name, ..
+        }
+        | DieselJsonError::TooLarge {
+            type_name: name, ..
+        }
+        | DieselJsonError::InvalidOutput {
+            type_name: name, ..
+        }
+        | DieselJsonError::Migrate {
+            type_name: name, ..
+        }
+        | DieselJsonError::UnknownDiscriminant {
+            type_name: name, ..
+        }
+        | DieselJsonError::UnexpectedPayload {
+            type_name: name, ..
+        }
+        | DieselJsonError::UnexpectedNull { type_name: name }
+        | DieselJsonError::UnknownFormat {
+            type_name: name, ..
+        }
+        | DieselJsonError::UnsupportedFormat {
+            type_name: name, ..
+        } => *name = type_name,
+        _ => {}
+    }
+    error
+}
@@ -0,0 +1,138 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+
+thread_local! {
+    /// The strings read so far by the innermost `with_interner`, `None`
+    /// outside of one.
+    static INTERNER: RefCell<Option<HashSet<Arc<str>>>> = const { RefCell::new(None) };
+}
+
+/// Runs `read` with an interner for the [`Interned`] strings it deserializes,
+/// what `from_sql` does for `#[diesel_json(intern_strings)]`. Equal strings
+/// share one allocation, the interner itself is dropped when `read` returns.
+/// Nested calls use the interner of the outer one.
+pub fn with_interner<R>(read: impl FnOnce() -> R) -> R {
+    /// Drops the interner even if `read` panics.
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            INTERNER.with(|interner| *interner.borrow_mut() = None);
+        }
+    }
+
+    let outermost = INTERNER.with(|interner| {
+        let mut interner = interner.borrow_mut();
+        let outermost = interner.is_none();
+        if outermost {
+            *interner = Some(HashSet::new());
+        }
+        outermost
+    });
+    let _reset = outermost.then_some(Reset);
+    read()
+}
+
+/// An immutable string that is deserialized through the interner of
+/// [`with_interner`], so that equal strings of a value read with
+/// `#[diesel_json(intern_strings)]` share one allocation. Outside of it each
+/// string is allocated on its own, like a `String`. Map keys and values can
+/// be `Interned`, it hashes and compares like the `str` it derefs to.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interned(Arc<str>);
+
+impl Interned {
+    /// Whether `self` and `other` share their allocation.
+    pub fn ptr_eq(&self, other: &Interned) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Interned {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Interned {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(value: &str) -> Self {
+        Interned(Arc::from(value))
+    }
+}
+
+impl fmt::Debug for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(InternedVisitor)
+    }
+}
+
+struct InternedVisitor;
+
+impl Visitor<'_> for InternedVisitor {
+    type Value = Interned;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    // parsers hand out unescaped strings borrowed from the input, only the
+    // first of equal strings is allocated
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Interned, E> {
+        Ok(intern(value))
+    }
+}
+
+/// `value` from the interner, added to it if it is new.
+fn intern(value: &str) -> Interned {
+    INTERNER.with(|interner| {
+        let mut interner = interner.borrow_mut();
+        let Some(interner) = interner.as_mut() else {
+            return Interned::from(value);
+        };
+        if let Some(interned) = interner.get(value) {
+            return Interned(Arc::clone(interned));
+        }
+        let interned: Arc<str> = Arc::from(value);
+        interner.insert(Arc::clone(&interned));
+        Interned(interned)
+    })
+}
@@ -0,0 +1,314 @@
+use std::io::Write;
+
+use diesel::deserialize;
+#[cfg(feature = "postgres")]
+use diesel::pg::PgValue;
+use diesel::serialize::{self, IsNull};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::DieselJsonError;
+
+/// The format version byte Postgres puts in front of the JSON text of a JSONB
+/// value, the only version there is so far. The derive writes and expects it
+/// unless `#[diesel_json(version = N)]` says otherwise.
+pub const JSONB_VERSION: u8 = 1;
+
+/// Writes `value` the way Postgres expects a JSONB parameter: the format
+/// version byte followed by the JSON text. This is what the derive's
+/// `ToSql<Jsonb, Pg>` does with the default options, for hand-written impls,
+/// e.g. `to_jsonb(&self, out)` in `to_sql`.
+pub fn to_jsonb<T, W>(value: &T, mut out: W) -> serialize::Result
+where
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    out.write_all(&[JSONB_VERSION])?;
+    serde_json::to_writer(out, value)?;
+    Ok(IsNull::No)
+}
+
+/// Reads a JSONB value as written by [`to_jsonb`], the counterpart of the
+/// derive's `FromSql<Jsonb, Pg>` with the default options. Fails with a boxed
+/// [`DieselJsonError`], e.g. `from_jsonb(bytes.as_bytes())` in `from_sql`.
+pub fn from_jsonb<T>(bytes: &[u8]) -> deserialize::Result<T>
+where
+    T: DeserializeOwned,
+{
+    from_json(jsonb_payload(bytes, Some(JSONB_VERSION))?)
+}
+
+/// [`from_jsonb`] for the value diesel hands to `FromSql<Jsonb, Pg>`, which
+/// is all the derive's impl does with the default options, e.g.
+/// `jsonb_from_pg_value(bytes)` in `from_sql`.
+#[cfg(feature = "postgres")]
+pub fn jsonb_from_pg_value<T>(value: PgValue<'_>) -> deserialize::Result<T>
+where
+    T: DeserializeOwned,
+{
+    from_jsonb(value.as_bytes())
+}
+
+/// Reads plain JSON, without a version byte, like the derive's impls for
+/// `Json`, `Text` and `Binary` columns and for the other backends do.
+pub fn from_json<T>(bytes: &[u8]) -> deserialize::Result<T>
+where
+    T: DeserializeOwned,
+{
+    #[cfg(feature = "arbitrary-precision")]
+    let value = serde_json::from_slice(bytes).or_else(|error| {
+        // see the `arbitrary-precision` feature, a flattened float fails
+        // to decode directly
+        if !error.is_data() {
+            return Err(error);
+        }
+        serde_json::from_slice::<serde_json::Value>(bytes)
+            .and_then(serde_json::from_value)
+            .map_err(|_| error)
+    });
+    #[cfg(not(feature = "arbitrary-precision"))]
+    let value = serde_json::from_slice(bytes);
+    value.map_err(|e| {
+        DieselJsonError::Deserialize {
+            type_name: short_type_name::<T>(),
+            format: "Json",
+            source: e.into(),
+        }
+        .into()
+    })
+}
+
+/// Reads the first record of a log of length-prefixed JSONB values: a
+/// big-endian `u32` length, then that many bytes of a JSONB value as written
+/// by [`to_jsonb`]. Returns the value and the bytes the record took, its
+/// length included, so that the next record starts at `&buf[consumed..]`. A
+/// log that ends within a record is [`DieselJsonError::TruncatedRecord`].
+pub fn read_length_prefixed<T>(buf: &[u8]) -> deserialize::Result<(T, usize)>
+where
+    T: DeserializeOwned,
+{
+    let (length, rest) = buf
+        .split_first_chunk::<4>()
+        .ok_or(DieselJsonError::TruncatedRecord {
+            needed: 4,
+            available: buf.len(),
+        })?;
+    let length = u32::from_be_bytes(*length) as usize;
+    let record = rest.get(..length).ok_or(DieselJsonError::TruncatedRecord {
+        needed: length,
+        available: rest.len(),
+    })?;
+    Ok((from_jsonb(record)?, 4 + length))
+}
+
+/// The JSON text of a JSONB value, i.e. the bytes after the format version
+/// byte. With `Some(version)` other versions are rejected, with `None` any
+/// version is accepted. A version byte without anything after it is
+/// [`DieselJsonError::MissingPayload`].
+pub fn jsonb_payload(bytes: &[u8], version: Option<u8>) -> Result<&[u8], DieselJsonError> {
+    let (&found, payload) = bytes.split_first().ok_or(DieselJsonError::Empty)?;
+    match version {
+        Some(version) if found != version => Err(DieselJsonError::UnsupportedVersion(found)),
+        _ if payload.is_empty() => Err(DieselJsonError::MissingPayload),
+        _ => Ok(payload),
+    }
+}
+
+/// Whether a JSONB value is JSON without the version byte, for
+/// `#[diesel_json(detect_version_byte)]`: its first byte could start a JSON
+/// value (`{`, `[`, `"`, `-`, a digit, the `t`, `f` or `n` of a literal, or
+/// whitespace) and isn't `version`. The version bytes in use are not among
+/// these, but a version byte that is, e.g. a future version 0x30, is taken
+/// for the digit 0 instead of being rejected.
+pub fn lacks_version_byte(bytes: &[u8], version: Option<u8>) -> bool {
+    match bytes.first() {
+        Some(&first) if Some(first) != version => matches!(
+            first,
+            b'{' | b'[' | b'"' | b'-' | b'0'
+                ..=b'9' | b't' | b'f' | b'n' | b' ' | b'\t' | b'\n' | b'\r'
+        ),
+        _ => false,
+    }
+}
+
+/// The discriminant and the JSON after it of a value written with
+/// `#[diesel_json(enum_discriminant)]`: the version byte, one byte for the
+/// variant and the JSON of its fields, nothing for unit variants.
+pub fn discriminant_payload(bytes: &[u8], version: u8) -> Result<(u8, &[u8]), DieselJsonError> {
+    let (&discriminant, payload) = jsonb_payload(bytes, Some(version))?
+        .split_first()
+        .expect("jsonb_payload rejects a value without payload");
+    Ok((discriminant, payload))
+}
+
+/// Checks that the value of a `Json` or `Text` column is UTF-8, as its
+/// column type promises. Without the check, bytes that are not would fail
+/// with a parser error about the place they are at, or, for SQLite, which
+/// replaces them when reading text, not fail at all.
+pub fn check_utf8(bytes: &[u8], type_name: &'static str) -> Result<(), DieselJsonError> {
+    std::str::from_utf8(bytes)
+        .map(|_| ())
+        .map_err(|e| DieselJsonError::NotUtf8 {
+            type_name,
+            valid_up_to: e.valid_up_to(),
+        })
+}
+
+/// Checks that `json`, the bytes a `validate_on_write` type serialized to, is
+/// one JSON value. serde_json's serializer doesn't check what a `formatter`
+/// writes.
+pub fn validate_json(json: &[u8], type_name: &'static str) -> Result<(), DieselJsonError> {
+    serde_json::from_slice::<serde::de::IgnoredAny>(json)
+        .map(|_| ())
+        .map_err(|e| DieselJsonError::InvalidOutput {
+            type_name,
+            source: Box::new(e),
+        })
+}
+
+/// Checks that `json` nests arrays and objects at most `max_depth` levels
+/// deep, without parsing it, for `#[diesel_json(max_depth = N)]`. serde_json
+/// stops at 128 levels itself, which is still a lot of recursion for a
+/// service reading values it didn't write. Malformed JSON is left to the
+/// parser.
+pub fn check_depth(
+    json: &[u8],
+    max_depth: usize,
+    type_name: &'static str,
+) -> Result<(), DieselJsonError> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in json {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(DieselJsonError::TooDeep {
+                        type_name,
+                        max_depth,
+                    });
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether `json` is the JSON `sentinel`, for
+/// `#[diesel_json(null_sentinel = "...")]`. Whitespace outside of strings is
+/// ignored, so the `{ }` of a `Json` column is `{}` too, other spellings of
+/// the same value, e.g. with escapes or reordered keys, are not.
+pub fn is_null_sentinel(json: &[u8], sentinel: &str) -> bool {
+    significant_bytes(json).eq(significant_bytes(sentinel.as_bytes()))
+}
+
+/// The bytes of `json` without the whitespace between its tokens.
+fn significant_bytes(json: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    let mut in_string = false;
+    let mut escaped = false;
+    json.iter().copied().filter(move |&byte| {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            return true;
+        }
+        in_string = byte == b'"';
+        !byte.is_ascii_whitespace()
+    })
+}
+
+/// Reads JSON that may lack fields of `T`, for `#[diesel_json(lenient)]`:
+/// the JSON is parsed into a `serde_json::Value` and laid over `T::default()`,
+/// so every field that is missing, in nested objects too, keeps its default
+/// value. Values that are no objects are deserialized as they are.
+pub fn from_lenient_json<T>(bytes: &[u8]) -> deserialize::Result<T>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let invalid_json = |e: serde_json::Error| DieselJsonError::Deserialize {
+        type_name: short_type_name::<T>(),
+        format: "Json",
+        source: e.into(),
+    };
+    let stored: Value = serde_json::from_slice(bytes).map_err(invalid_json)?;
+    let value = if stored.is_object() {
+        let mut value = serde_json::to_value(T::default()).map_err(invalid_json)?;
+        merge(&mut value, stored);
+        value
+    } else {
+        stored
+    };
+    Ok(serde_json::from_value(value).map_err(invalid_json)?)
+}
+
+/// Writes the entries of `stored` into `value`, merging objects that are in
+/// both.
+fn merge(value: &mut Value, stored: Value) {
+    match (value, stored) {
+        (Value::Object(value), Value::Object(stored)) => {
+            for (key, stored) in stored {
+                match value.get_mut(&key) {
+                    Some(value) => merge(value, stored),
+                    None => {
+                        value.insert(key, stored);
+                    }
+                }
+            }
+        }
+        (value, stored) => *value = stored,
+    }
+}
+
+/// `value` as a `serde_json::Value` with the keys of every object in order,
+/// whether or not serde_json's `preserve_order` feature is enabled. This is
+/// what `#[diesel_json(sort_keys)]` writes.
+pub fn to_sorted_value<T>(value: &T) -> serde_json::Result<Value>
+where
+    T: Serialize + ?Sized,
+{
+    let mut value = serde_json::to_value(value)?;
+    sort_objects(&mut value);
+    Ok(value)
+}
+
+/// Reinserts the entries of every object in `value` in the order of their keys.
+fn sort_objects(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in entries {
+                sort_objects(&mut value);
+                map.insert(key, value);
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_objects),
+        _ => {}
+    }
+}
+
+/// The name of `T` without its path and generic arguments, like the derive
+/// puts into errors, e.g. `Wrapper` for `my_crate::Wrapper<my_crate::Body>`.
+pub(crate) fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -0,0 +1,109 @@
+use diesel::deserialize;
+use serde::de::{DeserializeOwned, Visitor};
+use serde::Deserializer;
+use serde_json::Value;
+
+use crate::jsonb::short_type_name;
+use crate::DieselJsonError;
+
+/// Reads JSON into `T` without the entries of the top-level object that `T`
+/// has no field for, for `#[diesel_json(allow_unknown_fields)]`. The JSON is
+/// parsed into a `serde_json::Value` first, and the names of the fields are
+/// the ones serde's derive passes to `deserialize_struct`, renames and aliases
+/// included. So a `#[serde(deny_unknown_fields)]` struct reads values with
+/// fields a newer version added, missing fields still fail. Nested structs
+/// are read as they are.
+pub fn from_json_allowing_unknown_fields<T>(bytes: &[u8]) -> deserialize::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let invalid_json = |e: serde_json::Error| DieselJsonError::Deserialize {
+        type_name: short_type_name::<T>(),
+        format: "Json",
+        source: e.into(),
+    };
+    let value: Value = serde_json::from_slice(bytes).map_err(invalid_json)?;
+    Ok(T::deserialize(KnownFields(value)).map_err(invalid_json)?)
+}
+
+/// A `Value` that drops the unknown entries of an object deserialized as a
+/// struct, and deserializes like the `Value` otherwise.
+struct KnownFields(Value);
+
+macro_rules! forward {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.0.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for KnownFields {
+    type Error = serde_json::Error;
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut value = self.0;
+        if let Value::Object(map) = &mut value {
+            map.retain(|key, _| fields.contains(&key.as_str()));
+        }
+        value.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_option deserialize_unit deserialize_seq deserialize_map
+        deserialize_identifier deserialize_ignored_any
+    }
+}
@@ -17,7 +17,7 @@
 //!
 //! which is in Rust can be represented as as (does not compile!):
 //!
-//! ```rust
+//! ```rust,ignore
 //! #[derive(Debug, Queryable, Identifiable, Insertable, AsChangeset, Selectable)]
 //! #[diesel(table_name = crate::schema::foo)]
 //! #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -34,7 +34,7 @@
 //!
 //! In order to make `Bar` be represented as a jsonb blob you will need to implement the `diesel::deserialize::FromSql` and `diesel::deserialize::FromSql` traits, e.g. like this:
 //!
-//! ```rust
+//! ```rust,ignore
 //! impl ToSql<Jsonb, Pg> for Foo {
 //!     fn to_sql<'b>(&'b self, out: &mut serialize::Output<'b, '_, Pg>) -> serialize::Result {
 //!         out.write_all(&[1])?;
@@ -70,54 +70,711 @@
 //! }
 //! ```
 //!
+//! The bytes are the same as those of the hand-written impls above and of the
+//! `Json<T>` wrapper of the `diesel_json` crate, so columns written with either are
+//! read as they are when moving to the derive, and the other way around.
+//!
+//! The `#[diesel_jsonb]` attribute adds the `AsExpression` and `FromSqlRow`
+//! derives and the `sql_type` attribute as well:
+//!
+//! ```rust
+//! use diesel_json_derive::diesel_jsonb;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[diesel_jsonb]
+//! #[derive(Debug, Serialize, Deserialize)]
+//! struct Bar {
+//!     x: i32,
+//! }
+//! ```
+//!
+//! The sql type is taken from the `#[diesel(sql_type = ...)]` attribute. `Jsonb`,
+//! `Json`, `Text` and `Binary` are supported, all but `Jsonb` are written without
+//! the Postgres JSONB version byte. `Text` is for databases storing JSON in plain
+//! text columns, e.g. older SQLite files, `Binary` for `bytea` columns read by
+//! something that expects the plain JSON bytes. Any other sql type is a compile
+//! error pointing at the attribute. Values of `Json` and `Text` columns that are
+//! not valid UTF-8 fail with `DieselJsonError::NotUtf8` before they are parsed,
+//! also for SQLite, which would read them with replacement characters.
+//!
+//! These are diesel's own sql types: a type may derive diesel's `SqlType` as
+//! well, the derives implement different traits, but the impls are not for a sql
+//! type of your own. A custom `#[derive(SqlType)]` marker for `jsonb` columns
+//! named `Jsonb` is a mismatched types error at the attribute rather than impls
+//! for it, implement `FromSql` and `ToSql` for such a marker by hand.
+//!
+//! `Queryable` and `Selectable` models take the sql types of their fields from the
+//! table's columns and need nothing more. `QueryableByName` models of a
+//! `sql_query` have no table to take them from, `#[diesel_jsonb_model]` adds the
+//! `#[diesel(sql_type = Jsonb)]` of the fields marked with `#[diesel_jsonb]`,
+//! `Nullable<Jsonb>` for `Option` fields:
+//!
+//! ```rust,ignore
+//! use diesel::sql_types::Text;
+//! use diesel::QueryableByName;
+//! use diesel_json_derive::{diesel_jsonb, diesel_jsonb_model};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[diesel_jsonb]
+//! #[derive(Debug, Serialize, Deserialize)]
+//! struct Bar {
+//!     x: i32,
+//! }
+//!
+//! #[diesel_jsonb_model]
+//! #[derive(QueryableByName)]
+//! struct Row {
+//!     #[diesel(sql_type = Text)]
+//!     id: String,
+//!     #[diesel_jsonb]
+//!     bar: Bar,
+//!     #[diesel_jsonb(sql_type = Json)]
+//!     maybe_bar: Option<Bar>,
+//! }
+//! ```
+//!
+//! Attribute macros can't be put on fields, so it is the attribute on the struct,
+//! above its derives, that reads the ones on the fields. Fields that aren't marked
+//! are left as they are, with their own `sql_type` or that of the column of the
+//! model's `table_name`. A marked field with a `sql_type` of its own is a compile
+//! error. The sql types are named as `::diesel::sql_types::...`.
+//!
+//! A type can be stored in columns of several sql types, e.g. `JSON` and `JSONB`
+//! columns of the same table. Each `#[diesel(sql_type = ...)]` attribute gets its own
+//! impls, diesel's `AsExpression` derive reads all of them as well.
+//!
+//! The value is stored as whatever serde makes of it, so any type serde can
+//! round-trip works: tuple structs, newtypes, unit structs (stored as `null`,
+//! while `struct Empty {}` is `{}` and `struct Empty();` is `[]`) and
+//! enums in all of serde's representations, e.g. `#[serde(tag = "type")]`. Bare
+//! scalars are fine too, a `#[serde(transparent)]` newtype of a `u32` is the JSONB
+//! number `42`.
+//! Container attributes such as `#[serde(rename_all = "camelCase")]` or
+//! `#[serde(deny_unknown_fields)]` are respected the same way, the derive only calls
+//! serde. serde's message, e.g. for an unknown field, ends up in the `Deserialize`
+//! error.
+//! Field attributes apply as usual, and a field that is skipped when writing, e.g.
+//! with `#[serde(skip_serializing_if = "Option::is_none")]`, also needs
+//! `#[serde(default)]`, or reading the value back fails with a missing field error.
+//! Paths like the one of `#[serde(default = "defaults::region")]` resolve in the
+//! module of the type just as without the derive, serde's impls are next to the
+//! type and not inside the derive's `const _` block.
+//!
+//! To store a different shape than the type in memory, convert through a proxy
+//! with `#[serde(into = "Dto", try_from = "Dto")]`. The generated bounds are
+//! just `Serialize` and `DeserializeOwned`, which serde implements for these
+//! types, `into` needs the type to be `Clone`. The message of a failed `try_from`
+//! ends up in the `Deserialize` error.
+//!
+//! `u128` and `i128` fields are written as plain numbers, which Postgres keeps
+//! exactly, and read back as they were, except with the `simd-json` feature, which
+//! fails on numbers beyond 64 bits. `sort_keys` fails for such numbers unless the
+//! `arbitrary-precision` feature is enabled, and a `serde_json::Value` field reads
+//! them as `f64` without it. Readers limited to `f64`, such as JavaScript, lose
+//! precision too, so when the JSON is read elsewhere, store them as strings with a
+//! `#[serde(with = "...")]` module going through `Display` and `FromStr`.
+//!
+//! Nullable columns are loaded as `Option<Bar>`, SQL `NULL` is handled by diesel's
+//! `Nullable` impls and never reaches the generated `from_sql`. To load `Bar`
+//! itself from a nullable column, use `#[diesel_json(nullable)]`.
+//!
+//! To store `None` as the JSON `null` in a `NOT NULL` column instead, wrap the
+//! option, e.g. `struct MaybeBar(Option<Bar>)` with
+//! `#[diesel_json(transparent_value)]`. The impls can't be generated for
+//! `Option<Bar>` itself, Rust's orphan rule only allows diesel to implement its
+//! traits for `Option`.
+//!
+//! `JSONB[]` columns are loaded as `Vec<Bar>`, `Array<Nullable<Jsonb>>` as
+//! `Vec<Option<Bar>>`, and bound from them as well. diesel's `Array` impls for `Vec`
+//! read and write each element with the impls of `Bar`, version byte and errors
+//! included, so the derive generates nothing for arrays, and the orphan rule
+//! wouldn't let it implement diesel's traits for `Vec<Bar>` anyway.
+//!
+//! The `FromSqlRow` derive implements `Queryable` with `Row = Self`, so a value
+//! can be the whole query result, e.g. `foo::table.select(foo::bar).load::<Bar>(conn)`.
+//!
+//! Values can be bound by reference, e.g. in a changeset with a `bar: &'a Bar`
+//! field: diesel implements `ToSql` for `&T` whenever `T` implements it, so the
+//! derive doesn't generate impls for references.
+//!
+//! To change some of the fields of a stored value without reading it first,
+//! `jsonb_merge(column, &patch)` is Postgres' `column || $1`: the keys of the
+//! object `patch` serializes to replace those of the stored object, e.g.
+//! `update(foo.find(id)).set(bar.eq(jsonb_merge(bar, &patch)))` with a
+//! `serialize_only` patch type whose `None` fields are skipped. Only top-level
+//! keys are merged, a nested object in the patch replaces the stored one.
+//!
+//! The generated impls are wrapped in an anonymous `const _: () = { ... };` block.
+//! They don't add any named items, so there is no module to name, document or make
+//! visible, and nothing of it shows up in rustdoc. The block sits next to the
+//! definition and names the type as it is defined there, so re-exports don't
+//! matter: `pub use inner::Bar as PublicBar;` is the same type with the same impls,
+//! and its errors name it `Bar`, the name the derive sees.
+//!
+//! ### Backends
+//!
+//! The backends for which impls are generated are selected with cargo features:
+//!
+//! - `postgres` (default): `Jsonb` values are written with the leading JSONB
+//!   format version byte.
+//! - `sqlite`: `Jsonb` values are stored in SQLite's binary JSONB format, as its
+//!   `jsonb()` returns them and diesel's `serde_json::Value` writes them, so
+//!   SQLite's JSON functions (3.45 and newer) and diesel read what the derive
+//!   writes and the other way round. The JSON5 `jsonb()` keeps, e.g. hexadecimal
+//!   integers, is read the way SQLite's `json()` turns it into JSON. `Json` and
+//!   `Text` values are bound as text, which SQLite's JSON functions such as
+//!   `json_extract` read as well, without a version prefix. Blobs of JSON text,
+//!   which earlier versions of the derive stored for `Jsonb`, fail to read with
+//!   `DieselJsonError::Deserialize`, `UPDATE t SET c = jsonb(CAST(c AS TEXT))`
+//!   converts them.
+//! - `mysql`: the raw JSON bytes are written and read without a version prefix.
+//!   diesel has no `Jsonb` for MySQL, its `JSON` columns are
+//!   `#[diesel(sql_type = Json)]`. No MySQL impls are generated for `Jsonb`
+//!   types, list `Json` in `sql_types` for a type shared with Postgres.
+//!
+//! With the `simd-json` feature values are parsed with
+//! [simd-json](https://crates.io/crates/simd-json) instead of serde_json, which is
+//! faster for large payloads at the cost of copying the value first. The crate using
+//! the derive needs a `simd-json` dependency then. Serialization still uses
+//! serde_json.
+//!
+//! The `sonic-rs` feature parses and writes values with
+//! [sonic-rs](https://crates.io/crates/sonic-rs) instead, except for `pretty` and
+//! `formatter`, which are serde_json's. The stored bytes stay the same, version byte
+//! included, and the crate using the derive needs a `sonic-rs` dependency. It can't
+//! be combined with `simd-json`.
+//!
+//! Parts of a large value that are rarely needed can stay unparsed in a
+//! `Box<serde_json::value::RawValue>` field, with serde_json's `raw_value`
+//! feature. `from_sql` then only copies their JSON text, and
+//! `serde_json::from_str(field.get())` parses it when it is needed. For `Jsonb`
+//! that text is the one Postgres normalized, with its own whitespace and key
+//! order. Only serde_json can read and write a `RawValue`, so the derive ignores
+//! the `simd-json` and `sonic-rs` features for types with a field naming one.
+//! Only the types of the fields themselves are looked at: a `RawValue` inside
+//! another type (e.g. a `Vec<Report>` of a type that has one), behind an alias
+//! or in a generic parameter isn't seen, and neither are the fields of remote
+//! types for `impl_diesel_jsonb!`. `#[diesel_json(raw_value)]` says the type
+//! holds one, so it is read and written with serde_json whatever the features.
+//!
+//! The `arbitrary-precision` feature enables serde_json's `arbitrary_precision`,
+//! so numbers that don't fit an `f64` or `u64`, e.g. in a `serde_json::Number`
+//! field, round-trip exactly. Cargo unifies the features of all users of
+//! serde_json, so this changes how numbers are parsed everywhere in the binary,
+//! and it is active as soon as any crate enables it.
+//!
+//! With `arbitrary_precision` serde_json hands numbers that are not integers to
+//! serde as a map holding their text, which is what `#[serde(flatten)]` fields,
+//! internally tagged and untagged enums get to see, e.g. an `f64` in a flattened
+//! struct fails with "invalid type: map, expected f64". With this crate's feature
+//! the derive decodes values that fail like this once more from a
+//! `serde_json::Value`, which hands such numbers out as `f64` where that keeps
+//! their digits. If that fails as well the first error is reported, e.g. a float
+//! for an integer field of a flattened struct still reads "invalid type: map".
+//! Without `arbitrary_precision` flattened fields round-trip like any other.
+//!
+//! serde_json's default float parsing is fast but can be off by one in the last
+//! bit, e.g. `1.868356550599746e87` reads back as `1.8683565505997458e87`. The
+//! `float-roundtrip` feature enables its `float_roundtrip`, which parses every
+//! `f64` back exactly as it was written, at some cost in speed. Like
+//! `arbitrary-precision` it applies to every user of serde_json in the binary.
+//!
+//! JSON has no `NaN` or infinities, and serde_json writes them as `null` instead
+//! of failing. `to_sql` succeeds for a value with a computed `f64` that went
+//! `NaN`, reading it back fails with `invalid type: null, expected f64`. Types
+//! that can't rule it out can reject such values with `pre_serialize`, or keep
+//! the field an `Option<f64>`.
+//!
+//! With the `tracing` feature every value `from_sql` fails to read is logged with
+//! `tracing::warn!` before the error is returned, with the type name, the error
+//! and a hex preview of the first 32 bytes of the value, version byte included.
+//! Values `default_on_error` replaces with the default are logged as well. The
+//! crate using the derive doesn't need a `tracing` dependency of its own.
+//!
+//! The `test-util` feature adds the `test_util` module, for unit tests of
+//! `from_sql` and `to_sql` without a database: `jsonb_bytes` returns the bytes the
+//! derive writes with the default options, `jsonb_value` and `json_value` wrap
+//! bytes into the `PgValue` diesel hands to `from_sql`, and `to_sql_bytes::<ST, _>`
+//! returns the bytes a value is bound as. It enables diesel's
+//! `i-implement-a-third-party-backend-and-opt-into-breaking-changes` feature,
+//! which `PgValue::new` needs, so it is best kept to `dev-dependencies`.
+//!
+//! ### Attributes
+//!
+//! The generated code can be configured with `#[diesel_json(...)]`:
+//!
+//! - `sql_types(Json, Jsonb)`: the same as one `#[diesel(sql_type = ...)]` attribute
+//!   per type. With `#[diesel_jsonb]` those attributes are added for `AsExpression`,
+//!   with the derive they still have to be written for it.
+//! - `version = N`: the JSONB format version byte that is written and accepted
+//!   by the Postgres impls. Defaults to `JSONB_VERSION`, which is 1.
+//! - `accept_any_version` or `skip_version_check`: don't check the version byte
+//!   on read, just skip it. No comparison is left in the generated code, only the
+//!   check that there is a byte and JSON after it, e.g. for reading rows written
+//!   by another version in a trusted pipeline.
+//! - `version_check = "path::to::fn"`: a `fn(u8) -> bool` deciding which version
+//!   bytes are accepted on read, e.g. both the old and the new one during a
+//!   migration. The `version` is still the one written.
+//! - `unknown_version = "default"`: read JSONB values of a version that isn't
+//!   accepted, by `version` or `version_check`, as `Default::default()` instead
+//!   of failing with `DieselJsonError::UnsupportedVersion`, for readers that
+//!   should degrade gracefully on rows of a newer writer. Values of an accepted
+//!   version that can't be read still fail. The type has to implement `Default`.
+//!   `unknown_version = "error"` is the default. Can't be combined with
+//!   `accept_any_version`.
+//! - `detect_version_byte`: read values whose first byte could start JSON (`{`,
+//!   `[`, `"`, `-`, a digit, `t`, `f`, `n` or whitespace) as JSON without a
+//!   version byte, for data copied from tools that left it out. Postgres always
+//!   sends it for JSONB columns, however the value got there. Values are still
+//!   written with it. This is a guess: a value of another version whose byte
+//!   looks like JSON, e.g. a future version 0x30 (`0`), is parsed as JSON instead
+//!   of being rejected, and a `version` that looks like JSON is a compile error.
+//! - `schema_version = N` and `migrate = "path::to::fn"`: version the shape of the
+//!   value, for changes serde attributes can't cover. The value is written as
+//!   `{"schema_version":N,"data":...}`, in the JSON since Postgres accepts
+//!   nothing else before it. Values of an older version are read with `migrate`,
+//!   a `fn(u8, serde_json::Value) -> Result<T, String>` getting the version and
+//!   the data. JSON without the envelope, e.g. written before the type had a
+//!   schema version, is version 0. Not for `transparent_value`, `streaming` or
+//!   `ignore_trailing` types.
+//! - `crate_path = path::to::diesel_json_derive`: the path this crate is reachable
+//!   at, the generated code refers to it for `DieselJsonError`. Defaults to
+//!   `::diesel_json_derive`.
+//! - `diesel_path = path::to::diesel`: the path diesel is reachable at, for crates
+//!   that re-export it. Defaults to `::diesel`.
+//! - `serde_json_path = path::to::serde_json`: the same for serde_json. Defaults to
+//!   `::serde_json`. In a crate on the 2015 edition the defaults need the
+//!   `extern crate` items of the three crates (and of serde) in the crate root,
+//!   the types deriving can be in any module.
+//! - `size_hint = N`: serialize into a buffer with room for `N` bytes before
+//!   writing it out, for values whose size is known to be large.
+//! - `max_bytes = N`: fail `to_sql` with `DieselJsonError::TooLarge` for values of
+//!   more than `N` bytes, the version byte and checksum included, e.g. to keep a
+//!   `Vec` that a bug let grow out of the database. The value is serialized into
+//!   a buffer first and nothing is written if it is too large. Can't be combined
+//!   with `enum_discriminant`.
+//! - `validate_on_write`: parse the JSON a value serialized to before writing it,
+//!   failing `to_sql` with `DieselJsonError::InvalidOutput` if it isn't JSON.
+//!   serde_json's own serializer always writes JSON, a `formatter` or a
+//!   hand-written `Serialize` impl may not, and only Postgres' `Json` and `Jsonb`
+//!   columns check what they are sent. The value is buffered like with
+//!   `max_bytes`. JSON only, can't be combined with `enum_discriminant`.
+//! - `atomic_write`: serialize the whole value into a buffer and only write the
+//!   version byte and the buffer once that succeeded, so a failing `Serialize`
+//!   impl leaves nothing behind in the output. Diesel's own bind collectors
+//!   discard the output of a failed `to_sql` anyway, this matters for custom
+//!   ones.
+//! - `reuse_buffer`: serialize into a buffer of the current thread that is kept for
+//!   the next value and copy it into the output from there, for batch inserts of
+//!   many values. The output is then allocated once at its full size instead of
+//!   growing, `examples/write_memory.rs` of the test crate counts 1 allocation per
+//!   bind instead of 7 for a value of 1 KB, and `benches/batch_insert.rs` binds an
+//!   insert of 1000 such rows about a fifth faster. Buffers grown beyond 1 MiB
+//!   are not kept. SQLite takes an owned `Vec` for every value, so nothing changes
+//!   there. Can't be combined with `atomic_write`, `size_hint` or `checksum`.
+//! - `serialize_only` and `deserialize_only`: only generate `ToSql` (needing just
+//!   `Serialize`) or `FromSql` (needing just `DeserializeOwned`), for types that
+//!   are only ever written or only ever read. `#[diesel_jsonb]` then only adds
+//!   the `AsExpression` or the `FromSqlRow` derive.
+//! - `owned_read`: implement `FromSql` only for the type with `'static` for every
+//!   lifetime, e.g. `Doc<'static>`, while `ToSql` stays generic over them. This
+//!   suits types written from borrowed data whose `Deserialize` impl only
+//!   exists for the `'static` type. Can't be combined with `transparent_value`.
+//! - `raw_value`: read and write the type with serde_json whatever the parser
+//!   features, for a `RawValue` the derive doesn't see in the field types, see
+//!   above.
+//! - `serialize_bound = "..."` and `deserialize_bound = "..."`: where predicates
+//!   replacing the bounds the derive infers for generic types, e.g.
+//!   `serialize_bound = "T: Display"` instead of `Shown<T>: Serialize` for the
+//!   `ToSql` impl and `deserialize_bound = "T: FromStr"` instead of
+//!   `Shown<T>: DeserializeOwned` for `FromSql`, the way serde's own `bound`
+//!   works. `ToSql` still requires the type to be `Debug`. If serde's impls need
+//!   more than the predicates, the error points at the type.
+//! - `instantiate(Reading<u32>, Reading<String>)`: implement the traits only for
+//!   the listed instantiations of a generic type, each like
+//!   `impl_diesel_jsonb!(Reading<u32>)` would, instead of once for every
+//!   instantiation. The impls are concrete, so they need no bounds on the
+//!   parameters, and other instantiations, e.g. `Reading<bool>`, get none. Can't
+//!   be combined with `transparent_value`, `enum_discriminant`, `owned_read` or
+//!   the bounds above.
+//! - `nullable`: also implement `FromSql<Nullable<Jsonb>>` for the type itself, so
+//!   that it loads from a nullable column without an `Option`, e.g. in a
+//!   `Queryable` struct for rows known to have a value. A `NULL` then fails with
+//!   `DieselJsonError::UnexpectedNull`, `Option<Bar>` keeps reading it as `None`.
+//!   diesel's `AsExpression` derive already writes the type to nullable columns,
+//!   so there is nothing to add for `ToSql` and this can't be combined with
+//!   `serialize_only`.
+//! - `context = "users.settings"`: where the values are stored, named after the
+//!   type in the errors of `from_sql`, e.g. `Invalid Json for Settings in column
+//!   users.settings: ...`, for finding the failing column of wide rows in logs.
+//!   The `type_name` of the `DieselJsonError` is that text too, errors that name
+//!   no type, e.g. `UnsupportedVersion`, don't get it.
+//! - `format = "msgpack"` or `format = "cbor"`: store the value as MessagePack
+//!   (with `rmp_serde`) or CBOR (with `ciborium`) instead of JSON. The crate using
+//!   the derive has to depend on that crate. Needs the `messagepack` or `cbor`
+//!   feature and `#[diesel(sql_type = Binary)]`.
+//!   The `DIESEL_JSONB_DEFAULT_FORMAT` environment variable (`json`, `msgpack`
+//!   or `cbor`), when set while compiling, is the format of every type that is
+//!   only `Binary` and has no `format` of its own, e.g. from the `[env]` table of
+//!   a workspace's `.cargo/config.toml`. `enum_discriminant` types keep their
+//!   encoding, and `format = "json"` keeps a type on JSON. Cargo doesn't know
+//!   the derive reads the variable: changing it doesn't rebuild crates that
+//!   were already compiled (`cargo clean` them, or add
+//!   `println!("cargo:rerun-if-env-changed=DIESEL_JSONB_DEFAULT_FORMAT")` to
+//!   their build script), and settings that differ between machines build
+//!   different code from the same source.
+//! - `validate = "path::to::fn"`: a `fn(&T) -> Result<(), E>` with `E: Display`
+//!   that is called with every value read. An error is returned as the
+//!   deserialization error, so invalid but parseable JSON does not get past the
+//!   database layer.
+//! - `pre_serialize = "path::to::fn"`: the same for writing, a `fn(&T) ->
+//!   Result<(), E>` with `E: Display` that is called with every value before it is
+//!   serialized. An error fails `to_sql` with `DieselJsonError::Rejected` and
+//!   nothing is written, so invalid values in memory do not reach the database.
+//! - `streaming` or `reader`: parse with `serde_json::from_reader` instead of
+//!   `from_slice`. The reader copies strings through a scratch buffer instead of
+//!   working on the slice, which is slower for small values. diesel hands out the
+//!   whole value either way, so it doesn't lower the peak memory use. Also overrides
+//!   the `simd-json` and `sonic-rs` features.
+//! - `intern_strings`: read with an interner, so that the equal strings of the
+//!   `Interned` fields, map keys and values share one allocation, e.g. for
+//!   documents repeating the same keys in many records. A `String` owns its bytes,
+//!   so this takes `Interned` instead, an immutable `Arc<str>` that serializes
+//!   like a string and can be used like one. The interner lives while one value is
+//!   read, `with_interner` gives other code the same. For 10000 records of three
+//!   keys, `examples/intern_memory.rs` of the test crate keeps 4.4 MB instead of
+//!   6.2 MB in less than a third of the allocations.
+//! - `ignore_trailing`: read the first JSON value and ignore anything after it, for
+//!   rows written with stray bytes after the JSON. By default everything but
+//!   whitespace after the value is rejected. Like `streaming` it overrides the
+//!   `simd-json` and `sonic-rs` features.
+//! - `empty_as_default`: read an empty value, or a JSONB value that is just the
+//!   version byte, as `Default::default()` instead of failing with
+//!   `DieselJsonError::Empty`. A single byte of another version still fails with
+//!   `DieselJsonError::UnsupportedVersion`. The type has to implement `Default`.
+//! - `null_sentinel = "{}"`: read the given JSON as `Default::default()`, for
+//!   tables that store e.g. `{}` or `"null"` for no value instead of SQL `NULL`.
+//!   Whitespace outside of strings doesn't matter, the value has to be spelled
+//!   like the sentinel otherwise. The type has to implement `Default`, the
+//!   default is written as it serializes, not as the sentinel. JSON only.
+//! - `default_on_error`: read every value that cannot be read, corrupt JSON, a
+//!   wrong version byte or anything else, as `Default::default()`. This hides
+//!   broken data instead of reporting it, so only use it where a default is
+//!   really better than a failed query, e.g. for a batch job that must not stop
+//!   at one bad row. The type has to implement `Default`.
+//! - `lenient`: read rows written before fields were added to the type. The JSON is
+//!   parsed into a `serde_json::Value` and laid over the serialized
+//!   `Default::default()`, so every missing field, in nested objects too, keeps its
+//!   default value. The type has to implement `Default` and `Serialize`, and the
+//!   value is parsed by serde_json whatever the parser features. Without it, the
+//!   usual way is `#[serde(default)]` on every field added later, or on the whole
+//!   struct, which needs nothing from the derive and costs nothing.
+//! - `require_default`: check the `Default` impl the four options above and
+//!   `unknown_version = "default"` need in a function of its own, so that a type
+//!   without one fails with an error naming the type and `Default`, instead of
+//!   one about the bounds of the generated `FromSql` impl. For a generic type the
+//!   `FromSql` impl's other bounds have to imply `Default`, e.g. with
+//!   `deserialize_bound = "T: Default"`. Only with one of those options.
+//! - `allow_unknown_fields`: the other way around, read rows written by a newer
+//!   version with fields the type doesn't have, even if it is
+//!   `#[serde(deny_unknown_fields)]` for other readers. The JSON is parsed into a
+//!   `serde_json::Value` and the entries of the top-level object that are no
+//!   field of the struct, by the names serde's derive knows, aliases included,
+//!   are dropped before deserializing it. Nested structs are read as they are,
+//!   and fields that are missing still fail. Can't be combined with `lenient`,
+//!   `schema_version`, `streaming`, `ignore_trailing` or `enum_discriminant`, or
+//!   types with a `RawValue`.
+//! - `checksum = "crc32"`: write a CRC32 of the JSON in front of it and check it
+//!   on every read, failing with `DieselJsonError::ChecksumMismatch` for corrupted
+//!   values. Only for `#[diesel(sql_type = Binary)]`, the JSON types don't accept
+//!   the extra bytes. The value starts with a NUL byte, which JSON never does, so
+//!   rows written before the checksum was added are still read.
+//! - `compress = "gzip"`: store the JSON gzipped (with `flate2`) behind the byte
+//!   `TaggedFormat::GzipJson` names, for large values that compress well. Needs
+//!   the `compression` feature and `#[diesel(sql_type = Binary)]`, the JSON types
+//!   don't accept gzip. JSON never starts with that byte, so rows written before
+//!   compression was turned on are read as they are. Corrupt gzip fails with
+//!   `DieselJsonError::Deserialize`. Can't be combined with `checksum` or
+//!   `reuse_buffer`.
+//! - `tagged_format`: write a byte naming the format in front of the value, see
+//!   `TaggedFormat`: 1 for JSON, 2 for gzipped JSON, 3 for MessagePack and 4 for
+//!   CBOR. With `compress = "gzip"` JSON is written gzipped, behind 2. Reading
+//!   dispatches on the byte and reads every format the derive was built with
+//!   (gzipped JSON with the `compression` feature), so a column can hold values
+//!   written with different `format`s over time, and the crate using the derive
+//!   has to depend on the crates of all of them. A type's JSON options only apply
+//!   to its JSON values, gzipped or not.
+//!   Values in other formats fail with `DieselJsonError::UnsupportedFormat`.
+//!   JSON behind its tag is laid out like JSONB, so JSONB copied into the column
+//!   reads as it is. Only for `#[diesel(sql_type = Binary)]`, and can't be
+//!   combined with `checksum`, `enum_discriminant` or `empty_as_default`.
+//! - `enum_discriminant`: store an enum as the version byte, one byte for the
+//!   variant and the JSON of its fields, instead of the tag serde writes, e.g.
+//!   `\x01\x03[1,"a"]` for the fourth variant, `Created { id: 1, name: "a" }`.
+//!   A variant with one field stores its value, one with more an array of them,
+//!   a unit variant nothing. The discriminant is the index of the variant and the
+//!   fields are stored by position, so new variants have to go at the end and
+//!   the fields of a variant cannot change without a migration.
+//!   The enum needs no serde impls, only its fields. Only for
+//!   `#[diesel(sql_type = Binary)]` and enums of up to 256 variants.
+//! - `strict`: reject values that are not JSON objects with
+//!   `DieselJsonError::NotAnObject`, for columns that must not hold arrays or
+//!   scalars. Only the first byte of the JSON is checked, before it is parsed.
+//!   Unit structs and tuple structs of other than one field, which are never
+//!   objects, are a compile error unless they have `#[serde(...)]` attributes
+//!   that may change the shape.
+//! - `pretty`: write indented JSON, for columns that are read by hand. Only
+//!   meaningful for `Json`, Postgres normalizes `Jsonb` values anyway.
+//! - `formatter = "path::to::fn"`: a function returning the
+//!   `serde_json::ser::Formatter` the JSON is written with, e.g. one escaping
+//!   everything but ASCII. Like `pretty` only meaningful for `Json`.
+//! - `sort_keys`: write the keys of every object in order, so equal values give the
+//!   same bytes however their maps were built, e.g. for comparing or hashing stored
+//!   values. The value is converted to a `serde_json::Value` first. Postgres orders
+//!   the keys of `Jsonb` values itself.
+//! - `canonical`: write the canonical JSON of RFC 8785 (JCS) with
+//!   `to_canonical_writer`, e.g. for keying a cache on a hash of the bytes: keys in
+//!   the order of their UTF-16 code units, numbers as ECMAScript prints them (`1`
+//!   for `1.0`, `1e+21`) and no whitespace, so structurally equal values give the
+//!   same bytes whatever order their fields are declared or their maps built in.
+//!   Unlike JCS, integers a `u64` or `i64` holds are written with all their digits.
+//!   Only meaningful for `Json`, `Text` and `Binary` columns, Postgres rewrites
+//!   `Jsonb` values in its own form, and can't be combined with `pretty`,
+//!   `formatter` or `sort_keys`.
+//! - `max_depth = N`: reject JSON nesting arrays and objects more than `N` levels
+//!   deep with `DieselJsonError::TooDeep`, checked with a scan of the bytes before
+//!   parsing. serde_json and sonic-rs stop at a recursion limit of their own, but
+//!   simd-json overflows the stack on deeply nested input, so set this when reading
+//!   values from untrusted sources with the `simd-json` feature.
+//! - `generic_backend`: instead of one impl per backend feature, generate a
+//!   single impl generic over the backend. It covers every backend that collects
+//!   bind parameters as raw bytes, i.e. Postgres, MySQL and custom backends but not
+//!   SQLite. Only the JSON text is written, no version prefix, so this requires
+//!   `#[diesel(sql_type = Json)]`, `Text` or `Binary`.
+//! - `backend = path::to::Backend, value = path::to::RawValue`: generate the impls
+//!   for a backend the crate has no feature for, instead of the backend features.
+//!   They are the Postgres impls with the backend swapped in, so `Jsonb` still
+//!   gets the version byte (`version` and the other options apply as usual). The
+//!   backend has to collect binds with diesel's `RawBytesBindCollector`, and its
+//!   raw value type needs one lifetime and an impl of `JsonbRawValue`, handing out
+//!   the bytes of the value like `PgValue` does.
+//! - `backend_cfg = "db_postgres"`: put the impls behind the given cfg predicate
+//!   of the using crate, e.g. `"feature = \"pg\""` or `"any(db_postgres, test)"`,
+//!   for model crates selecting their database with cfgs of their own.
+//!   `backend_cfg(postgres = "...", sqlite = "...")` gates the impls of each
+//!   backend feature on their own, so one build can have the Postgres impls and
+//!   another the SQLite ones. Backends not listed are not gated. The impls are only
+//!   generated for the features of this crate, which have to include every
+//!   backend a build may select, and custom cfgs need a `rustc-check-cfg` in the
+//!   build script like any other.
+//! - `transparent_value`: for a struct with a single field, e.g.
+//!   `struct Doc(serde_json::Value)`, store the field itself. The wrapper needs no
+//!   serde impls of its own, which suits dynamic-schema columns.
+//! - `value_conversions`: also implement `From<T> for serde_json::Value` and
+//!   `TryFrom<serde_json::Value> for T`, through `serde_json::to_value` and
+//!   `from_value`, for code that works on the JSON in memory as well. Like
+//!   `serde_json::json!`, the `From` impl panics for values JSON can't represent,
+//!   e.g. maps with non-string keys. With `transparent_value` the field is
+//!   converted.
+//! - `inherent_methods`: also add `to_jsonb_bytes(&self)` and
+//!   `from_jsonb_bytes(&[u8])` to the type, writing and reading the bytes of a JSONB
+//!   value exactly like the Postgres impls, version byte and options included, for
+//!   caches and test fixtures in the database's format without a connection. The
+//!   type needs a `Jsonb` sql type.
+//! - `debug`: print the generated impls to stderr while compiling, for tracking
+//!   down trait errors without `cargo expand`. Meant to be added temporarily, it
+//!   prints on every rebuild of the crate. Each block of impls starts with a doc
+//!   comment naming the type and sql type, here and in `cargo expand` output.
+//!
+//! ### Errors
+//!
+//! Values that cannot be read make `from_sql` fail with a boxed `DieselJsonError`,
+//! telling an empty value, an unsupported JSONB version, a version byte without any
+//! JSON, undecodable data, values of `strict` types that are not objects, values
+//! rejected by `validate`, values of a `schema_version` that cannot be migrated and
+//! values failing their checksum apart. `to_sql` fails with it for values
+//! rejected by `pre_serialize`, as diesel's `SerializationError`.
+//! Loading a row wraps it in diesel's `DeserializeFieldError`, so downcast twice:
+//!
+//! ```rust,ignore
+//! if let Err(diesel::result::Error::DeserializationError(e)) = &result {
+//!     let error = e
+//!         .downcast_ref::<diesel::result::DeserializeFieldError>()
+//!         .and_then(|e| e.error.downcast_ref::<DieselJsonError>());
+//!     if let Some(DieselJsonError::UnsupportedVersion(version)) = error {
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! ### Types without the derive
+//!
+//! `impl_diesel_jsonb!` generates the same impls for a type named by path, for
+//! types the derive can't be put on, e.g. because another macro defines them. The
+//! options follow the type:
+//!
+//! ```rust,ignore
+//! impl_diesel_jsonb!(other::Bar);
+//! impl_diesel_jsonb!(other::Baz, sql_type = Json, version = 2);
+//! ```
+//!
+//! Generic types take the generics and where clause of the impls first, like an
+//! impl header. The impls also require the type itself to implement the serde
+//! traits and `Debug`:
+//!
+//! ```rust,ignore
+//! impl_diesel_jsonb!(impl<T: Serialize + DeserializeOwned> for other::Pair<T>);
+//! impl_diesel_jsonb!(impl<T> for other::Tagged<T> where T: Clone, sql_type = Json);
+//! ```
+//!
+//! `diesel_jsonb_types!` does the same for a list of types with the default
+//! options:
+//!
+//! ```rust,ignore
+//! diesel_jsonb_types! { other::Foo, other::Bar, other::Baz }
+//! ```
+//!
+//! Rust's orphan rule still applies, so the type has to be defined in the crate
+//! calling the macro. Types of other crates such as `chrono::DateTime` need a local
+//! newtype, which can then use the derive. The same goes for collections: `Vec<Bar>`
+//! or `BTreeMap<String, Bar>` are not local even if `Bar` is, so the macros reject
+//! them and a newtype like `struct Bars(Vec<Bar>)` is needed. Map keys are written as
+//! JSON strings, integer keys are quoted, other keys fail to serialize.
+//!
+//! `Arc<Bar>` and `Rc<Bar>` are not local either, and the macros reject them too.
+//! No newtype is needed for them: with the impls on `Bar`, a field of either is
+//! loaded with `#[diesel(deserialize_as = Bar)]`, which converts it with `From`,
+//! and written by binding `&*value`, e.g. in an `Insertable` struct with a `&'a
+//! Bar` field. `Box<Bar>` is local, since `Box` is `#[fundamental]`, but diesel
+//! also needs `AsExpression` and `Queryable` for it, so it is handled like `Arc`.
+//!
+//! A type of another crate without serde impls is usually wrapped with serde's
+//! remote derive, `struct Local(#[serde(with = "RemoteDef")] Remote)`. The derive
+//! goes on `Local`, or any type with such a field, and reads it like any other
+//! owned type. The `#[serde(remote = "Remote")]` definition itself can't get the
+//! impls, serde doesn't implement `Serialize` and `Deserialize` for it.
+//!
+//! Impls written by hand, e.g. for values that are only serialized through a trait
+//! object, can use the functions the derive is built on. `to_jsonb` and `from_jsonb`
+//! write and read the version byte and the JSON like the derive's Postgres impls
+//! with the default options, `jsonb_payload` only checks and strips the version
+//! byte and `from_json` reads plain JSON like the impls for other columns do.
+//! `jsonb_from_pg_value` is `from_jsonb` taking diesel's `PgValue`. For streaming
+//! protocols that frame values as a big-endian `u32` length followed by the JSONB
+//! bytes, `read_length_prefixed` reads the first record of a buffer and returns it
+//! with the bytes it took, a buffer that ends within a record is an error instead
+//! of a partial read. The derive calls the others too, so the generated code stays
+//! small:
+//!
+//! ```rust,ignore
+//! impl ToSql<Jsonb, Pg> for Plugin {
+//!     fn to_sql<'b>(&'b self, out: &mut serialize::Output<'b, '_, Pg>) -> serialize::Result {
+//!         diesel_json_derive::to_jsonb(&self.document, out)
+//!     }
+//! }
+//!
+//! impl FromSql<Jsonb, Pg> for Plugin {
+//!     fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+//!         diesel_json_derive::jsonb_from_pg_value(bytes)
+//!     }
+//! }
+//! ```
+//!
+//! ### Borrowed data
+//!
+//! Types with `#[serde(borrow)]` fields cannot be read: `FromSql::from_sql` gets
+//! the raw value for a lifetime that ends with the call, so the value has to be
+//! `DeserializeOwned`. Fields like `Cow<'a, str>` without `#[serde(borrow)]` are
+//! fine, they are always deserialized into owned data.
+//!
+//! The derive rejects reference fields like `&'a str` for the same reason. Types
+//! that are only written, e.g. a view of data that lives elsewhere, can borrow
+//! with `#[diesel_json(serialize_only)]`, any number of lifetimes and bounds
+//! between them are carried over to the impl. Types that can only be deserialized
+//! with `'static` lifetimes are read with `#[diesel_json(owned_read)]`.
+//!
+//! Generic types are bounded as a whole, e.g. `Holder<T>: Serialize` rather than
+//! `T: Serialize`, leaving the bounds on the parameters to serde's derive. So a
+//! `PhantomData<T>` marker or a field of an associated type like `T::Record` needs
+//! nothing of `T` that serde doesn't.
+//!
+//! ### Compression
+//!
+//! JSON and JSONB values can't be compressed by the derive: Postgres parses them
+//! on insert, so anything but JSON text (and the JSONB version byte) is rejected.
+//! Large values are compressed by Postgres itself when they are moved out of line
+//! (TOAST), `ALTER TABLE foo ALTER COLUMN bar SET COMPRESSION lz4` picks a faster
+//! algorithm than the default. `compress = "gzip"` stores gzipped JSON in a
+//! `bytea` column instead, which loses the JSON operators.
+//!
 //! ### diesel_json_derive vs diesel_json
 //!
 //! The [diesel_json](https://crates.io/crates/diesel_json) crate solves the
 //! same problem but uses a wrapper type for it. This has the disadvantage that
 //! this type needs to be used when matching for example. This crate does not
 //! have this disadvantage.
+//!
+//! Both write the same bytes, so values written by diesel_json's `Json<T>`
+//! are read by the derive and the other way around. The same goes for sqlx's
+//! `sqlx::types::Json<T>`, which binds the version byte and the compact JSON as
+//! well and reads any JSONB value of version 1, so a table can be written by both
+//! crates while moving from one to the other.
 
+mod buffer;
+mod canonical;
+mod checksum;
+#[cfg(feature = "compression")]
+mod compress;
+mod error;
+mod intern;
+mod jsonb;
+mod known_fields;
+#[cfg(feature = "tracing")]
+mod log;
+#[cfg(feature = "postgres")]
+mod merge;
+mod raw_value;
+#[cfg(feature = "sqlite")]
+mod sqlite_jsonb;
+mod tagged;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod versioned;
 
+pub use canonical::to_canonical_writer;
+pub use checksum::{crc32, CHECKSUM_MARKER};
+pub use diesel_json_derive_impl::{
+    diesel_jsonb, diesel_jsonb_model, diesel_jsonb_types, impl_diesel_jsonb, DieselJsonb,
+};
+pub use error::DieselJsonError;
+pub use intern::{with_interner, Interned};
+#[cfg(feature = "postgres")]
+pub use jsonb::jsonb_from_pg_value;
+pub use jsonb::{
+    from_json, from_jsonb, jsonb_payload, read_length_prefixed, to_jsonb, JSONB_VERSION,
+};
+#[cfg(feature = "postgres")]
+pub use merge::jsonb_merge;
+pub use raw_value::JsonbRawValue;
+pub use tagged::TaggedFormat;
 
-use heck::ToSnakeCase;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Ident};
-
-#[proc_macro_derive(DieselJsonb)]
-pub fn diesel_jsonb_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
-
-    let type_name = input.ident;
-    let mod_name = format!("{}_diesel_jsonb", type_name.to_string().to_snake_case());
-    let mod_name = Ident::new(&mod_name, type_name.span());
-
-    (quote! {
-        mod #mod_name {
-            use super::#type_name;
-
-            use diesel::deserialize::{self, FromSql};
-            use diesel::pg::{Pg, PgValue};
-            use diesel::serialize::{self, ToSql};
-            use diesel::sql_types::*;
-            use std::io::Write;
-
-            impl ToSql<Jsonb, Pg> for #type_name {
-                fn to_sql<'b>(&'b self, out: &mut serialize::Output<'b, '_, Pg>) -> serialize::Result {
-                    out.write_all(&[1])?;
-                    serde_json::to_writer(out, &self)?;
-                    Ok(serialize::IsNull::No)
-                }
-            }
-
-            impl FromSql<Jsonb, Pg> for #type_name {
-                fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
-                    let bytes = bytes.as_bytes();
-                    if bytes[0] != 1 {
-                        return Err("Unsupported JSONB encoding version".into());
-                    }
-                    serde_json::from_slice(&bytes[1..]).map_err(|_| "Invalid Json".into())
-                }
-            }
-        }
-    }).into()
+/// What the generated code calls, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::buffer::with_scratch_buffer;
+    pub use crate::checksum::{checksum_payload, with_checksum};
+    #[cfg(feature = "compression")]
+    pub use crate::compress::{gunzip, gunzip_payload, gzip_json};
+    pub use crate::error::with_context;
+    pub use crate::jsonb::{
+        check_depth, check_utf8, discriminant_payload, from_lenient_json, is_null_sentinel,
+        lacks_version_byte, to_sorted_value, validate_json,
+    };
+    pub use crate::known_fields::from_json_allowing_unknown_fields;
+    #[cfg(feature = "tracing")]
+    pub use crate::log::log_read_error;
+    #[cfg(feature = "sqlite")]
+    pub use crate::sqlite_jsonb::{sqlite_jsonb_from_json, sqlite_jsonb_to_json};
+    pub use crate::tagged::tagged_payload;
+    pub use crate::versioned::{from_versioned_json, Migrate, Versioned};
 }
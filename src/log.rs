@@ -0,0 +1,31 @@
+use std::error::Error;
+use std::fmt::Write;
+
+/// How many bytes of a value [`log_read_error`] shows.
+const PREVIEW_LEN: usize = 32;
+
+/// Logs a value `from_sql` failed to read, with the `tracing` feature: a
+/// warning with the type name, the error and the first bytes of the value in
+/// hex. The derive's impls call it before returning the error, e.g. to find
+/// the rows `default_on_error` reads as the default.
+pub fn log_read_error(type_name: &str, bytes: &[u8], error: &(dyn Error + Send + Sync)) {
+    tracing::warn!(
+        type_name,
+        len = bytes.len(),
+        bytes = %hex_preview(bytes),
+        "cannot read {type_name}: {error}"
+    );
+}
+
+/// The first [`PREVIEW_LEN`] bytes in hex, followed by `...` if there are
+/// more.
+fn hex_preview(bytes: &[u8]) -> String {
+    let mut preview = String::with_capacity(PREVIEW_LEN * 2 + 3);
+    for byte in bytes.iter().take(PREVIEW_LEN) {
+        let _ = write!(preview, "{byte:02x}");
+    }
+    if bytes.len() > PREVIEW_LEN {
+        preview.push_str("...");
+    }
+    preview
+}
@@ -0,0 +1,23 @@
+use diesel::dsl::Concat;
+use diesel::expression::{AsExpression, Expression};
+use diesel::expression_methods::PgJsonbExpressionMethods;
+use diesel::sql_types::Jsonb;
+
+/// `column || value`, Postgres merging the keys of the object `value` binds
+/// to into the JSONB `column`, for updating some of the fields of a stored
+/// value without reading it first:
+/// `update(foo.find(1)).set(bar.eq(jsonb_merge(bar, &patch)))`. Keys of
+/// `value` replace those of the column, nested objects are replaced as a
+/// whole, not merged. `value` is any `AsExpression<Jsonb>`, e.g. a type
+/// deriving `DieselJsonb` that has just the changed fields; one that
+/// serializes to an array or a scalar is concatenated into an array instead.
+///
+/// A `NULL` column stays `NULL`, which is why the column has to be `Jsonb`
+/// rather than `Nullable<Jsonb>`. Merge into `coalesce(bar, '{}')` for those.
+pub fn jsonb_merge<C, T>(column: C, value: T) -> Concat<C, T>
+where
+    C: Expression<SqlType = Jsonb>,
+    T: AsExpression<Jsonb>,
+{
+    column.concat(value)
+}
@@ -0,0 +1,30 @@
+#[cfg(feature = "mysql")]
+use diesel::mysql::MysqlValue;
+#[cfg(feature = "postgres")]
+use diesel::pg::PgValue;
+
+/// The raw value of a backend that hands out the bytes of a value as they
+/// are, which the derive's `from_sql` reads JSON from. Implementing it is all
+/// a backend needs for `#[diesel_json(backend = ..., value = ...)]`.
+///
+/// SQLite's value only gives out its bytes mutably, its impls read through
+/// `FromSql<Binary, Sqlite>` instead.
+pub trait JsonbRawValue {
+    /// The bytes of the value, for `Jsonb` the format version byte followed by
+    /// the JSON.
+    fn jsonb_bytes(&self) -> &[u8];
+}
+
+#[cfg(feature = "postgres")]
+impl JsonbRawValue for PgValue<'_> {
+    fn jsonb_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl JsonbRawValue for MysqlValue<'_> {
+    fn jsonb_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
@@ -0,0 +1,405 @@
+use crate::DieselJsonError;
+
+// The element types of SQLite's JSONB, the low four bits of the first byte
+// of an element's header, see https://sqlite.org/jsonb.html
+const NULL: u8 = 0x0;
+const TRUE: u8 = 0x1;
+const FALSE: u8 = 0x2;
+const INT: u8 = 0x3;
+const INT5: u8 = 0x4;
+const FLOAT: u8 = 0x5;
+const FLOAT5: u8 = 0x6;
+const TEXT: u8 = 0x7;
+const TEXTJ: u8 = 0x8;
+const TEXT5: u8 = 0x9;
+const TEXTRAW: u8 = 0xA;
+const ARRAY: u8 = 0xB;
+const OBJECT: u8 = 0xC;
+
+/// How deep arrays and objects may nest, SQLite's own limit.
+const MAX_DEPTH: usize = 1000;
+
+/// Encodes the JSON text `json` in SQLite's binary JSONB format, what the
+/// derive's `ToSql<Jsonb, Sqlite>` stores and SQLite's `jsonb()` returns.
+/// Whitespace is dropped, strings keep their escapes and numbers their text.
+/// Fails with [`DieselJsonError::InvalidOutput`] for bytes that aren't JSON.
+pub fn sqlite_jsonb_from_json(
+    json: &[u8],
+    type_name: &'static str,
+) -> Result<Vec<u8>, DieselJsonError> {
+    let mut encoder = Encoder {
+        json,
+        pos: 0,
+        out: Vec::with_capacity(json.len()),
+    };
+    encoder
+        .value(0)
+        .and_then(|()| {
+            encoder.skip_whitespace();
+            if encoder.pos == json.len() {
+                Ok(())
+            } else {
+                Err(encoder.error("trailing characters"))
+            }
+        })
+        .map_err(|message| DieselJsonError::InvalidOutput {
+            type_name,
+            source: message.into(),
+        })?;
+    Ok(encoder.out)
+}
+
+/// Decodes SQLite's binary JSONB `bytes` to JSON text, what the derive's
+/// `FromSql<Jsonb, Sqlite>` parses. The JSON5 SQLite accepts in `jsonb()`,
+/// e.g. hexadecimal integers or `Infinity`, is turned into JSON the way
+/// SQLite's `json()` does. Empty bytes stay empty. Fails with
+/// [`DieselJsonError::Deserialize`] for bytes that aren't JSONB, e.g. JSON
+/// text stored in a blob.
+pub fn sqlite_jsonb_to_json(
+    bytes: &[u8],
+    type_name: &'static str,
+) -> Result<Vec<u8>, DieselJsonError> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 2);
+    if !bytes.is_empty() {
+        let decoded = decode(bytes, &mut out, 0).and_then(|size| {
+            if size == bytes.len() {
+                Ok(())
+            } else {
+                Err(format!("{} bytes after the value", bytes.len() - size))
+            }
+        });
+        decoded.map_err(|message| DieselJsonError::Deserialize {
+            type_name,
+            format: "SQLite JSONB",
+            source: message.into(),
+        })?;
+    }
+    Ok(out)
+}
+
+struct Encoder<'a> {
+    json: &'a [u8],
+    pos: usize,
+    out: Vec<u8>,
+}
+
+impl Encoder<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{message} at byte {}", self.pos)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.json.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips the whitespace and the byte after it if it is `byte`.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.json.get(self.pos) == Some(&byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.json.get(self.pos) {
+            Some(b'{') => self.container(OBJECT, b'}', depth),
+            Some(b'[') => self.container(ARRAY, b']', depth),
+            Some(b'"') => self.string(),
+            Some(b't') => self.literal(b"true", TRUE),
+            Some(b'f') => self.literal(b"false", FALSE),
+            Some(b'n') => self.literal(b"null", NULL),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("EOF while parsing a value")),
+        }
+    }
+
+    fn literal(&mut self, text: &[u8], element_type: u8) -> Result<(), String> {
+        if !self.json[self.pos..].starts_with(text) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += text.len();
+        self.out.push(element_type);
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.json.get(self.pos) {
+            self.pos += 1;
+        }
+        let json = self.json;
+        let text = &json[start..self.pos];
+        let element_type = if text.iter().all(|&b| b == b'-' || b.is_ascii_digit()) {
+            INT
+        } else {
+            FLOAT
+        };
+        self.element(element_type, text);
+        Ok(())
+    }
+
+    /// A string as `TEXT`, or as `TEXTJ` if it has escapes, which are kept.
+    fn string(&mut self) -> Result<(), String> {
+        let start = self.pos + 1;
+        let mut end = start;
+        let mut escaped = false;
+        loop {
+            match self.json.get(end) {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    escaped = true;
+                    end += 2;
+                }
+                Some(_) => end += 1,
+                None => {
+                    self.pos = self.json.len();
+                    return Err(self.error("EOF while parsing a string"));
+                }
+            }
+        }
+        self.pos = end + 1;
+        let element_type = if escaped { TEXTJ } else { TEXT };
+        let json = self.json;
+        self.element(element_type, &json[start..end]);
+        Ok(())
+    }
+
+    /// An object or array, whose header is written once the size of the
+    /// elements in it is known.
+    fn container(&mut self, element_type: u8, close: u8, depth: usize) -> Result<(), String> {
+        if depth == MAX_DEPTH {
+            return Err(self.error("nested too deep"));
+        }
+        self.pos += 1;
+        let start = self.out.len();
+        if !self.eat(close) {
+            loop {
+                if element_type == OBJECT {
+                    self.skip_whitespace();
+                    if self.json.get(self.pos) != Some(&b'"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.string()?;
+                    if !self.eat(b':') {
+                        return Err(self.error("expected `:`"));
+                    }
+                }
+                self.value(depth + 1)?;
+                if self.eat(close) {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return Err(self.error("expected `,`"));
+                }
+            }
+        }
+        let (header, header_size) = header(element_type, self.out.len() - start);
+        self.out
+            .splice(start..start, header[..header_size].iter().copied());
+        Ok(())
+    }
+
+    fn element(&mut self, element_type: u8, payload: &[u8]) {
+        let (header, header_size) = header(element_type, payload.len());
+        self.out.extend_from_slice(&header[..header_size]);
+        self.out.extend_from_slice(payload);
+    }
+}
+
+/// The header of an element of `element_type` with a payload of `size`
+/// bytes, in as few bytes as it fits.
+fn header(element_type: u8, size: usize) -> ([u8; 9], usize) {
+    let mut header = [0; 9];
+    let (code, size_bytes) = match size {
+        0..=11 => (size as u8, 0),
+        0x0C..=0xFF => (12, 1),
+        0x100..=0xFFFF => (13, 2),
+        _ if size <= u32::MAX as usize => (14, 4),
+        _ => (15, 8),
+    };
+    header[0] = code << 4 | element_type;
+    let size = (size as u64).to_be_bytes();
+    header[1..1 + size_bytes].copy_from_slice(&size[8 - size_bytes..]);
+    (header, 1 + size_bytes)
+}
+
+/// Writes the JSON of the element at the start of `bytes` to `out`, evaluating
+/// to the size of the element.
+fn decode(bytes: &[u8], out: &mut Vec<u8>, depth: usize) -> Result<usize, String> {
+    let first = *bytes.first().ok_or("expected an element")?;
+    let (header_size, size) = match first >> 4 {
+        code @ 0..=11 => (1, code as usize),
+        code => {
+            let size_bytes = 1 << (code - 12);
+            let size = bytes
+                .get(1..1 + size_bytes)
+                .ok_or("header ends early")?
+                .iter()
+                .fold(0u64, |size, &b| size << 8 | u64::from(b));
+            (
+                1 + size_bytes,
+                usize::try_from(size).map_err(|_| "element too large")?,
+            )
+        }
+    };
+    let payload = bytes
+        .get(header_size..)
+        .and_then(|rest| rest.get(..size))
+        .ok_or_else(|| format!("element of {size} bytes ends early"))?;
+    match first & 0x0F {
+        NULL => out.extend_from_slice(b"null"),
+        TRUE => out.extend_from_slice(b"true"),
+        FALSE => out.extend_from_slice(b"false"),
+        INT | FLOAT => out.extend_from_slice(payload),
+        INT5 => int5(payload, out)?,
+        FLOAT5 => float5(payload, out),
+        TEXT | TEXTJ => {
+            out.push(b'"');
+            out.extend_from_slice(payload);
+            out.push(b'"');
+        }
+        TEXT5 => text5(payload, out)?,
+        TEXTRAW => {
+            let text = std::str::from_utf8(payload).map_err(|e| e.to_string())?;
+            serde_json::to_writer(&mut *out, text).map_err(|e| e.to_string())?;
+        }
+        element_type @ (ARRAY | OBJECT) => {
+            if depth == MAX_DEPTH {
+                return Err("nested too deep".to_string());
+            }
+            let (open, close) = match element_type {
+                ARRAY => (b'[', b']'),
+                _ => (b'{', b'}'),
+            };
+            out.push(open);
+            let mut pos = 0;
+            let mut count = 0;
+            while pos < payload.len() {
+                let separator = match (element_type, count % 2) {
+                    (OBJECT, 1) => b':',
+                    _ => b',',
+                };
+                if count > 0 {
+                    out.push(separator);
+                }
+                if element_type == OBJECT && count % 2 == 0 {
+                    let key_type = payload[pos] & 0x0F;
+                    if !(TEXT..=TEXTRAW).contains(&key_type) {
+                        return Err(format!("key of element type {key_type}"));
+                    }
+                }
+                pos += decode(&payload[pos..], out, depth + 1)?;
+                count += 1;
+            }
+            if element_type == OBJECT && count % 2 == 1 {
+                return Err("key without a value".to_string());
+            }
+            out.push(close);
+        }
+        element_type => return Err(format!("reserved element type {element_type}")),
+    }
+    Ok(header_size + size)
+}
+
+/// A JSON5 integer, which may be hexadecimal or have a `+` sign, in decimal.
+fn int5(payload: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    let text = std::str::from_utf8(payload).map_err(|e| e.to_string())?;
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .map_err(|_| format!("invalid integer {text:?}"))?;
+    if negative && value != 0 {
+        out.push(b'-');
+    }
+    out.extend_from_slice(value.to_string().as_bytes());
+    Ok(())
+}
+
+/// A JSON5 float as JSON: `Infinity` is `9e999` and `NaN` is `null`, like
+/// SQLite's `json()` has them, a `+` sign is dropped and a leading or
+/// trailing decimal point gets its zero.
+fn float5(payload: &[u8], out: &mut Vec<u8>) {
+    let (sign, number) = match payload.first() {
+        Some(b'-') => (&b"-"[..], &payload[1..]),
+        Some(b'+') => (&b""[..], &payload[1..]),
+        _ => (&b""[..], payload),
+    };
+    if number == b"Infinity" || number == b"Inf" {
+        out.extend_from_slice(sign);
+        out.extend_from_slice(b"9e999");
+        return;
+    }
+    if number == b"NaN" {
+        out.extend_from_slice(b"null");
+        return;
+    }
+    out.extend_from_slice(sign);
+    if number.first() == Some(&b'.') {
+        out.push(b'0');
+    }
+    for (i, &b) in number.iter().enumerate() {
+        out.push(b);
+        let next = number.get(i + 1);
+        if b == b'.' && !matches!(next, Some(b'0'..=b'9')) {
+            out.push(b'0');
+        }
+    }
+}
+
+/// A string with JSON5 escapes and characters JSON needs escaped, e.g. a
+/// `"` of a single-quoted string, as a JSON string.
+fn text5(payload: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    let text = std::str::from_utf8(payload).map_err(|e| e.to_string())?;
+    out.push(b'"');
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next().ok_or("string ends within an escape")? {
+                'x' => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    out.extend_from_slice(format!("\\u00{hex}").as_bytes());
+                }
+                '\'' => out.push(b'\''),
+                'v' => out.extend_from_slice(b"\\u000b"),
+                '0' => out.extend_from_slice(b"\\u0000"),
+                // line continuations
+                '\n' | '\u{2028}' | '\u{2029}' => {}
+                '\r' => {
+                    if chars.as_str().starts_with('\n') {
+                        chars.next();
+                    }
+                }
+                escaped => {
+                    out.push(b'\\');
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+            },
+            '"' => out.extend_from_slice(b"\\\""),
+            c if u32::from(c) < 0x20 => {
+                out.extend_from_slice(format!("\\u{:04x}", u32::from(c)).as_bytes());
+            }
+            c => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    out.push(b'"');
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use crate::DieselJsonError;
+
+/// The formats a value written with `#[diesel_json(tagged_format)]` can be
+/// in, told apart by the byte the value starts with. The tag of JSON is the
+/// JSONB version byte, so JSONB values copied into a `bytea` column read as
+/// they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TaggedFormat {
+    /// JSON text, tag 1.
+    Json,
+    /// Gzipped JSON text, tag 2, written with `compress = "gzip"` and read
+    /// with the `compression` feature.
+    GzipJson,
+    /// MessagePack, tag 3.
+    MessagePack,
+    /// CBOR, tag 4.
+    Cbor,
+}
+
+impl TaggedFormat {
+    /// The byte in front of a value of this format.
+    pub const fn tag(self) -> u8 {
+        match self {
+            TaggedFormat::Json => 1,
+            TaggedFormat::GzipJson => 2,
+            TaggedFormat::MessagePack => 3,
+            TaggedFormat::Cbor => 4,
+        }
+    }
+
+    /// The format with the tag `tag`, if there is one.
+    pub const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(TaggedFormat::Json),
+            2 => Some(TaggedFormat::GzipJson),
+            3 => Some(TaggedFormat::MessagePack),
+            4 => Some(TaggedFormat::Cbor),
+            _ => None,
+        }
+    }
+
+    /// The name used in error messages.
+    pub const fn name(self) -> &'static str {
+        match self {
+            TaggedFormat::Json => "Json",
+            TaggedFormat::GzipJson => "gzipped JSON",
+            TaggedFormat::MessagePack => "MessagePack",
+            TaggedFormat::Cbor => "CBOR",
+        }
+    }
+}
+
+/// The format of a `tagged_format` value and the bytes after its tag.
+pub fn tagged_payload<'a>(
+    bytes: &'a [u8],
+    type_name: &'static str,
+) -> Result<(TaggedFormat, &'a [u8]), DieselJsonError> {
+    let Some((&tag, payload)) = bytes.split_first() else {
+        return Err(DieselJsonError::UnknownFormat {
+            type_name,
+            tag: None,
+        });
+    };
+    match TaggedFormat::from_tag(tag) {
+        Some(format) => Ok((format, payload)),
+        None => Err(DieselJsonError::UnknownFormat {
+            type_name,
+            tag: Some(tag),
+        }),
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers for testing `from_sql` and `to_sql` without a database, with the
+//! `test-util` feature.
+
+use std::num::NonZeroU32;
+
+use diesel::pg::{Pg, PgMetadataLookup, PgTypeMetadata, PgValue};
+use diesel::query_builder::bind_collector::RawBytesBindCollector;
+use diesel::query_builder::BindCollector;
+use diesel::serialize::ToSql;
+use diesel::sql_types::HasSqlType;
+use diesel::QueryResult;
+use serde::Serialize;
+
+use crate::JSONB_VERSION;
+
+static JSON_OID: NonZeroU32 = NonZeroU32::new(114).unwrap();
+static JSONB_OID: NonZeroU32 = NonZeroU32::new(3802).unwrap();
+
+/// The bytes the derive's `ToSql<Jsonb, Pg>` writes for `value` with the
+/// default options, the version byte followed by the JSON.
+///
+/// # Panics
+///
+/// If `value` can't be serialized to JSON, e.g. a map with non-string keys.
+pub fn jsonb_bytes<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![JSONB_VERSION];
+    serde_json::to_writer(&mut bytes, value).expect("the value serializes to JSON");
+    bytes
+}
+
+/// `bytes` as the value of a `JSONB` column, for calling `from_sql`, e.g.
+/// `Foo::from_sql(jsonb_value(&jsonb_bytes(&foo)))`.
+pub fn jsonb_value(bytes: &[u8]) -> PgValue<'_> {
+    PgValue::new(bytes, &JSONB_OID)
+}
+
+/// `bytes` as the value of a `JSON` column, the JSON text without a version
+/// byte.
+pub fn json_value(bytes: &[u8]) -> PgValue<'_> {
+    PgValue::new(bytes, &JSON_OID)
+}
+
+/// The bytes `value` is bound as for `ST`, collected like diesel does for a
+/// query, e.g. `to_sql_bytes::<Jsonb, _>(&foo)?` for the version byte and the
+/// JSON.
+///
+/// # Panics
+///
+/// If `value` is bound as `NULL`, or binding it looks up a type's oid, see
+/// [`NoLookup`].
+pub fn to_sql_bytes<ST, T>(value: &T) -> QueryResult<Vec<u8>>
+where
+    Pg: HasSqlType<ST>,
+    T: ToSql<ST, Pg> + ?Sized,
+{
+    let mut collector = RawBytesBindCollector::<Pg>::new();
+    collector.push_bound_value::<ST, T>(value, &mut NoLookup)?;
+    Ok(collector.binds.pop().flatten().expect("a non-null bind"))
+}
+
+/// The metadata lookup of `to_sql_bytes`, for collecting the binds of whole
+/// queries without a connection. `Json`, `Jsonb`, `Text` and `Binary` have
+/// fixed oids, looking up any other type, e.g. a custom enum type, panics.
+#[derive(Debug, Default)]
+pub struct NoLookup;
+
+impl PgMetadataLookup for NoLookup {
+    fn lookup_type(&mut self, type_name: &str, _schema: Option<&str>) -> PgTypeMetadata {
+        unreachable!("looked up type {type_name}")
+    }
+}
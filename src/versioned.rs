@@ -0,0 +1,94 @@
+use diesel::deserialize;
+use serde::de::DeserializeOwned;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::jsonb::short_type_name;
+use crate::DieselJsonError;
+
+/// A value together with the version of its shape, written as
+/// `{"schema_version":N,"data":...}` by types with
+/// `#[diesel_json(schema_version = N)]`. Unlike the JSONB format version byte
+/// this is part of the JSON, Postgres would not accept anything else in front
+/// of it.
+pub struct Versioned<'a, T: ?Sized> {
+    schema_version: u8,
+    data: &'a T,
+}
+
+impl<'a, T: ?Sized> Versioned<'a, T> {
+    /// `data` of the shape `schema_version`.
+    pub fn new(schema_version: u8, data: &'a T) -> Self {
+        Self {
+            schema_version,
+            data,
+        }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for Versioned<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Versioned", 2)?;
+        state.serialize_field("schema_version", &self.schema_version)?;
+        state.serialize_field("data", self.data)?;
+        state.end()
+    }
+}
+
+/// Reads a value of an older schema version, given that version and the
+/// JSON of the value.
+pub type Migrate<T> = fn(u8, Value) -> Result<T, String>;
+
+/// Reads JSON written with [`Versioned`]. Values of the current
+/// `schema_version` are deserialized, older ones are handed to `migrate` with
+/// their version. JSON without the envelope, e.g. written before the type had
+/// a schema version, counts as version 0.
+pub fn from_versioned_json<T>(
+    bytes: &[u8],
+    schema_version: u8,
+    migrate: Option<Migrate<T>>,
+) -> deserialize::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let invalid_json = |e: serde_json::Error| DieselJsonError::Deserialize {
+        type_name: short_type_name::<T>(),
+        format: "Json",
+        source: e.into(),
+    };
+    let value: Value = serde_json::from_slice(bytes).map_err(invalid_json)?;
+    let (version, data) = split_envelope(value);
+    let cannot_migrate = |message: String| DieselJsonError::Migrate {
+        type_name: short_type_name::<T>(),
+        schema_version: version,
+        message,
+    };
+    if version == schema_version {
+        Ok(serde_json::from_value(data).map_err(invalid_json)?)
+    } else if version > schema_version {
+        Err(cannot_migrate(format!("newer than schema version {schema_version}")).into())
+    } else if let Some(migrate) = migrate {
+        Ok(migrate(version, data).map_err(cannot_migrate)?)
+    } else {
+        Err(cannot_migrate("no migrate function".to_string()).into())
+    }
+}
+
+/// The schema version and the data of a [`Versioned`] value, or version 0 and
+/// the whole value for anything else.
+fn split_envelope(value: Value) -> (u8, Value) {
+    match value {
+        Value::Object(mut map) if map.len() == 2 && map.contains_key("data") => {
+            let version = map
+                .get("schema_version")
+                .and_then(Value::as_u64)
+                .and_then(|version| u8::try_from(version).ok());
+            match version {
+                Some(version) => (version, map.remove("data").expect("checked above")),
+                None => (0, Value::Object(map)),
+            }
+        }
+        value => (0, value),
+    }
+}